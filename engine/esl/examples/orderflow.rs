@@ -135,17 +135,12 @@ async fn main() -> Result<()> {
         .add_system_without_plugin(plot_tracks::new::<MaxBalance>(), End::new())
         .add_system_without_plugin(plot_tracks::new::<Drawdown>(), End::new())
         .add_system_without_plugin(print_metric::new::<CagrOverMeanDd>(), End::new())
-        .add_resource(MetricsPtr(metrics.as_mut_ptr()))
         .add_resource(TracksPtr(tracks.as_mut_ptr()))
         // Used to compute elapsed time for CAGR
         .add_resource(StartTimestampNs(orderflow[0].timestamp_ns as u64))
-        .add_resource(AccountsPerThread(1))
-        .add_resource(ThreadsPerDevice(1))
-        .add_resource(ThreadId(0))
-        .add_resource(NSamples(input_len))
-        .add_resource(RiskFreeRate(0.))
-        .add_resource(TradingDaysPerYear(252.))
+        .add_plugin(MetricsPlugin::new(metrics.as_mut_ptr(), input_len))
         .add_plugin(CorePlugin {
+            loop_start_bound: 0,
             loop_end_bound_excluded: input_len,
         })
         .add_plugin(OrderflowBacktestPlugin {
@@ -156,6 +151,8 @@ async fn main() -> Result<()> {
                 starting_balance: 1.0,
                 slippage: types::Slippage::Absolute(0.25),
                 fee: types::Fee::RelativeToVolume(0.),
+                max_pyramiding: 1,
+                margin: Default::default(),
                 inputs_marker: PhantomData::<orderflow::OrderflowInput>,
             },
         })
@@ -95,27 +95,29 @@ async fn main() -> Result<()> {
         .add_metric::<SimulationEnding, UpdateRelay, OnPositionClosed, Count<LossRel>>()
         .add_metric::<SimulationEnding, UpdateRelay, OnPositionClosed, Mean<LossRel>>()
         .finish()
-        .add_resource(MetricsPtr(metrics.as_mut_ptr()))
         .add_resource(TracksPtr(tracks.as_mut_ptr()))
-        .add_resource(AccountsPerThread(1))
-        .add_resource(ThreadsPerDevice(1))
-        .add_resource(ThreadId(0))
-        // How many samples will be generated from this backtest. Not known in advance, so it's set
-        // to max possible value.
-        .add_resource(NSamples(input_len))
-        .add_resource(RiskFreeRate(0.))
-        .add_resource(TradingDaysPerYear(252.))
-        .add_plugin(CorePlugin {
-            loop_end_bound_excluded: input_len,
-        })
-        .add_plugin(HlcvBacktestPlugin {
-            timeframe_s,
-            backtest_plugin: BacktestPlugin {
-                inputs: unsafe { Series::<hlcv::HlcvInputNest>::new(hlcvs.as_ptr() as *const u8) },
-                starting_balance: 1.0,
-                slippage: types::Slippage::Relative(0.),
-                fee: types::Fee::RelativeToVolume(0.00075 * 2.),
-                inputs_marker: PhantomData::<hlcv::HlcvInput>,
+        .add_plugins(DefaultBacktestPlugins {
+            core: CorePlugin {
+                loop_start_bound: 0,
+                loop_end_bound_excluded: input_len,
+            },
+            // How many samples will be generated from this backtest. Not known in advance, so it's
+            // set to max possible value.
+            metrics: MetricsPlugin::new(metrics.as_mut_ptr(), input_len),
+            backtest: HlcvBacktestPlugin {
+                timeframe_s,
+                funding_interval_s: 0,
+                backtest_plugin: BacktestPlugin {
+                    inputs: unsafe {
+                        Series::<hlcv::HlcvInputNest>::new(hlcvs.as_ptr() as *const u8)
+                    },
+                    starting_balance: 1.0,
+                    slippage: types::Slippage::Relative(0.),
+                    fee: types::Fee::RelativeToVolume(0.00075 * 2.),
+                    max_pyramiding: 1,
+                    margin: Default::default(),
+                    inputs_marker: PhantomData::<hlcv::HlcvInput>,
+                },
             },
         })
         .add_plugin(ComputeIndicatorPlugin::<
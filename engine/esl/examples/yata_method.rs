@@ -8,7 +8,7 @@ use core::marker::PhantomData;
 use esl::{
     block_relays::UpdateRelay,
     simulation_relays::SimulationEnding,
-    stages::{PostTrade0, Signal},
+    stages::{PostTrade, Signal},
     ta::yata::methods::*,
     *,
 };
@@ -64,21 +64,17 @@ async fn main() -> Result<()> {
         ));
     let schedule = esl::stages::BacktestSchedule::builder();
     let builder = EcsBuilderStruct::new::<_, 6>(config, schedule)
-        // Resources are like singletons in ECS. These ones may be needed. If a resource isn't
-        // present then the system will panic. If an entity doesn't have a component then a system
-        // will just skip it.
-        .add_resource(MetricsPtr(metrics.as_mut_ptr()))
-        .add_resource(AccountsPerThread(1))
-        .add_resource(ThreadsPerDevice(1))
-        .add_resource(ThreadId(0))
-        .add_resource(NSamples(input_len))
-        .add_resource(RiskFreeRate(0.))
-        .add_resource(TradingDaysPerYear(252.))
+        // Plugins add resources (singletons in ECS) and systems of a backtest. If a resource
+        // isn't present then the system will panic. If an entity doesn't have a component then a
+        // system will just skip it.
+        .add_plugin(MetricsPlugin::new(metrics.as_mut_ptr(), input_len))
         .add_plugin(CorePlugin {
+            loop_start_bound: 0,
             loop_end_bound_excluded: input_len,
         })
         .add_plugin_multiple(HlcvBacktestPlugin {
             timeframe_s,
+            funding_interval_s: 0,
             backtest_plugin: BacktestPlugin {
                 inputs: unsafe {
                     Series::<hlcv::HlcvInputNest>::new(hlcvs.as_ptr() as *const u8)
@@ -87,6 +83,8 @@ async fn main() -> Result<()> {
                 starting_balance: 1.0,
                 slippage: types::Slippage::Relative(0.),
                 fee: types::Fee::RelativeToVolume(0.00075 * 2.),
+                max_pyramiding: 1,
+                margin: Default::default(),
                 inputs_marker: PhantomData::<hlcv::HlcvInput>,
             },
         });
@@ -94,8 +92,8 @@ async fn main() -> Result<()> {
         .add_metric::<SimulationEnding, UpdateRelay, OnPositionClosed, Balance>()
         .finish()
         .add_system_to_stage(engine_strategies::sma::new(), Signal::new())
-        // `PostTrade0` stage executes inisde a loop for every candle.
-        .add_system_to_stage(print_sma::new(), PostTrade0::new())
+        // `PostTrade` stage executes inisde a loop for every candle.
+        .add_system_to_stage(print_sma::new(), PostTrade::new())
         .build();
     ecs.run();
     let elapsed_ns = now.elapsed().as_nanos();
@@ -2,22 +2,28 @@ use core::marker::PhantomData;
 
 use ergnomics::some_loop;
 use inception::*;
+use num_traits::Float;
 
 use crate::{
     loop_index::LoopIndexResource,
+    orders::OrdersComponent,
     stages::{BacktestInit, Input0},
-    types::{Direction, Fee, OrderId, PositionAction, PositionSize, Slippage},
+    types::{is_buy, Direction, Fee, Margin, OrderId, PositionAction, PositionSize, Slippage},
     *,
 };
 
 pub mod hlcv_backtest;
 pub mod orderflow_backtest;
+pub mod portfolio_backtest;
 
 pub struct BacktestPlugin<I, S> {
     pub inputs: Series<S>,
     pub starting_balance: f32,
     pub slippage: Slippage,
     pub fee: Fee,
+    /// Maximum number of entries in the same direction. Set this to 1 to disable pyramiding.
+    pub max_pyramiding: u32,
+    pub margin: Margin,
     pub inputs_marker: PhantomData<I>,
 }
 
@@ -40,11 +46,14 @@ impl<I: Inputs + 'static, S: Nest + 'static> Plugin for BacktestPlugin<I, S> {
             .extend_entities(MetricComponent::<Position>::default())
             .extend_entities(MetricComponent::<NOrders>::default())
             .extend_entities(MetricComponent::<EntryPrice>::default())
+            .extend_entities(MetricComponent::<NEntries>::default())
             .extend_entities(MetricComponent::<ExitPrice>::default())
             .extend_entities(MetricComponent::<PrevBalance>::default())
             .init_resource::<PriceResource>()
             .add_resource(self.slippage)
             .add_resource(self.fee)
+            .add_resource(MaxPyramiding(self.max_pyramiding))
+            .add_resource(self.margin)
             .add_resource(StartingBalance(self.starting_balance))
             .add_system_without_plugin(input::new::<I, S>(self.inputs), Input0::new())
             .add_system_without_plugin(update_prev_balance::new(), Last::new())
@@ -59,37 +68,118 @@ pub struct BacktestingParams {
     position: Metric<Position>,
     entry_price: Metric<EntryPrice>,
     exit_price: Metric<ExitPrice>,
+    n_entries: Metric<NEntries>,
+    max_pyramiding: Res<MaxPyramiding>,
+    margin: Res<Margin>,
     fee: Res<Fee>,
     slippage: Res<Slippage>,
+    events: TradeEvents,
+    orders: Orders,
+}
+
+#[system_param]
+/// Events that are sent while orders are filled.
+pub struct TradeEvents {
     position_updated: EntityEvents<PositionUpdated>,
     position_opened: EntityEvents<PositionOpened>,
     position_closed: EntityEvents<PositionClosed>,
+    position_liquidated: EntityEvents<PositionLiquidated>,
     order_executed: EntityEvents<OrderExecuted>,
     order_canceled: EntityEvents<OrderCanceled>,
-    orders: Orders,
 }
 
 impl<'w, 's, const N: usize> BacktestingParams<'w, 's, N> {
     #[inline(always)]
     pub fn trade(&mut self, high: f32, low: f32, close: f32) {
-        use crate::types::{
-            Direction::{Long, Short},
-            PositionAction::{Close, Open},
-        };
-        let price = close;
         let mut state = CoreState {
             position: *self.position,
             entry_price: *self.entry_price,
             balance: *self.balance,
             exit_price: *self.exit_price,
+            n_entries: *self.n_entries,
+            max_pyramiding: self.max_pyramiding.0,
+            margin: **self.margin,
+            locked: 0.,
+            other_pnl: 0.,
         };
-        let slippage = **self.slippage;
-        let fee = **self.fee;
+        self.events.fill(
+            &mut state,
+            self.orders.get_mut(),
+            high,
+            low,
+            close,
+            **self.slippage,
+            **self.fee,
+        );
+        *self.balance = state.balance;
+        *self.position = state.position;
+        *self.entry_price = state.entry_price;
+        *self.exit_price = state.exit_price;
+        *self.n_entries = state.n_entries;
+    }
+}
+
+impl<'w, 's, const N: usize> TradeEvents<'w, 's, N> {
+    /// Sends events in `scope` of the entity, see `EntityScope`.
+    #[inline(always)]
+    fn set_scope(&mut self, scope: Option<usize>) {
+        self.position_updated.set_scope(scope);
+        self.position_opened.set_scope(scope);
+        self.position_closed.set_scope(scope);
+        self.position_liquidated.set_scope(scope);
+        self.order_executed.set_scope(scope);
+        self.order_canceled.set_scope(scope);
+    }
+
+    /// Cancels all `orders`.
+    #[inline(always)]
+    fn cancel_all(&mut self, orders: &mut OrdersComponent<N>) {
+        let order_canceled = &mut self.order_canceled;
+        let mut canceled = |id| order_canceled.send(OrderCanceled { id });
+        for id in orders.canceled.ids.iter_mut().filter_map(Option::take) {
+            canceled(id);
+        }
+        loop {
+            let id = orders
+                .tmp_orders
+                .orders
+                .iter()
+                .chain(orders.active_orders.orders.iter())
+                .flatten()
+                .map(|o| o.id)
+                .next();
+            match id {
+                Some(id) => orders.cancel(id, &mut canceled),
+                None => break,
+            }
+        }
+    }
+
+    /// Liquidates the position if needed, then fills, moves and cancels `orders` on a bar.
+    #[allow(clippy::too_many_arguments)]
+    #[inline(always)]
+    fn fill(
+        &mut self,
+        state: &mut CoreState,
+        orders: &mut OrdersComponent<N>,
+        high: f32,
+        low: f32,
+        close: f32,
+        slippage: Slippage,
+        fee: Fee,
+    ) {
+        use crate::types::{
+            Direction::{Long, Short},
+            PositionAction::{Close, Open},
+        };
+        let price = close;
         let position_updated = &mut self.position_updated;
         let position_opened = &mut self.position_opened;
         let position_closed = &mut self.position_closed;
         let order_executed = &mut self.order_executed;
-        // Function used instead of a closure to force inlining.
+        // Function used instead of a closure to force inlining. Returns `false` if nothing was
+        // traded.
+        #[allow(clippy::too_many_arguments)]
         #[inline(always)]
         fn execute<const N: usize>(
             order_id: OrderId,
@@ -104,13 +194,16 @@ impl<'w, 's, const N: usize> BacktestingParams<'w, 's, N> {
             position_opened: &mut EntityEvents<PositionOpened, N>,
             position_closed: &mut EntityEvents<PositionClosed, N>,
             order_executed: &mut EntityEvents<OrderExecuted, N>,
-        ) {
+        ) -> bool {
             let size = match (position_action, direction) {
                 (Open, Long) => state.open_long(price, position_size, slippage, fee),
                 (Open, Short) => state.open_short(price, position_size, slippage, fee),
                 (Close, Long) => state.close_long(price, position_size, slippage, fee),
                 (Close, Short) => state.close_short(price, position_size, slippage, fee),
             };
+            if size <= 0. {
+                return false;
+            }
             // dbg!(state);
             position_updated.send(PositionUpdated {
                 position_action,
@@ -122,6 +215,7 @@ impl<'w, 's, const N: usize> BacktestingParams<'w, 's, N> {
                 Close => position_closed.send(PositionClosed { direction, size }),
             }
             order_executed.send(OrderExecuted { id: order_id });
+            true
         }
         // let mut execute =
         //     |order_id, position_action, direction, price, position_size, state: &mut CoreState| {
@@ -143,76 +237,239 @@ impl<'w, 's, const N: usize> BacktestingParams<'w, 's, N> {
         //         }
         //         order_executed.send(OrderExecuted { id: order_id });
         //     };
-        let orders = self.orders.get_mut();
+        if let Some(liquidation_price) = state.liquidation_price() {
+            let direction = if state.position > 0. { Long } else { Short };
+            let liquidated = match direction {
+                Long => low <= liquidation_price,
+                Short => high >= liquidation_price,
+            };
+            if liquidated {
+                let size = match direction {
+                    Long => state.close_long(
+                        liquidation_price,
+                        PositionSize::Relative(1.),
+                        Slippage::Absolute(0.),
+                        fee,
+                    ),
+                    Short => state.close_short(
+                        liquidation_price,
+                        PositionSize::Relative(1.),
+                        Slippage::Absolute(0.),
+                        fee,
+                    ),
+                };
+                position_updated.send(PositionUpdated {
+                    position_action: Close,
+                    direction,
+                    size,
+                });
+                position_closed.send(PositionClosed { direction, size });
+                self.position_liquidated.send(PositionLiquidated {
+                    direction,
+                    size,
+                    price: liquidation_price,
+                });
+            }
+        }
+        let order_canceled = &mut self.order_canceled;
+        let mut canceled = |id| order_canceled.send(OrderCanceled { id });
+        // Orders canceled by a strategy were already removed.
+        for id in orders.canceled.ids.iter_mut().filter_map(Option::take) {
+            canceled(id);
+        }
         // let c = orders.clone();
-        for maybe_order in &mut orders.active_orders.orders {
-            let order = some_loop!(maybe_order);
+        for i in 0..orders.active_orders.orders.len() {
+            let order = some_loop!(&mut orders.active_orders.orders[i]);
+            if order.parent.is_some() {
+                continue;
+            }
             let id = order.id;
+            let (position_action, direction) = order.order.action();
+            let stale = !state.can_execute(position_action, direction);
+            if stale {
+                orders.cancel(id, &mut canceled);
+                continue;
+            }
+            let mut executed = None;
             match &mut order.order {
+                // Activated child of a bracket
+                OrderPlaced::Market {
+                    size,
+                    position_action,
+                    direction,
+                } => {
+                    executed = Some(execute::<N>(
+                        id,
+                        *position_action,
+                        *direction,
+                        price,
+                        *size,
+                        state,
+                        slippage,
+                        fee,
+                        position_updated,
+                        position_opened,
+                        position_closed,
+                        order_executed,
+                    ));
+                },
                 OrderPlaced::StopMarket {
                     size,
                     position_action,
                     direction,
                     trigger,
                 } => {
-                    match position_action {
-                        PositionAction::Open => {
-                            if state.is_position_opened() {
-                                *maybe_order = None;
-                                self.order_canceled.send(OrderCanceled { id });
-                                continue;
-                            }
-                        },
-                        PositionAction::Close => {
-                            if state.is_position_closed() {
-                                *maybe_order = None;
-                                self.order_canceled.send(OrderCanceled { id });
-                                continue;
-                            }
-                        },
+                    let triggered = if is_buy(*position_action, *direction) {
+                        high >= *trigger
+                    } else {
+                        low <= *trigger
+                    };
+                    if !triggered {
+                        continue;
                     }
-                    match direction {
-                        Short if high > *trigger => {},
-                        Long if low < *trigger => {},
-                        _ => {
-                            continue;
-                        },
+                    executed = Some(execute::<N>(
+                        id,
+                        *position_action,
+                        *direction,
+                        price,
+                        *size,
+                        state,
+                        slippage,
+                        fee,
+                        position_updated,
+                        position_opened,
+                        position_closed,
+                        order_executed,
+                    ));
+                },
+                OrderPlaced::TrailingStop {
+                    size,
+                    position_action,
+                    direction,
+                    trail,
+                    trigger,
+                } => {
+                    // The stop is checked before it's moved because the order of high and low
+                    // within a bar is unknown. Buy stops follow the low, sell stops the high.
+                    let buy = is_buy(*position_action, *direction);
+                    let hit = if buy {
+                        high >= *trigger
+                    } else {
+                        low <= *trigger
+                    };
+                    if !hit {
+                        *trigger = if buy {
+                            trigger.min(trail.stop_price(low, true))
+                        } else {
+                            trigger.max(trail.stop_price(high, false))
+                        };
+                        continue;
                     }
-                    // println!("stop");
-                    execute::<N>(
-                        order.id,
+                    executed = Some(execute::<N>(
+                        id,
                         *position_action,
                         *direction,
                         price,
                         *size,
-                        &mut state,
+                        state,
                         slippage,
                         fee,
                         position_updated,
                         position_opened,
                         position_closed,
                         order_executed,
-                    );
+                    ));
+                },
+                OrderPlaced::StopLimit {
+                    size,
+                    position_action,
+                    direction,
+                    trigger,
+                    limit,
+                } => {
+                    let triggered = if is_buy(*position_action, *direction) {
+                        high >= *trigger
+                    } else {
+                        low <= *trigger
+                    };
+                    if !triggered {
+                        continue;
+                    }
+                    // Triggered orders rest as limit orders and can fill on the same bar.
+                    order.order = OrderPlaced::Limit {
+                        size: *size,
+                        position_action: *position_action,
+                        direction: *direction,
+                        limit: *limit,
+                    };
+                },
+                OrderPlaced::Limit { .. } => {},
+            }
+            if let OrderPlaced::Limit {
+                size,
+                position_action,
+                direction,
+                limit,
+            } = &order.order
+            {
+                let crossed = if is_buy(*position_action, *direction) {
+                    low <= *limit
+                } else {
+                    high >= *limit
+                };
+                if crossed {
+                    executed = Some(execute::<N>(
+                        id,
+                        *position_action,
+                        *direction,
+                        *limit,
+                        *size,
+                        state,
+                        Slippage::Absolute(0.),
+                        fee,
+                        position_updated,
+                        position_opened,
+                        position_closed,
+                        order_executed,
+                    ));
+                }
+            }
+            match executed {
+                Some(true) => {
+                    orders.active_orders.orders[i] = None;
+                    orders.on_filled(id, &mut canceled);
                 },
-                _ => {},
+                // Nothing could be opened, e.g. margin is used up.
+                Some(false) => orders.cancel(id, &mut canceled),
+                None => {},
             }
         }
-        for maybe_order in &mut orders.tmp_orders.orders {
-            let mut order = some_loop!(maybe_order.take());
+        for i in 0..orders.tmp_orders.orders.len() {
+            let order = some_loop!(orders.tmp_orders.orders[i].take());
             // dbg!(&order);
-            match &mut order.order {
+            match &order.order {
+                OrderPlaced::Market {
+                    position_action,
+                    direction,
+                    ..
+                } if order.parent.is_none() && !state.can_execute(*position_action, *direction) => {
+                    // Put back so that orders waiting on it are canceled too.
+                    let id = order.id;
+                    orders.tmp_orders.orders[i] = Some(order);
+                    orders.cancel(id, &mut canceled);
+                },
                 OrderPlaced::Market {
                     size,
                     position_action,
                     direction,
-                } => {
-                    execute::<N>(
+                } if order.parent.is_none() => {
+                    let executed = execute::<N>(
                         order.id,
                         *position_action,
                         *direction,
                         price,
                         *size,
-                        &mut state,
+                        state,
                         slippage,
                         fee,
                         position_updated,
@@ -220,7 +477,13 @@ impl<'w, 's, const N: usize> BacktestingParams<'w, 's, N> {
                         position_closed,
                         order_executed,
                     );
-                    *maybe_order = None;
+                    if executed {
+                        orders.on_filled(order.id, &mut canceled);
+                    } else {
+                        let id = order.id;
+                        orders.tmp_orders.orders[i] = Some(order);
+                        orders.cancel(id, &mut canceled);
+                    }
                 },
                 _ => {
                     orders.active_orders.push(order);
@@ -230,39 +493,65 @@ impl<'w, 's, const N: usize> BacktestingParams<'w, 's, N> {
         // if c != *orders {
         // dbg!(&c, &orders);
         // }
-        *self.balance = state.balance;
-        *self.position = state.position;
-        *self.entry_price = state.entry_price;
-        *self.exit_price = state.exit_price;
     }
 }
 
 #[derive(Debug)]
 struct CoreState {
+    /// Positive for long, negative for short.
     position: f32,
+    /// Volume weighted price of all entries.
     entry_price: f32,
     balance: f32,
     exit_price: f32,
+    n_entries: u32,
+    max_pyramiding: u32,
+    margin: Margin,
+    /// Value of positions in other instruments of a portfolio, it reduces buying power.
+    locked: f32,
+    /// Unrealized profit and loss of positions in other instruments of a portfolio.
+    other_pnl: f32,
 }
 
 impl CoreState {
     #[inline(always)]
-    fn is_position_opened(&self) -> bool {
-        !self.is_position_closed()
+    fn is_position_closed(&self) -> bool {
+        self.position == 0.
+    }
+
+    #[inline(always)]
+    fn direction(&self) -> Option<Direction> {
+        if self.position > 0. {
+            Some(Direction::Long)
+        } else if self.position < 0. {
+            Some(Direction::Short)
+        } else {
+            None
+        }
     }
 
+    /// Whether an order can be executed with the current position.
     #[inline(always)]
-    fn is_position_closed(&self) -> bool {
-        self.position == 0.
+    fn can_execute(&self, position_action: PositionAction, direction: Direction) -> bool {
+        match (position_action, self.direction()) {
+            (PositionAction::Open, None) => true,
+            (PositionAction::Open, Some(d)) => {
+                d == direction && self.n_entries < self.max_pyramiding
+            },
+            (PositionAction::Close, d) => d == Some(direction),
+        }
     }
 
     #[inline(always)]
-    fn open(&mut self, size: f32, fee: Fee) {
+    fn open(&mut self, price: f32, size: f32, fee: Fee) {
         match fee {
             Fee::RelativeToVolume(x) => {
-                self.balance -= size * self.entry_price * x;
+                self.balance -= size * price * x;
             },
         }
+        let prev_size = self.position.abs();
+        self.entry_price = (self.entry_price * prev_size + price * size) / (prev_size + size);
+        self.n_entries += 1;
     }
 
     #[inline(always)]
@@ -271,17 +560,16 @@ impl CoreState {
         //     "open long: {:?}, {:?}, {:?} {:?}",
         //     price, size, slippage, fee
         // );
-        match slippage {
-            Slippage::Relative(x) => {
-                self.entry_price = price * (1. + x);
-            },
-            Slippage::Absolute(x) => {
-                self.entry_price = price + x;
-            },
+        let price = match slippage {
+            Slippage::Relative(x) => price * (1. + x),
+            Slippage::Absolute(x) => price + x,
+        };
+        let size = self.get_open_size(price, size);
+        if size <= 0. {
+            return 0.;
         }
-        let size = self.get_open_size(size);
+        self.open(price, size, fee);
         self.position += size;
-        self.open(size, fee);
         size
     }
 
@@ -291,17 +579,16 @@ impl CoreState {
         //     "open short: {:?}, {:?}, {:?} {:?}",
         //     price, size, slippage, fee
         // );
-        match slippage {
-            Slippage::Relative(x) => {
-                self.entry_price = price * (1. - x);
-            },
-            Slippage::Absolute(x) => {
-                self.entry_price = price - x;
-            },
+        let price = match slippage {
+            Slippage::Relative(x) => price * (1. - x),
+            Slippage::Absolute(x) => price - x,
+        };
+        let size = self.get_open_size(price, size);
+        if size <= 0. {
+            return 0.;
         }
-        let size = self.get_open_size(size);
-        self.position = -size;
-        self.open(size, fee);
+        self.open(price, size, fee);
+        self.position -= size;
         size
     }
 
@@ -311,19 +598,30 @@ impl CoreState {
         //     "close long: {:?}, {:?}, {:?} {:?}",
         //     price, size, slippage, fee
         // );
-        let exit_price;
-        match slippage {
-            Slippage::Relative(x) => {
-                exit_price = price * (1. - x);
-            },
-            Slippage::Absolute(x) => {
-                exit_price = price - x;
-            },
-        }
+        let exit_price = match slippage {
+            Slippage::Relative(x) => price * (1. - x),
+            Slippage::Absolute(x) => price - x,
+        };
         let size = self.get_close_size(size);
+        self.balance += (exit_price - self.entry_price) * size;
         self.position -= size;
-        self.close(exit_price, size, fee);
-        size
+        self.close(exit_price, size, fee)
+    }
+
+    #[inline(always)]
+    fn close_short(&mut self, price: f32, size: PositionSize, slippage: Slippage, fee: Fee) -> f32 {
+        // println!(
+        //     "close short: {:?}, {:?}, {:?} {:?}",
+        //     price, size, slippage, fee
+        // );
+        let exit_price = match slippage {
+            Slippage::Relative(x) => price * (1. + x),
+            Slippage::Absolute(x) => price + x,
+        };
+        let size = self.get_close_size(size);
+        self.balance += (self.entry_price - exit_price) * size;
+        self.position += size;
+        self.close(exit_price, size, fee)
     }
 
     #[inline(always)]
@@ -333,47 +631,51 @@ impl CoreState {
                 self.balance -= exit_price * size * x;
             },
         }
-        self.balance += (exit_price - self.entry_price) * size;
         // dbg!(self.balance, exit_price, self.entry_price, size);
         self.exit_price = exit_price;
-        self.position = 0.;
+        // Avoids dust left by floating point errors.
+        if self.position.abs() <= f32::EPSILON * size {
+            self.position = 0.;
+        }
+        if self.is_position_closed() {
+            self.n_entries = 0;
+        }
         size
     }
 
+    /// Price at which equity falls to maintenance margin. Positions in other instruments add their
+    /// unrealized profit and loss to the equity and their maintenance margin to the requirement.
     #[inline(always)]
-    fn close_short(&mut self, price: f32, size: PositionSize, slippage: Slippage, fee: Fee) -> f32 {
-        let exit_price;
-        // println!(
-        //     "close short: {:?}, {:?}, {:?} {:?}",
-        //     price, size, slippage, fee
-        // );
-        match slippage {
-            Slippage::Relative(x) => {
-                exit_price = price * (1. + x);
-            },
-            Slippage::Absolute(x) => {
-                exit_price = price + x;
-            },
+    fn liquidation_price(&self) -> Option<f32> {
+        let size = self.position.abs();
+        let maintenance = self.margin.maintenance_margin;
+        let balance = self.balance + self.other_pnl - self.locked * maintenance;
+        match self.direction()? {
+            Direction::Long => Some(self.entry_price * (1. + maintenance) - balance / size),
+            Direction::Short => Some(self.entry_price * (1. - maintenance) + balance / size),
         }
-        let size = self.get_close_size(size);
-        self.position += size;
-        self.close(exit_price, size, fee);
-        size
     }
 
+    /// Size to open, it never exceeds leveraged balance and is 0 when it is used up.
     #[inline(always)]
-    fn get_open_size(&self, size: PositionSize) -> f32 {
-        match size {
-            PositionSize::Relative(x) => self.balance / self.entry_price * x,
+    fn get_open_size(&self, price: f32, size: PositionSize) -> f32 {
+        let buying_power = self.balance * self.margin.leverage();
+        let used = self.locked + self.position.abs() * self.entry_price;
+        let available = (buying_power - used).max(0.) / price;
+        let size = match size {
+            PositionSize::Relative(x) => buying_power / price * x,
             PositionSize::Absolute(x) => x,
-        }
+        };
+        size.min(available).max(0.)
     }
 
+    /// Size to close, it never exceeds the position.
     #[inline(always)]
     fn get_close_size(&self, size: PositionSize) -> f32 {
+        let position = self.position.abs();
         match size {
-            PositionSize::Relative(x) => self.position * x,
-            PositionSize::Absolute(x) => x,
+            PositionSize::Relative(x) => position * x,
+            PositionSize::Absolute(x) => x.min(position),
         }
     }
 }
@@ -385,6 +687,9 @@ pub struct BacktestInput {
 #[system]
 fn update_prev_balance(balance: Metric<Balance>, mut prev_balance: Metric<PrevBalance>) {
     *prev_balance = *balance;
+    for (scope, balance) in balance.scopes().iter().enumerate() {
+        **prev_balance.scope_mut(scope) = **balance;
+    }
 }
 
 #[system]
@@ -463,3 +768,395 @@ all_tuples::all_tuples!(impl_inputs, 0, 16, P);
 fn input<I: Inputs, S: Nest + 'static>(_inputs: InputsParam<I, S>, _in: PhantomIn<Series<S>>) {
     // Handled in param.
 }
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicU32, Ordering};
+    use std::prelude::v1::*;
+
+    use super::*;
+    use crate::{
+        block_relays::UpdateRelay, hlcv, simulation_relays::SimulationEnding, stages::Signal,
+        CorePlugin, *,
+    };
+
+    /// Balance, position and entry price of an entity after a backtest.
+    #[derive(Debug, PartialEq)]
+    struct Account {
+        balance: f32,
+        position: f32,
+        entry_price: f32,
+    }
+
+    /// Orders sent by the strategy of a test. Every test shares one strategy system, so only one
+    /// ECS gets compiled.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Scenario {
+        LimitLong,
+        LimitShort,
+        StopOpenLong,
+        StopLimitOpenLong,
+        CancelBracket,
+        TrailingLong,
+        TrailingShort,
+        TrailingOpenLong,
+        StaleBracket,
+        MarginUsedUp,
+    }
+
+    /// Backtests `scenario` over bars of `[high, low, close]`. The first bar is skipped by the
+    /// loop, so orders are sent starting at index 1.
+    fn backtest(bars: &[[f32; 3]], scenario: Scenario) -> Account {
+        let data: Vec<f32> = bars
+            .iter()
+            .flat_map(|&[high, low, close]| [high, low, close, 1.])
+            .collect();
+        let mut metrics = vec![0u8; 1024 * 1024];
+        let max_pyramiding = if scenario == Scenario::MarginUsedUp {
+            2
+        } else {
+            1
+        };
+        let builder = EcsBuilderStruct::new::<_, 6>(
+            crate::stages::BacktestSchedule::builder(),
+            EntitiesBuilderStruct1::new(),
+        )
+        .add_plugins(DefaultBacktestPlugins {
+            core: CorePlugin {
+                loop_start_bound: 0,
+                loop_end_bound_excluded: bars.len(),
+            },
+            metrics: MetricsPlugin::new(metrics.as_mut_ptr(), bars.len()),
+            backtest: HlcvBacktestPlugin {
+                timeframe_s: 60,
+                funding_interval_s: 0,
+                backtest_plugin: BacktestPlugin {
+                    inputs: unsafe {
+                        Series::<hlcv::HlcvInputNest>::new(data.as_ptr() as *const u8)
+                    },
+                    starting_balance: 1000.,
+                    slippage: Slippage::Relative(0.),
+                    fee: Fee::RelativeToVolume(0.),
+                    max_pyramiding,
+                    margin: Default::default(),
+                    inputs_marker: PhantomData::<hlcv::HlcvInput>,
+                },
+            },
+        });
+        let mut ecs = MetricsBuilderStruct::new(builder)
+            .add_metric::<SimulationEnding, UpdateRelay, OnPositionClosed, Balance>()
+            .finish()
+            .add_system(strategy::new(scenario), Signal::new())
+            .build();
+        ecs.run();
+        Account {
+            balance: ecs
+                .component::<MetricComponent<Balance>>(Entity(0))
+                .metric
+                .get(),
+            position: ecs
+                .component::<MetricComponent<Position>>(Entity(0))
+                .metric
+                .get(),
+            entry_price: ecs
+                .component::<MetricComponent<EntryPrice>>(Entity(0))
+                .metric
+                .get(),
+        }
+    }
+
+    const ZERO: AtomicU32 = AtomicU32::new(0);
+    /// Number of `OrderCanceled` events of each scenario.
+    static N_CANCELED: [AtomicU32; 10] = [ZERO; 10];
+
+    #[system]
+    fn strategy(
+        mut orders: Orders,
+        index: LoopIndex,
+        canceled: EntityEvents<OrderCanceled>,
+        scenario: In<Scenario>,
+    ) {
+        N_CANCELED[*scenario as usize].fetch_add(canceled.iter().count() as u32, Ordering::Relaxed);
+        if *scenario == Scenario::MarginUsedUp && *index <= 2 {
+            orders.send(MarketOpenLong::full());
+        }
+        if *index != 1 {
+            return;
+        }
+        match *scenario {
+            Scenario::LimitLong => {
+                orders.send(LimitOpenLong::full(95.));
+            },
+            Scenario::LimitShort => {
+                orders.send(LimitOpenShort::full(105.));
+            },
+            Scenario::StopOpenLong => {
+                orders.send(StopMarketOpenFullLong::absolute(103.));
+            },
+            Scenario::StopLimitOpenLong => {
+                orders.send(StopLimitOpenLong::full(103., 104.));
+            },
+            Scenario::CancelBracket => {
+                let ids = orders.send_bracket(Bracket {
+                    entry: LimitOpenLong::full(95.),
+                    take_profit: LimitCloseLong::full(110.),
+                    stop_loss: StopMarketCloseFullLong::absolute(90.),
+                });
+                orders.cancel(ids.unwrap().entry);
+            },
+            Scenario::TrailingLong => {
+                orders.send(MarketOpenLong::full());
+                orders.send(TrailingStopCloseFullLong::absolute(5.));
+            },
+            Scenario::TrailingShort => {
+                orders.send(MarketOpenShort::full());
+                orders.send(TrailingStopCloseFullShort::absolute(5.));
+            },
+            Scenario::TrailingOpenLong => {
+                orders.send(TrailingStopOpenFullLong::absolute(5.));
+            },
+            Scenario::StaleBracket => {
+                orders.send(MarketOpenLong::full());
+                // Goes stale once the first order uses up pyramiding.
+                orders.send_bracket(Bracket {
+                    entry: MarketOpenLong::full(),
+                    take_profit: LimitCloseLong::full(110.),
+                    stop_loss: StopMarketCloseFullLong::absolute(90.),
+                });
+            },
+            Scenario::MarginUsedUp => {},
+        }
+    }
+
+    #[test]
+    fn buy_stops_trigger_above_price() {
+        let bars = [
+            [101., 99., 100.],
+            [101., 99., 100.],
+            [101., 90., 95.],
+            [105., 99., 102.],
+            [101., 99., 100.],
+        ];
+        // A fall doesn't trigger buy stops, a rise does.
+        let account = backtest(&bars, Scenario::StopOpenLong);
+        assert_eq!(account.entry_price, 102.);
+        let account = backtest(&bars, Scenario::StopLimitOpenLong);
+        assert_eq!(account.entry_price, 104.);
+    }
+
+    #[test]
+    fn trailing_stop_ratchets() {
+        // Opened at 100, the stop starts at 95.
+        let bars = [
+            [101., 99., 100.],
+            [101., 99., 100.],
+            // Moves to 105.
+            [110., 99., 108.],
+            // Low stays above 105 before the stop moves to 115.
+            [120., 106., 118.],
+            // Lower high doesn't move it back.
+            [117., 116., 116.],
+            [116., 114., 115.],
+            [101., 99., 100.],
+        ];
+        let account = backtest(&bars, Scenario::TrailingLong);
+        assert_eq!(account.position, 0.);
+        assert_eq!(account.balance, 1000. + 10. * 15.);
+        let mirrored: Vec<_> = bars
+            .iter()
+            .map(|&[high, low, close]| [200. - low, 200. - high, 200. - close])
+            .collect();
+        let account = backtest(&mirrored, Scenario::TrailingShort);
+        assert_eq!(account.position, 0.);
+        assert_eq!(account.balance, 1000. + 10. * 15.);
+    }
+
+    #[test]
+    fn trailing_buy_stop_follows_low() {
+        let bars = [
+            [101., 99., 100.],
+            [101., 99., 100.],
+            // Stop starts at 105 and moves down to 95.
+            [101., 90., 92.],
+            [96., 91., 95.],
+            [101., 99., 100.],
+        ];
+        let account = backtest(&bars, Scenario::TrailingOpenLong);
+        assert_eq!(account.entry_price, 95.);
+    }
+
+    #[test]
+    fn limit_crossed_by_low() {
+        let bars = [[101., 99., 100.]; 3];
+        // Not crossed, the order rests.
+        let account = backtest(&bars, Scenario::LimitLong);
+        assert_eq!(account.position, 0.);
+        let mut bars = bars.to_vec();
+        bars.extend([[101., 99., 100.], [101., 94., 96.], [101., 99., 100.]]);
+        // Crossed 3 bars after it was sent.
+        let account = backtest(&bars, Scenario::LimitLong);
+        assert_eq!(account.position, 1000. / 95.);
+        assert_eq!(account.entry_price, 95.);
+    }
+
+    #[test]
+    fn cancel_sends_events() {
+        let bars = [[101., 94., 100.]; 4];
+        let account = backtest(&bars, Scenario::CancelBracket);
+        assert_eq!(account.position, 0.);
+        // Entry, take profit and stop loss.
+        assert_eq!(
+            N_CANCELED[Scenario::CancelBracket as usize].load(Ordering::Relaxed),
+            3
+        );
+    }
+
+    #[test]
+    fn stale_bracket_entry() {
+        let bars = [
+            [101., 99., 100.],
+            [101., 99., 100.],
+            [101., 99., 100.],
+            [120., 99., 100.],
+            [101., 99., 100.],
+        ];
+        let account = backtest(&bars, Scenario::StaleBracket);
+        assert_eq!(account.position, 10.);
+        // Entry, take profit and stop loss.
+        assert_eq!(
+            N_CANCELED[Scenario::StaleBracket as usize].load(Ordering::Relaxed),
+            3
+        );
+    }
+
+    #[test]
+    fn limit_crossed_by_high() {
+        let bars = [
+            [101., 99., 100.],
+            [101., 99., 100.],
+            [101., 99., 100.],
+            [106., 99., 104.],
+            [101., 99., 100.],
+        ];
+        let account = backtest(&bars, Scenario::LimitShort);
+        assert_eq!(account.position, -1000. / 105.);
+        assert_eq!(account.entry_price, 105.);
+        assert_eq!(account.balance, 1000.);
+    }
+
+    #[test]
+    fn margin_used_up() {
+        let bars = [[101., 99., 100.]; 4];
+        let account = backtest(&bars, Scenario::MarginUsedUp);
+        assert_eq!(account.position, 10.);
+        assert_eq!(account.entry_price, 100.);
+        // Second entry opens nothing.
+        assert_eq!(
+            N_CANCELED[Scenario::MarginUsedUp as usize].load(Ordering::Relaxed),
+            1
+        );
+    }
+
+    fn state(max_pyramiding: u32) -> CoreState {
+        CoreState {
+            position: 0.,
+            entry_price: 0.,
+            balance: 100.,
+            exit_price: 0.,
+            n_entries: 0,
+            max_pyramiding,
+            margin: Margin::default(),
+            locked: 0.,
+            other_pnl: 0.,
+        }
+    }
+
+    const NO_SLIPPAGE: Slippage = Slippage::Absolute(0.);
+    const NO_FEE: Fee = Fee::RelativeToVolume(0.);
+
+    #[test]
+    fn partial_close_long() {
+        let mut state = state(1);
+        state.open_long(10., PositionSize::Absolute(4.), NO_SLIPPAGE, NO_FEE);
+        assert_eq!(state.position, 4.);
+        let closed = state.close_long(12., PositionSize::Relative(0.5), NO_SLIPPAGE, NO_FEE);
+        assert_eq!(closed, 2.);
+        assert_eq!(state.position, 2.);
+        assert_eq!(state.balance, 104.);
+        assert!(state.can_execute(PositionAction::Close, Direction::Long));
+        state.close_long(8., PositionSize::Absolute(5.), NO_SLIPPAGE, NO_FEE);
+        assert!(state.is_position_closed());
+        assert_eq!(state.balance, 100.);
+        assert_eq!(state.n_entries, 0);
+    }
+
+    #[test]
+    fn open_without_margin() {
+        let mut state = state(2);
+        state.open_long(10., PositionSize::Relative(1.), NO_SLIPPAGE, NO_FEE);
+        assert_eq!(state.position, 10.);
+        let opened = state.open_long(10., PositionSize::Relative(1.), NO_SLIPPAGE, NO_FEE);
+        assert_eq!(opened, 0.);
+        assert_eq!(state.position, 10.);
+        assert_eq!(state.entry_price, 10.);
+        assert_eq!(state.n_entries, 1);
+        state.balance = 50.;
+        assert_eq!(state.get_open_size(10., PositionSize::Absolute(1.)), 0.);
+    }
+
+    #[test]
+    fn liquidation_with_other_positions() {
+        let mut state = state(1);
+        state.margin.maintenance_margin = 0.1;
+        state.open_long(10., PositionSize::Absolute(10.), NO_SLIPPAGE, NO_FEE);
+        assert_eq!(state.liquidation_price(), Some(11. - 100. / 10.));
+        // Loss and maintenance margin of other positions leave 100 - 20 - 5 to back this one.
+        state.other_pnl = -20.;
+        state.locked = 50.;
+        assert_eq!(state.liquidation_price(), Some(11. - 75. / 10.));
+    }
+
+    #[test]
+    fn partial_close_short() {
+        let mut state = state(1);
+        state.open_short(10., PositionSize::Absolute(4.), NO_SLIPPAGE, NO_FEE);
+        assert_eq!(state.position, -4.);
+        let closed = state.close_short(8., PositionSize::Relative(0.25), NO_SLIPPAGE, NO_FEE);
+        assert_eq!(closed, 1.);
+        assert_eq!(state.position, -3.);
+        assert_eq!(state.balance, 102.);
+        assert!(!state.can_execute(PositionAction::Close, Direction::Long));
+    }
+
+    #[test]
+    fn pyramiding() {
+        let mut state = state(2);
+        state.open_long(10., PositionSize::Absolute(1.), NO_SLIPPAGE, NO_FEE);
+        assert!(state.can_execute(PositionAction::Open, Direction::Long));
+        assert!(!state.can_execute(PositionAction::Open, Direction::Short));
+        state.open_long(13., PositionSize::Absolute(2.), NO_SLIPPAGE, NO_FEE);
+        assert_eq!(state.position, 3.);
+        assert_eq!(state.entry_price, 12.);
+        assert!(!state.can_execute(PositionAction::Open, Direction::Long));
+        state.close_long(14., PositionSize::Relative(1.), NO_SLIPPAGE, NO_FEE);
+        assert_eq!(state.balance, 106.);
+        assert!(state.can_execute(PositionAction::Open, Direction::Long));
+    }
+
+    #[test]
+    fn leverage() {
+        let mut state = state(2);
+        state.margin = Margin {
+            initial_margin: 0.1,
+            maintenance_margin: 0.05,
+            max_leverage: 5.,
+        };
+        let size = state.open_long(10., PositionSize::Relative(1.), NO_SLIPPAGE, NO_FEE);
+        assert_eq!(size, 50.);
+        // Leverage is used up
+        let size = state.open_long(10., PositionSize::Relative(1.), NO_SLIPPAGE, NO_FEE);
+        assert_eq!(size, 0.);
+        // Equity at 8.5 is 25 which equals maintenance margin of 0.05 * 50 * 10
+        assert_eq!(state.liquidation_price(), Some(8.5));
+    }
+}
@@ -9,6 +9,9 @@ use crate::{
 
 pub struct HlcvBacktestPlugin<I, S> {
     pub timeframe_s: u32,
+    /// Seconds between funding payments, 0 disables funding. Rates are read from
+    /// `FundingRateResource`, use `HlcvFundingInput` to load them.
+    pub funding_interval_s: u32,
     pub backtest_plugin: super::BacktestPlugin<I, S>,
 }
 
@@ -29,21 +32,152 @@ impl<I: Inputs, S: Nest + 'static> Plugin
         builder
             .add_resource(TimeframeS(self.timeframe_s))
             .add_resource(Elapsed(0))
+            .add_resource(FundingIntervalS(self.funding_interval_s))
             .init_resource::<HighResource>()
             .init_resource::<LowResource>()
             // .init_resource::<CloseResource>()
             .init_resource::<VolumeResource>()
+            .init_resource::<FundingRateResource>()
             .add_plugin(self.backtest_plugin)
             .add_system(input::new(), IndicatorCompute::new())
+            .add_system(funding::new(), IndicatorCompute::new())
             .add_system(trade::new(), Trade::new())
     }
 }
 #[system]
-fn input(index: LoopIndex, mut elapsed: Res<Elapsed>, timeframe: Res<TimeframeS>) {
-    elapsed.0 = *index as u64 * timeframe.0 as u64 * 1_000_000_000;
+pub(crate) fn input(
+    index: LoopIndex,
+    start: Res<LoopStartBound>,
+    mut elapsed: Res<Elapsed>,
+    timeframe: Res<TimeframeS>,
+) {
+    elapsed.0 = index.saturating_sub(start.0) as u64 * timeframe.0 as u64 * 1_000_000_000;
 }
 
 #[system]
 fn trade(mut params: BacktestingParams, price: Price, high: High, low: Low) {
     params.trade(*high, *low, *price);
 }
+
+/// Number of funding timestamps in a candle that starts at `elapsed_s`.
+#[inline(always)]
+fn funding_periods(elapsed_s: u64, timeframe_s: u64, interval_s: u64) -> u64 {
+    if interval_s == 0 {
+        return 0;
+    }
+    (elapsed_s + timeframe_s) / interval_s - elapsed_s / interval_s
+}
+
+/// Longs pay shorts when the funding rate is positive.
+#[system]
+fn funding(
+    mut balance: Metric<Balance>,
+    position: Metric<Position>,
+    price: Price,
+    rate: FundingRate,
+    elapsed: Res<Elapsed>,
+    timeframe: Res<TimeframeS>,
+    interval: Res<FundingIntervalS>,
+) {
+    let periods = funding_periods(
+        elapsed.0 / 1_000_000_000,
+        timeframe.0 as u64,
+        interval.0 as u64,
+    );
+    if periods == 0 || *position == 0. {
+        return;
+    }
+    *balance -= *position * *price * *rate * periods as f32;
+}
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+    use std::prelude::v1::*;
+
+    use super::*;
+    use crate::{
+        backtest::BacktestPlugin, block_relays::UpdateRelay, simulation_relays::SimulationEnding,
+        stages::Signal, CorePlugin,
+    };
+
+    const H: u32 = 60 * 60;
+
+    /// Balance after a backtest over `n_bars` hourly bars at a price of 100 and a funding rate of
+    /// 1%. A full position is opened at the start.
+    fn balance(n_bars: usize, long: bool, funding_interval_s: u32) -> f32 {
+        let data: Vec<f32> = (0..n_bars)
+            .flat_map(|_| [100., 100., 100., 1., 0.01])
+            .collect();
+        let mut metrics = vec![0u8; 1024 * 1024];
+        let builder = EcsBuilderStruct::new::<_, 6>(
+            crate::stages::BacktestSchedule::builder(),
+            EntitiesBuilderStruct1::new(),
+        )
+        .add_plugins(DefaultBacktestPlugins {
+            core: CorePlugin {
+                loop_start_bound: 0,
+                loop_end_bound_excluded: n_bars,
+            },
+            metrics: MetricsPlugin::new(metrics.as_mut_ptr(), n_bars),
+            backtest: HlcvBacktestPlugin {
+                timeframe_s: H,
+                funding_interval_s,
+                backtest_plugin: BacktestPlugin {
+                    inputs: unsafe {
+                        Series::<HlcvFundingInputNest>::new(data.as_ptr() as *const u8)
+                    },
+                    starting_balance: 1000.,
+                    slippage: Slippage::Relative(0.),
+                    fee: Fee::RelativeToVolume(0.),
+                    max_pyramiding: 1,
+                    margin: Default::default(),
+                    inputs_marker: PhantomData::<HlcvFundingInput>,
+                },
+            },
+        });
+        let mut ecs = MetricsBuilderStruct::new(builder)
+            .add_metric::<SimulationEnding, UpdateRelay, OnPositionClosed, Balance>()
+            .finish()
+            .add_system(open::new(long), Signal::new())
+            .build();
+        ecs.run();
+        ecs.component::<MetricComponent<Balance>>(Entity(0))
+            .metric
+            .get()
+    }
+
+    #[system]
+    fn open(mut orders: Orders, index: LoopIndex, long: In<bool>) {
+        if *index != 1 {
+            return;
+        }
+        if *long {
+            orders.send(MarketOpenLong::full());
+        } else {
+            orders.send(MarketOpenShort::full());
+        }
+    }
+
+    #[test]
+    fn funding() {
+        // The candle of the 8th bar starts at 7h and contains the 8h funding timestamp. A full
+        // position is 10 at a price of 100, so 10 is paid.
+        let before = balance(7, true, 8 * H);
+        assert_eq!(balance(8, true, 8 * H), before - 10.);
+        let before = balance(7, false, 8 * H);
+        assert_eq!(balance(8, false, 8 * H), before + 10.);
+        let before = balance(7, true, 0);
+        assert_eq!(balance(8, true, 0), before);
+    }
+
+    #[test]
+    fn periods() {
+        const H: u64 = self::H as u64;
+        assert_eq!(funding_periods(0, H, 8 * H), 0);
+        assert_eq!(funding_periods(7 * H, H, 8 * H), 1);
+        assert_eq!(funding_periods(8 * H, H, 8 * H), 0);
+        assert_eq!(funding_periods(0, 24 * H, 8 * H), 3);
+        assert_eq!(funding_periods(7 * H, H, 0), 0);
+    }
+}
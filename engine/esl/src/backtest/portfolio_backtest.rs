@@ -0,0 +1,470 @@
+//! One account that trades several instruments. Each instrument has its own series, position and
+//! orders, `Balance` is shared between them.
+//! Every instrument is an `EntityScope` of the account, so metrics are computed both for the
+//! account and for each instrument. Read the latter with `MetricComponent::scope(instrument_id)`.
+use std::prelude::v1::*;
+
+use inception::*;
+use num_traits::Float;
+
+use crate::{
+    backtest::{hlcv_backtest, BacktestingParams, CoreState},
+    hlcv::*,
+    orders::OrdersComponent,
+    stages::{BacktestInit, IndicatorCompute, Input1, Trade},
+    types::{Direction, InstrumentId},
+    *,
+};
+
+pub struct PortfolioBacktestPlugin<I, S, P> {
+    pub timeframe_s: u32,
+    /// Series of every instrument, `InstrumentId` is an index into it. Bars are read from
+    /// `HighResource`, `LowResource`, `CloseResource` and `VolumeResource` fields.
+    pub instruments: Vec<Series<P>>,
+    /// Loads global inputs, e.g. the series of the first instrument. Orders sent with `Orders`
+    /// have no instrument, they are canceled in the `Trade` stage. Use `PortfolioOrders` instead.
+    pub backtest_plugin: super::BacktestPlugin<I, S>,
+}
+
+impl<I: Inputs, S: Nest + 'static, P: Nest + 'static> Plugin for PortfolioBacktestPlugin<I, S, P> {
+    type Deps<L: PluginLoader> = L;
+
+    type Build<B: EcsBuilder> = impl EcsBuilder;
+
+    #[inline(always)]
+    fn deps<L: PluginLoader>(&mut self, loader: L) -> Self::Deps<L> {
+        loader
+    }
+
+    #[inline(always)]
+    fn build<B: EcsBuilder>(self, builder: B) -> Self::Build<B> {
+        let n_instruments = self.instruments.len();
+        builder
+            .add_resource(TimeframeS(self.timeframe_s))
+            .add_resource(Elapsed(0))
+            .add_resource(InstrumentBars(vec![Bar::default(); n_instruments]))
+            .init_resource::<HighResource>()
+            .init_resource::<LowResource>()
+            .init_resource::<VolumeResource>()
+            .add_plugin(self.backtest_plugin)
+            .add_system(init_portfolio::new(), BacktestInit::new())
+            .add_system(load_bars::new::<P>(self.instruments), Input1::new())
+            .add_system(hlcv_backtest::input::new(), IndicatorCompute::new())
+            .add_system(trade::new(), Trade::new())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bar {
+    pub high: f32,
+    pub low: f32,
+    pub close: f32,
+    pub volume: f32,
+}
+
+/// Current bar of every instrument, indexed by `InstrumentId`.
+#[derive(Clone, Debug, Default)]
+pub struct InstrumentBars(pub Vec<Bar>);
+
+impl InstrumentBars {
+    #[inline(always)]
+    pub fn get(&self, id: InstrumentId) -> &Bar {
+        &self.0[id.0]
+    }
+}
+
+/// Position, orders and instrument level metrics of one instrument.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InstrumentAccount<const N: usize> {
+    /// Positive for long, negative for short.
+    pub position: f32,
+    /// Volume weighted price of all entries.
+    pub entry_price: f32,
+    pub exit_price: f32,
+    pub n_entries: u32,
+    pub orders: OrdersComponent<N>,
+    /// Realized profit and loss including fees.
+    pub pnl: f32,
+    /// Number of positions that were fully closed.
+    pub n_trades: u32,
+    pub n_win_positions: u32,
+    /// Realized profit and loss of the current position.
+    position_pnl: f32,
+}
+
+impl<const N: usize> InstrumentAccount<N> {
+    #[inline(always)]
+    pub fn direction(&self) -> Option<Direction> {
+        if self.position > 0. {
+            Some(Direction::Long)
+        } else if self.position < 0. {
+            Some(Direction::Short)
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    pub fn unrealized_pnl(&self, price: f32) -> f32 {
+        self.position * (price - self.entry_price)
+    }
+
+    /// 0 if there were no trades.
+    #[inline(always)]
+    pub fn win_rate(&self) -> f32 {
+        if self.n_trades == 0 {
+            return 0.;
+        }
+        self.n_win_positions as f32 / self.n_trades as f32
+    }
+
+    /// Stores `state` after a trade, `balance` is the balance before the trade.
+    #[inline(always)]
+    fn update(&mut self, state: &CoreState, balance: f32) {
+        let delta = state.balance - balance;
+        self.pnl += delta;
+        self.position_pnl += delta;
+        if self.position != 0. && state.position == 0. {
+            self.n_trades += 1;
+            if self.position_pnl > 0. {
+                self.n_win_positions += 1;
+            }
+            self.position_pnl = 0.;
+        }
+        self.position = state.position;
+        self.entry_price = state.entry_price;
+        self.exit_price = state.exit_price;
+        self.n_entries = state.n_entries;
+    }
+}
+
+/// Accounts of all instruments of an entity.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Portfolio<const N: usize> {
+    pub instruments: Vec<InstrumentAccount<N>>,
+}
+
+impl<const N: usize> Portfolio<N> {
+    #[inline(always)]
+    pub fn instrument(&self, id: InstrumentId) -> &InstrumentAccount<N> {
+        &self.instruments[id.0]
+    }
+
+    /// `balance` with unrealized profit and loss of all positions.
+    #[inline(always)]
+    pub fn equity(&self, balance: f32, bars: &InstrumentBars) -> f32 {
+        self.instruments
+            .iter()
+            .zip(&bars.0)
+            .fold(balance, |equity, (account, bar)| {
+                equity + account.unrealized_pnl(bar.close)
+            })
+    }
+
+    /// Value of positions in other instruments than `index`.
+    #[inline(always)]
+    fn locked(&self, index: usize) -> f32 {
+        self.instruments
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, x)| x.position.abs() * x.entry_price)
+            .sum()
+    }
+
+    /// Unrealized profit and loss of positions in other instruments than `index`.
+    #[inline(always)]
+    fn other_pnl(&self, index: usize, bars: &InstrumentBars) -> f32 {
+        self.instruments
+            .iter()
+            .zip(&bars.0)
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, (account, bar))| account.unrealized_pnl(bar.close))
+            .sum()
+    }
+}
+
+/// Sends orders to instruments of a portfolio.
+pub struct PortfolioOrders<'w, 's, const N: usize> {
+    max_pyramiding: u32,
+    bars: &'w InstrumentBars,
+    n_orders: &'w mut MetricComponent<NOrders>,
+    portfolio: &'w mut Portfolio<N>,
+    _marker: PhantomSystemParam<'w, 's, N>,
+}
+
+impl<'w, 's, const N: usize> SystemParam for PortfolioOrders<'w, 's, N> {
+    type Item<'world, 'state, Wrld: World> = PortfolioOrders<'world, 'state, N>;
+    type State = ();
+
+    type Build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static> =
+        impl EcsBuilder;
+
+    unimpl_get_param!();
+
+    #[inline(always)]
+    fn get_param_for_entity<'world, 'state, Wrld, SB, ParamName, E>(
+        entity: &'world mut E,
+        _state: &'state mut Self::State,
+        world: &'world mut Wrld,
+    ) -> Option<Self::Item<'world, 'state, Wrld>>
+    where
+        Wrld: World,
+        SB: SystemParamNameMapper,
+        E: EntityFetch,
+        ParamName: 'static,
+    {
+        let world: &'world Wrld = world;
+        let entity = entity as *mut E;
+        unsafe {
+            Some(PortfolioOrders {
+                max_pyramiding: world.resource::<MaxPyramiding>().0,
+                bars: world.resource::<InstrumentBars>(),
+                n_orders: (*entity).get_component_mut::<MetricComponent<NOrders>>()?,
+                portfolio: (*entity).get_component_mut::<Portfolio<N>>()?,
+                _marker: PhantomSystemParam::default(),
+            })
+        }
+    }
+
+    #[inline(always)]
+    fn build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static>(
+        builder: B,
+    ) -> Self::Build<B, SB, ParamName> {
+        builder
+            .extend_entities(Portfolio::<N>::default())
+            .extend_entities(EntityScope::default())
+    }
+}
+
+impl<'w, 's, const N: usize> PortfolioOrders<'w, 's, N> {
+    /// Orders of an instrument, they are placed at its close price. Their ids are taken from
+    /// `NOrders` of the instrument.
+    #[inline(always)]
+    pub fn instrument(&mut self, id: InstrumentId) -> Orders<'_, 's, N> {
+        let account = &mut self.portfolio.instruments[id.0];
+        Orders::new(
+            account.direction(),
+            account.n_entries,
+            self.max_pyramiding,
+            self.bars.get(id).close,
+            self.n_orders.scope_mut(id.0),
+            &mut account.orders,
+        )
+    }
+
+    #[inline(always)]
+    pub fn bars(&self) -> &InstrumentBars {
+        self.bars
+    }
+
+    #[inline(always)]
+    pub fn get(&self) -> &Portfolio<N> {
+        self.portfolio
+    }
+
+    #[inline(always)]
+    pub fn get_mut(&mut self) -> &mut Portfolio<N> {
+        self.portfolio
+    }
+}
+
+impl<'w, 's, const N: usize> BacktestingParams<'w, 's, N> {
+    /// Fills orders of every instrument on its bar. Instruments are traded in order of their ids,
+    /// each one sees the balance left by the previous one. Orders sent with `Orders` are canceled.
+    /// Events and base metrics of each instrument are sent and written in its `EntityScope`,
+    /// `Balance` of an instrument is the starting balance plus its profit and loss.
+    #[inline(always)]
+    pub fn trade_portfolio(&mut self, portfolio: &mut Portfolio<N>, bars: &InstrumentBars) {
+        self.events.cancel_all(self.orders.get_mut());
+        let mut balance = *self.balance;
+        for (i, bar) in bars.0.iter().enumerate() {
+            let locked = portfolio.locked(i);
+            let other_pnl = portfolio.other_pnl(i, bars);
+            let account = &mut portfolio.instruments[i];
+            let mut state = CoreState {
+                position: account.position,
+                entry_price: account.entry_price,
+                balance,
+                exit_price: account.exit_price,
+                n_entries: account.n_entries,
+                max_pyramiding: self.max_pyramiding.0,
+                margin: **self.margin,
+                locked,
+                other_pnl,
+            };
+            self.events.set_scope(Some(i));
+            self.events.fill(
+                &mut state,
+                &mut account.orders,
+                bar.high,
+                bar.low,
+                bar.close,
+                **self.slippage,
+                **self.fee,
+            );
+            account.update(&state, balance);
+            **self.balance.scope_mut(i) += state.balance - balance;
+            **self.position.scope_mut(i) = state.position;
+            **self.entry_price.scope_mut(i) = state.entry_price;
+            **self.exit_price.scope_mut(i) = state.exit_price;
+            **self.n_entries.scope_mut(i) = state.n_entries;
+            balance = state.balance;
+        }
+        self.events.set_scope(None);
+        *self.balance = balance;
+    }
+}
+
+#[system]
+fn init_portfolio(
+    mut orders: PortfolioOrders,
+    scope: &mut EntityScope,
+    starting_balance: Res<StartingBalance>,
+    mut balance: Metric<Balance>,
+    mut prev_balance: Metric<PrevBalance>,
+) {
+    let n_instruments = orders.bars.0.len();
+    orders.portfolio.instruments = vec![InstrumentAccount::default(); n_instruments];
+    scope.len = n_instruments;
+    for i in 0..n_instruments {
+        **balance.scope_mut(i) = starting_balance.0;
+        **prev_balance.scope_mut(i) = starting_balance.0;
+    }
+}
+
+#[system]
+fn load_bars<P: Nest + 'static>(
+    instruments: In<Vec<Series<P>>>,
+    index: LoopIndex,
+    mut bars: Res<InstrumentBars>,
+) {
+    for (bar, series) in bars.0.iter_mut().zip(instruments.iter()) {
+        let row = &series[*index];
+        *bar = Bar {
+            high: row.field::<HighResource>().0,
+            low: row.field::<LowResource>().0,
+            close: row.field::<CloseResource>().0,
+            volume: row.get::<VolumeResource>().map_or(0., |x| x.0),
+        };
+    }
+}
+
+#[system]
+fn trade(mut params: BacktestingParams, orders: PortfolioOrders) {
+    params.trade_portfolio(orders.portfolio, orders.bars);
+    // Orders sent with `Orders` were canceled, the account only counts the ones of instruments.
+    let n_orders = orders.n_orders.scopes().iter().map(Value::get).sum();
+    *orders.n_orders.metric = n_orders;
+}
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+
+    use super::*;
+    use crate::{
+        backtest::BacktestPlugin, block_relays::UpdateRelay, simulation_relays::SimulationEnding,
+        stages::Signal, CorePlugin,
+    };
+
+    #[system]
+    fn open_and_close(mut orders: PortfolioOrders, index: LoopIndex) {
+        for id in [InstrumentId(0), InstrumentId(1)] {
+            match *index {
+                1 => {
+                    orders.instrument(id).send(MarketOpenLong::absolute(1.));
+                },
+                3 => {
+                    orders.instrument(id).send(MarketCloseLong::full());
+                },
+                _ => {},
+            }
+        }
+    }
+
+    #[test]
+    fn instrument_metrics() {
+        // The first instrument rises from 100 to 110, the second one falls to 90.
+        let bars = |closes: [f32; 5]| -> Vec<f32> {
+            closes
+                .iter()
+                .flat_map(|&close| [close, close, close, 1.])
+                .collect()
+        };
+        let rising = bars([100., 100., 110., 110., 110.]);
+        let falling = bars([100., 100., 90., 90., 90.]);
+        let series = |data: &[f32]| unsafe {
+            Series::<hlcv::HlcvInputNest>::new(data.as_ptr() as *const u8)
+        };
+        let mut metrics = vec![0u8; 1024 * 1024];
+        let builder = EcsBuilderStruct::new::<_, 6>(
+            crate::stages::BacktestSchedule::builder(),
+            EntitiesBuilderStruct1::new(),
+        )
+        .add_plugin(CorePlugin {
+            loop_start_bound: 0,
+            loop_end_bound_excluded: 5,
+        })
+        .add_plugin(MetricsPlugin::new(metrics.as_mut_ptr(), 5))
+        .add_plugin(PortfolioBacktestPlugin {
+            timeframe_s: 60,
+            instruments: vec![series(&rising), series(&falling)],
+            backtest_plugin: BacktestPlugin {
+                inputs: series(&rising),
+                starting_balance: 1000.,
+                slippage: Slippage::Relative(0.),
+                fee: Fee::RelativeToVolume(0.),
+                max_pyramiding: 1,
+                margin: Default::default(),
+                inputs_marker: PhantomData::<hlcv::HlcvInput>,
+            },
+        });
+        let mut ecs = MetricsBuilderStruct::new(builder)
+            .add_metric::<SimulationEnding, UpdateRelay, Always, Balance>()
+            .add_metric::<SimulationEnding, UpdateRelay, Always, NTrades>()
+            .finish()
+            .add_system(open_and_close::new(), Signal::new())
+            .build();
+        ecs.run();
+        let balance = ecs.component::<MetricComponent<Balance>>(Entity(0));
+        assert_eq!(balance.get(), 1000.);
+        assert_eq!(balance.scope(0).map(Value::get), Some(1010.));
+        assert_eq!(balance.scope(1).map(Value::get), Some(990.));
+        // An open and a close of each instrument.
+        let n_trades = ecs.component::<MetricComponent<NTrades>>(Entity(0));
+        assert_eq!(n_trades.get(), 4);
+        assert_eq!(n_trades.scope(0).map(Value::get), Some(2));
+        assert_eq!(n_trades.scope(1).map(Value::get), Some(2));
+        let n_orders = ecs.component::<MetricComponent<NOrders>>(Entity(0));
+        assert_eq!(n_orders.get(), 4);
+        assert_eq!(n_orders.scope(0).map(Value::get), Some(2));
+    }
+
+    #[test]
+    fn equity() {
+        let mut portfolio = Portfolio::<2> {
+            instruments: vec![InstrumentAccount::default(); 2],
+        };
+        portfolio.instruments[0].position = 2.;
+        portfolio.instruments[0].entry_price = 10.;
+        portfolio.instruments[1].position = -1.;
+        portfolio.instruments[1].entry_price = 50.;
+        assert_eq!(portfolio.locked(0), 50.);
+        assert_eq!(portfolio.locked(1), 20.);
+        let bar = |close| Bar {
+            close,
+            ..Default::default()
+        };
+        let bars = InstrumentBars(vec![bar(12.), bar(40.)]);
+        assert_eq!(portfolio.equity(100., &bars), 100. + 4. + 10.);
+        assert_eq!(portfolio.other_pnl(0, &bars), 10.);
+        assert_eq!(portfolio.other_pnl(1, &bars), 4.);
+    }
+
+    #[test]
+    fn win_rate_without_trades() {
+        assert_eq!(InstrumentAccount::<1>::default().win_rate(), 0.);
+    }
+}
@@ -16,6 +16,10 @@ pub use crate::{
     backtest::{
         hlcv_backtest::HlcvBacktestPlugin,
         orderflow_backtest::OrderflowBacktestPlugin,
+        portfolio_backtest::{
+            Bar, InstrumentAccount, InstrumentBars, Portfolio, PortfolioBacktestPlugin,
+            PortfolioOrders,
+        },
         BacktestPlugin,
     },
     components::*,
@@ -26,15 +30,17 @@ pub use crate::{
     metrics::{Sum, *},
     metrics_builder::*,
     order::*,
-    orders::Orders,
-    param::{HyperParam, ParamConfig},
-    plugin::CorePlugin,
+    orders::{Orders, OrdersComponent},
+    param::{HyperParam, ParamConfig, ParamRange, StrategyParams},
+    plugin::{CorePlugin, DefaultBacktestPlugins, MetricsPlugin},
     prev::Prev,
     resources::*,
     schema::Reader,
     series::Series,
+    sweep::{Sweep, SweepRow, SweepSlots},
     // ta::yata::Ind,
     value::Value,
+    walk_forward::{EquityCurve, EquityCurvePlugin, WalkForward},
 };
 
 extern crate no_std_compat as std;
@@ -59,6 +65,8 @@ pub mod resources;
 mod schema;
 mod series;
 pub mod stages;
+pub mod sweep;
 pub mod ta;
 pub mod types;
 mod value;
+pub mod walk_forward;
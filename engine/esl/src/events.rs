@@ -1,4 +1,6 @@
-use crate::types::{Direction, OrderId, PositionAction, PositionSize};
+use inception::Event;
+
+use crate::types::{Direction, OrderId, PositionAction, PositionSize, Trail};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct OrderCreated {
@@ -34,6 +36,22 @@ pub struct PositionClosed {
     pub size: f32,
 }
 
+/// Position was force closed at `price` because the equity fell below maintenance margin.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PositionLiquidated {
+    pub direction: Direction,
+    pub size: f32,
+    pub price: f32,
+}
+
+impl Event for OrderCreated {}
+impl Event for OrderExecuted {}
+impl Event for OrderCanceled {}
+impl Event for PositionUpdated {}
+impl Event for PositionOpened {}
+impl Event for PositionClosed {}
+impl Event for PositionLiquidated {}
+
 #[non_exhaustive]
 /// Not for public use. Use specific orders from this module.
 #[derive(Clone, Debug, PartialEq)]
@@ -50,4 +68,61 @@ pub enum OrderPlaced {
         direction: Direction,
         trigger: f32,
     },
+    /// Rests until the bar crosses `limit`, then fills at `limit`.
+    Limit {
+        size: PositionSize,
+        position_action: PositionAction,
+        direction: Direction,
+        limit: f32,
+    },
+    /// Stop market whose `trigger` follows the bar high (long) or low (short) by `trail`. It only
+    /// moves in favour of the position.
+    TrailingStop {
+        size: PositionSize,
+        position_action: PositionAction,
+        direction: Direction,
+        trail: Trail,
+        trigger: f32,
+    },
+    /// Becomes `Limit` once the bar crosses `trigger`.
+    StopLimit {
+        size: PositionSize,
+        position_action: PositionAction,
+        direction: Direction,
+        trigger: f32,
+        limit: f32,
+    },
+}
+
+impl OrderPlaced {
+    #[inline(always)]
+    pub fn action(&self) -> (PositionAction, Direction) {
+        match self {
+            OrderPlaced::Market {
+                position_action,
+                direction,
+                ..
+            }
+            | OrderPlaced::StopMarket {
+                position_action,
+                direction,
+                ..
+            }
+            | OrderPlaced::TrailingStop {
+                position_action,
+                direction,
+                ..
+            }
+            | OrderPlaced::Limit {
+                position_action,
+                direction,
+                ..
+            }
+            | OrderPlaced::StopLimit {
+                position_action,
+                direction,
+                ..
+            } => (*position_action, *direction),
+        }
+    }
 }
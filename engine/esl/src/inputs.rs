@@ -30,6 +30,15 @@ pub mod hlcv {
         CloseResource,
         VolumeResource
     );
+    input_layout!(
+        HlcvFundingInputNest,
+        HlcvFundingInput,
+        HighResource,
+        LowResource,
+        CloseResource,
+        VolumeResource,
+        FundingRateResource
+    );
     input_layout!(
         HlcInputNest,
         HlcInput,
@@ -53,6 +62,9 @@ pub mod hlcv {
     }
     #[input]
     pub struct Volume(pub f32);
+    /// Funding rate of a perpetual swap that is paid at the next funding timestamp.
+    #[input]
+    pub struct FundingRate(pub f32);
 }
 
 pub mod orderflow {
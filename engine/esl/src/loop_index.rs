@@ -17,6 +17,7 @@ impl<'w, 's, const N: usize> LoopIndex<'w, 's, N> {
 impl<'w, 's, const N: usize> SystemParam for LoopIndex<'w, 's, N> {
     // cast lifetimes
     type Item<'world, 'state, Wrld: World> = LoopIndex<'world, 'state, N>;
+    type Resources = RequiredResource<LoopIndexResource>;
     type State = ();
 
     type Build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static> =
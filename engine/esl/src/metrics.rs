@@ -11,7 +11,7 @@ use ergnomics::*;
 use inception::*;
 use num_traits::{Float, FromPrimitive};
 
-use crate::{execution_order::*, simulation_relays::*, types::Direction, value::Value, *};
+use crate::{simulation_relays::*, types::Direction, value::Value, *};
 
 macro_rules! impl_blanket_metric {
     ($metric:ident, $relay:ident, $inner:ident) => {
@@ -20,7 +20,6 @@ macro_rules! impl_blanket_metric {
         pub struct $metric($inner);
         #[impl_metric]
         impl MetricTrait for $metric {
-            type ExecutionOrder = Order0;
             type SimulationRelay = $relay;
 
             #[inline(always)]
@@ -36,6 +35,7 @@ impl_blanket_metric!(Position, BacktestEnding, f32);
 impl_blanket_metric!(NOrders, SimulationEnding, u32);
 impl_blanket_metric!(EntryPrice, BacktestEnding, f32);
 impl_blanket_metric!(ExitPrice, BacktestEnding, f32);
+impl_blanket_metric!(NEntries, BacktestEnding, u32);
 
 impl Position {
     #[inline(always)]
@@ -72,13 +72,12 @@ impl Position {
 }
 
 macro_rules! max_metric {
-    ($max:ident, $cur:tt, $order:ty, $simulation:ty) => {
+    ($max:ident, $cur:tt, $simulation:ty) => {
         #[metric]
         #[derive(Default)]
         pub struct $max(f32);
         #[impl_metric]
         impl MetricTrait for $max {
-            type ExecutionOrder = $order;
             type SimulationRelay = $simulation;
 
             #[inline(always)]
@@ -89,7 +88,7 @@ macro_rules! max_metric {
     };
 }
 
-max_metric!(MaxBalance, Balance, Order1, SimulationEnding);
+max_metric!(MaxBalance, Balance, SimulationEnding);
 
 #[metric]
 #[derive(Default, Debug)]
@@ -108,7 +107,7 @@ impl MetricTrait for Drawdown {
     }
 }
 
-max_metric!(MaxDrawdown, Drawdown, Order2, SimulationEnding);
+max_metric!(MaxDrawdown, Drawdown, SimulationEnding);
 
 #[metric]
 #[derive(Default)]
@@ -316,7 +315,6 @@ impl MetricTrait for Cagr {
 pub struct Sum<M: MetricTrait>(M::Value);
 #[impl_metric]
 impl<M: MetricTrait> MetricTrait for Sum<M> {
-    type ExecutionOrder = <M::ExecutionOrder as ExecutionOrder>::Next;
     type SimulationRelay = M::SimulationRelay;
 
     #[inline(always)]
@@ -330,7 +328,6 @@ impl<M: MetricTrait> MetricTrait for Sum<M> {
 pub struct Max<M: MetricTrait>(M::Value);
 #[impl_metric]
 impl<M: MetricTrait> MetricTrait for Max<M> {
-    type ExecutionOrder = <M::ExecutionOrder as ExecutionOrder>::Next;
     type SimulationRelay = M::SimulationRelay;
 
     #[inline(always)]
@@ -346,7 +343,6 @@ impl<M: MetricTrait> MetricTrait for Max<M> {
 pub struct Min<M: MetricTrait>(M::Value);
 #[impl_metric]
 impl<M: MetricTrait> MetricTrait for Min<M> {
-    type ExecutionOrder = <M::ExecutionOrder as ExecutionOrder>::Next;
     type SimulationRelay = M::SimulationRelay;
 
     #[inline(always)]
@@ -390,9 +386,11 @@ impl<M: MetricTrait> Default for Count<M> {
 }
 #[impl_metric]
 impl<M: MetricTrait> MetricTrait for Count<M> {
-    type ExecutionOrder = <M::ExecutionOrder as ExecutionOrder>::Next;
     type SimulationRelay = M::SimulationRelay;
 
+    // Doesn't read `M`, but counts its updates.
+    const DEPS: &'static [&'static str] = &[<MetricLabel<M> as SystemLabel>::NAME];
+
     #[inline(always)]
     fn update(mut count: Metric<Count<M>>) {
         *count += 1;
@@ -404,7 +402,6 @@ impl<M: MetricTrait> MetricTrait for Count<M> {
 pub struct Mean<M: MetricTrait>(M::Value);
 #[impl_metric]
 impl<M: MetricTrait> MetricTrait for Mean<M> {
-    type ExecutionOrder = <<M::ExecutionOrder as ExecutionOrder>::Next as ExecutionOrder>::Next;
     type SimulationRelay = M::SimulationRelay;
 
     #[inline(always)]
@@ -419,7 +416,6 @@ impl<M: MetricTrait> MetricTrait for Mean<M> {
 pub struct Stddev<M: MetricTrait>(M::Value);
 #[impl_metric]
 impl<M: MetricTrait<Value = f32>> MetricTrait for Stddev<M> {
-    type ExecutionOrder = <<Mean<M> as MetricTrait>::ExecutionOrder as ExecutionOrder>::Next;
     type SimulationRelay = M::SimulationRelay;
 
     #[inline(always)]
@@ -444,12 +440,7 @@ impl<M: MetricTrait<Value = f32>> MetricTrait for Stddev<M> {
 #[derive(Default)]
 pub struct CoefficientOfCorrelation<M: MetricTrait>(M::Value);
 #[impl_metric]
-impl<M: MetricTrait<Value = f32>> MetricTrait for CoefficientOfCorrelation<M>
-where
-    MaxExecutionOrder<(Order0, <M as MetricTrait>::ExecutionOrder)>: ExecutionOrder,
-{
-    type ExecutionOrder =
-        <<Sum<Squared<M>> as MetricTrait>::ExecutionOrder as ExecutionOrder>::Next;
+impl<M: MetricTrait<Value = f32>> MetricTrait for CoefficientOfCorrelation<M> {
     type SimulationRelay = M::SimulationRelay;
 
     #[inline(always)]
@@ -500,12 +491,7 @@ impl<M0: MetricTrait, M1: MetricTrait<Value = M0::Value>> Default for Mul<M0, M1
     }
 }
 #[impl_metric]
-impl<M0: MetricTrait, M1: MetricTrait<Value = M0::Value>> MetricTrait for Mul<M0, M1>
-where
-    MaxExecutionOrder<(M0::ExecutionOrder, M1::ExecutionOrder)>: ExecutionOrder,
-{
-    type ExecutionOrder =
-        <MaxExecutionOrder<(M0::ExecutionOrder, M1::ExecutionOrder)> as ExecutionOrder>::Next;
+impl<M0: MetricTrait, M1: MetricTrait<Value = M0::Value>> MetricTrait for Mul<M0, M1> {
     type SimulationRelay = M0::SimulationRelay;
 
     #[inline(always)]
@@ -519,7 +505,6 @@ where
 pub struct Squared<M: MetricTrait>(M::Value);
 #[impl_metric]
 impl<M: MetricTrait> MetricTrait for Squared<M> {
-    type ExecutionOrder = <M::ExecutionOrder as ExecutionOrder>::Next;
     type SimulationRelay = M::SimulationRelay;
 
     #[inline(always)]
@@ -2,8 +2,8 @@ use core::{
     marker::PhantomData,
     ops::{Deref, DerefMut},
 };
+use std::prelude::v1::*;
 
-use all_tuples::repeat;
 pub use conditions::*;
 use esl::*;
 
@@ -13,86 +13,41 @@ pub mod block_relays;
 pub mod conditions;
 pub mod simulation_relays;
 
-pub mod execution_order {
-    //! When to execute a system based on its dependencies. System with no dependencies has
-    //! `Order0`, system with one dependency in a chain has `Order1`, etc. Handled automatically
-    //! by `impl_metric` macro.
-    use super::*;
-
-    pub struct Order0;
-    pub struct Order1;
-    pub struct Order2;
-    pub struct Order3;
-    pub struct Order4;
-    pub struct Order5;
-    pub struct Order6;
-    pub struct Order7;
-    pub struct Order8;
-    pub struct Order9;
-    pub enum InvalidOrder {}
-
-    impl ExecutionOrder for Order0 {
-        type Next = Order1;
-    }
-
-    impl ExecutionOrder for Order1 {
-        type Next = Order2;
-    }
-
-    impl ExecutionOrder for Order2 {
-        type Next = Order3;
-    }
-
-    impl ExecutionOrder for Order3 {
-        type Next = Order4;
-    }
-
-    impl ExecutionOrder for Order4 {
-        type Next = Order5;
-    }
-
-    impl ExecutionOrder for Order5 {
-        type Next = Order6;
-    }
-
-    impl ExecutionOrder for Order6 {
-        type Next = Order7;
-    }
-
-    impl ExecutionOrder for Order7 {
-        type Next = Order8;
-    }
-
-    impl ExecutionOrder for Order8 {
-        type Next = Order9;
-    }
-
-    impl ExecutionOrder for Order9 {
-        type Next = InvalidOrder;
-    }
-
-    impl ExecutionOrder for InvalidOrder {
-        type Next = InvalidOrder;
-    }
-
-    pub struct MaxExecutionOrder<T>(PhantomData<T>);
+/// Label of the system that updates metric `M`. Metrics in a `PostTrade`/`PostBlock` stage run
+/// after the metrics they depend on.
+pub struct MetricLabel<M>(PhantomData<M>);
 
-    esl_macros::impl_max_execution_order!();
-}
+impl<M: 'static> SystemLabel for MetricLabel<M> {}
 
+/// Metric of the entity, or of its current `EntityScope` when one is set.
 pub struct Metric<'w, 's, M, const N: usize> {
     component: &'w mut MetricComponent<M>,
+    scope: Option<usize>,
     _marker: PhantomSystemParam<'s, 'w, N>,
 }
 
 impl<'w, 's, M, const N: usize> Metric<'w, 's, M, N> {
     #[inline(always)]
     pub fn metric(&self) -> &M {
-        &self.component.metric
+        self.component.scoped(self.scope)
+    }
+
+    /// Metric of every scope of the entity that it was updated in.
+    #[inline(always)]
+    pub fn scopes(&self) -> &[M] {
+        self.component.scopes()
     }
 }
 
-impl<'w, 's, M: 'static, const N: usize> SystemParam for Metric<'w, 's, M, N> {
+impl<'w, 's, M: Clone + Default, const N: usize> Metric<'w, 's, M, N> {
+    /// Metric of `scope` of the entity, e.g. of an instrument of a portfolio.
+    #[inline(always)]
+    pub fn scope_mut(&mut self, scope: usize) -> &mut M {
+        self.component.scope_mut(scope)
+    }
+}
+
+impl<'w, 's, M: Clone + Default + 'static, const N: usize> SystemParam for Metric<'w, 's, M, N> {
     // cast lifetimes
     type Item<'world, 'state, Wrld: World> = Metric<'world, 'state, M, N>;
     type State = ();
@@ -113,8 +68,16 @@ impl<'w, 's, M: 'static, const N: usize> SystemParam for Metric<'w, 's, M, N> {
         SB: SystemParamNameMapper,
         E: EntityFetch,
     {
+        let scope = entity
+            .get_component::<EntityScope>()
+            .and_then(|x| x.current);
+        let component = entity.get_component_mut::<MetricComponent<M>>()?;
+        if let Some(scope) = scope {
+            component.scope_mut(scope);
+        }
         Some(Metric {
-            component: entity.get_component_mut()?,
+            component,
+            scope,
             _marker: PhantomSystemParam::default(),
         })
     }
@@ -132,7 +95,7 @@ impl<'w, 's, M: Value, const N: usize> Value for Metric<'w, 's, M, N> {
 
     #[inline(always)]
     fn get(&self) -> Self::Value {
-        self.component.get()
+        self.metric().get()
     }
 }
 
@@ -141,20 +104,25 @@ impl<'w, 's, M: Deref, const N: usize> Deref for Metric<'w, 's, M, N> {
 
     #[inline(always)]
     fn deref(&self) -> &Self::Target {
-        self.component.deref()
+        self.metric().deref()
     }
 }
 
 impl<'w, 's, M: DerefMut, const N: usize> DerefMut for Metric<'w, 's, M, N> {
     #[inline(always)]
     fn deref_mut(&mut self) -> &mut Self::Target {
-        self.component.deref_mut()
+        self.component.scoped_mut(self.scope).deref_mut()
     }
 }
 
+/// `metric` is the value of the whole entity. Entities with an `EntityScope` also keep a value for
+/// each scope, e.g. for each instrument of a portfolio, indexed by `InstrumentId`. Only the entity
+/// value is tracked and stored in memory, so metrics that read `Tracks` are entity-wide even when
+/// they are updated in a scope.
 #[derive(Clone, Default)]
 pub struct MetricComponent<M> {
     pub metric: M,
+    scopes: Vec<M>,
     tracker_id: usize,
 }
 
@@ -163,11 +131,51 @@ impl<M: Default> MetricComponent<M> {
     pub fn new(tracker_id: usize) -> Self {
         Self {
             metric: M::default(),
+            scopes: Vec::new(),
             tracker_id,
         }
     }
 }
 
+impl<M> MetricComponent<M> {
+    /// Value of `scope` of the entity, `None` if the metric wasn't updated in it.
+    #[inline(always)]
+    pub fn scope(&self, scope: usize) -> Option<&M> {
+        self.scopes.get(scope)
+    }
+
+    #[inline(always)]
+    pub fn scopes(&self) -> &[M] {
+        &self.scopes
+    }
+
+    #[inline(always)]
+    fn scoped(&self, scope: Option<usize>) -> &M {
+        match scope {
+            Some(scope) => &self.scopes[scope],
+            None => &self.metric,
+        }
+    }
+
+    #[inline(always)]
+    fn scoped_mut(&mut self, scope: Option<usize>) -> &mut M {
+        match scope {
+            Some(scope) => &mut self.scopes[scope],
+            None => &mut self.metric,
+        }
+    }
+}
+
+impl<M: Clone + Default> MetricComponent<M> {
+    #[inline(always)]
+    pub fn scope_mut(&mut self, scope: usize) -> &mut M {
+        if self.scopes.len() <= scope {
+            self.scopes.resize(scope + 1, M::default());
+        }
+        &mut self.scopes[scope]
+    }
+}
+
 impl<M: Value> Value for MetricComponent<M> {
     type Value = M::Value;
 
@@ -193,10 +201,6 @@ impl<M: DerefMut> DerefMut for MetricComponent<M> {
     }
 }
 
-pub trait ExecutionOrder {
-    type Next: ExecutionOrder;
-}
-
 #[system_param]
 /// Records the value of the metric and stores it in memory for later access.
 pub struct Tracks<M: MetricTrait> {
@@ -275,27 +279,26 @@ impl<'w, 's, M: MetricTrait, const N: usize> Tracks<'w, 's, M, N> {
 // }
 //
 // impl<T0: MetricTrait> MetricPlugin for (T0,) {
-//     type ExecutionOrder = execution_order::Order0;
 //     type MetricDeps = (T0::MetricDeps,);
 //     type SimulationRelay = simulation_relays::SimulationEnding;
 //     type TrackerDeps = (T0::TrackerDeps,);
 //
 //     type AddMetrics<
 //         SimRelay: SimulationRelay<Self::SimulationRelay>,
-//         PosRelay: MetricsBuilderRelay<Self::ExecutionOrder>,
+//         PosRelay: MetricsBuilderRelay,
 //         C: Condition,
 //         B: MetricsBuilder,
 //     > = impl MetricsBuilder;
 //     type AddTrackers<
 //         SimRelay: SimulationRelay<Self::SimulationRelay>,
-//         PosRelay: MetricsBuilderRelay<Self::ExecutionOrder>,
+//         PosRelay: MetricsBuilderRelay,
 //         C: Condition,
 //         B: MetricsBuilder,
 //     > = impl MetricsBuilder;
 //
 //     fn add_metrics<
 //         SimRelay: SimulationRelay<Self::SimulationRelay>,
-//         PosRelay: MetricsBuilderRelay<Self::ExecutionOrder>,
+//         PosRelay: MetricsBuilderRelay,
 //         C: Condition,
 //         B: MetricsBuilder,
 //     >(
@@ -307,7 +310,7 @@ impl<'w, 's, M: MetricTrait, const N: usize> Tracks<'w, 's, M, N> {
 //
 //     fn add_trackers<
 //         SimRelay: SimulationRelay<Self::SimulationRelay>,
-//         PosRelay: MetricsBuilderRelay<Self::ExecutionOrder>,
+//         PosRelay: MetricsBuilderRelay,
 //         C: Condition,
 //         B: MetricsBuilder,
 //     >(
@@ -321,7 +324,9 @@ impl<'w, 's, M: MetricTrait, const N: usize> Tracks<'w, 's, M, N> {
 pub trait SimulationRelayMarker {}
 pub trait MetricTrait: Value + Default + Copy + 'static {
     type UpdateParams<'w, 's, W: World, const N: usize>: SystemParam;
-    type ExecutionOrder: ExecutionOrder;
+    /// Labels of metrics that must be updated before this one. Handled automatically by
+    /// `impl_metric` macro.
+    const DEPS: &'static [&'static str];
     // type MetricDeps: MetricPlugin;
     // type TrackerDeps: MetricPlugin;
     type SimulationRelay: SimulationRelayMarker;
@@ -332,7 +337,7 @@ pub trait MetricTrait: Value + Default + Copy + 'static {
 pub trait MetricKindRelay {
     type Add<
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        PosRelay: MetricsBuilderRelay<M::ExecutionOrder>,
+        PosRelay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
         B: MetricsBuilder,
@@ -340,7 +345,7 @@ pub trait MetricKindRelay {
 
     fn add<
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        PosRelay: MetricsBuilderRelay<M::ExecutionOrder>,
+        PosRelay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
         B: MetricsBuilder,
@@ -353,7 +358,7 @@ pub struct MetricRelay;
 impl MetricKindRelay for MetricRelay {
     type Add<
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        PosRelay: MetricsBuilderRelay<M::ExecutionOrder>,
+        PosRelay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
         B: MetricsBuilder,
@@ -362,7 +367,7 @@ impl MetricKindRelay for MetricRelay {
     #[inline(always)]
     fn add<
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        PosRelay: MetricsBuilderRelay<M::ExecutionOrder>,
+        PosRelay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
         B: MetricsBuilder,
@@ -378,7 +383,7 @@ pub struct TrackerRelay;
 impl MetricKindRelay for TrackerRelay {
     type Add<
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        PosRelay: MetricsBuilderRelay<M::ExecutionOrder>,
+        PosRelay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
         B: MetricsBuilder,
@@ -387,7 +392,7 @@ impl MetricKindRelay for TrackerRelay {
     #[inline(always)]
     fn add<
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        PosRelay: MetricsBuilderRelay<M::ExecutionOrder>,
+        PosRelay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
         B: MetricsBuilder,
@@ -401,30 +406,33 @@ impl MetricKindRelay for TrackerRelay {
 pub trait SimulationRelay<Sim> {
     type AddMetric<
         B: MetricsBuilder,
-        Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+        Relay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
     >: MetricsBuilder;
     type AddTracker<
         B: MetricsBuilder,
-        Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+        Relay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
     >: MetricsBuilder;
     fn add_metric<B, Relay, C, M>(builder: B) -> Self::AddMetric<B, Relay, C, M>
     where
         B: MetricsBuilder,
-        Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+        Relay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait;
     fn add_tracker<B, Relay, C, M>(builder: B) -> Self::AddTracker<B, Relay, C, M>
     where
         B: MetricsBuilder,
-        Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+        Relay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait;
 }
 
+/// Decides whether a metric is updated for an entity. Each metric is updated by a system that
+/// checks the condition for each entity separately. `SystemBuilder::run_if` can't replace it
+/// because it gates a whole system and passes when the condition passes for any entity.
 pub trait Condition: 'static {
     type Params<'w, 's, W: World, const N: usize>: SystemParam;
     fn run<'w, 's, W: World, const N: usize>(params: Self::Params<'w, 's, W, N>) -> Skip;
@@ -436,119 +444,56 @@ pub enum Skip {
     False,
 }
 
-pub struct MetricsBuilderStruct<
-    B: EcsBuilder,
-    Mems,
-    UM0,
-    UM1,
-    UM2,
-    UM3,
-    UM4,
-    UM5,
-    UM6,
-    UM7,
-    UM8,
-    EM0,
-    EM1,
-    EM2,
-    EM3,
-    EM4,
-    EM5,
-    EM6,
-    EM7,
-    EM8,
-> {
+pub struct MetricsBuilderStruct<B: EcsBuilder, Mems, UM, BM> {
     builder: B,
     field_offset: usize,
     n_trackers: usize,
     mems: Mems,
-    update_metrics0: UM0,
-    update_metrics1: UM1,
-    update_metrics2: UM2,
-    update_metrics3: UM3,
-    update_metrics4: UM4,
-    update_metrics5: UM5,
-    update_metrics6: UM6,
-    update_metrics7: UM7,
-    update_metrics8: UM8,
-    block_metrics0: EM0,
-    block_metrics1: EM1,
-    block_metrics2: EM2,
-    block_metrics3: EM3,
-    block_metrics4: EM4,
-    block_metrics5: EM5,
-    block_metrics6: EM6,
-    block_metrics7: EM7,
-    block_metrics8: EM8,
-}
-
-macro_rules! def_metrics_and_trackers {
-    (
-        $update_metric:ident,
-        $block_metric:ident,
-        $update_tracker:ident,
-        $block_tracker:ident,
-        $update_metric_fn:ident,
-        $block_metric_fn:ident,
-        $update_tracker_fn:ident,
-        $block_tracker_fn:ident
-    ) => {
-        type $update_metric<C: Condition, M: MetricTrait>: MetricsBuilder;
-        type $block_metric<C: Condition, M: MetricTrait>: MetricsBuilder;
-        type $update_tracker<C: Condition, M: MetricTrait>: MetricsBuilder;
-        type $block_tracker<C: Condition, M: MetricTrait>: MetricsBuilder;
-
-        fn $update_metric_fn<C: Condition, M: MetricTrait>(self) -> Self::$update_metric<C, M>;
-        fn $block_metric_fn<C: Condition, M: MetricTrait>(self) -> Self::$block_metric<C, M>;
-        fn $update_tracker_fn<C: Condition, M: MetricTrait>(self) -> Self::$update_tracker<C, M>;
-        fn $block_tracker_fn<C: Condition, M: MetricTrait>(self) -> Self::$block_tracker<C, M>;
-    };
+    update_metrics: UM,
+    block_metrics: BM,
 }
 
 pub trait MetricsBuilder {
     type AddMetric<
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+        Relay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
     >: MetricsBuilder;
     type AddTracker<
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+        Relay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
     >: MetricsBuilder;
-    repeat!(
-        def_metrics_and_trackers,
-        0,
-        9,
-        AddUpdateMetric,
-        AddBlockMetric,
-        AddUpdateTracker,
-        AddBlockTracker,
-        add_update_metric,
-        add_block_metric,
-        add_update_tracker,
-        add_block_tracker
-    );
+    type AddUpdateMetric<C: Condition, M: MetricTrait>: MetricsBuilder;
+    type AddBlockMetric<C: Condition, M: MetricTrait>: MetricsBuilder;
+    type AddUpdateTracker<C: Condition, M: MetricTrait>: MetricsBuilder;
+    type AddBlockTracker<C: Condition, M: MetricTrait>: MetricsBuilder;
     type Finish: EcsBuilder;
     fn add_metric<SimRelay, Relay, C, M>(self) -> Self::AddMetric<SimRelay, Relay, C, M>
     where
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+        Relay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait;
     fn add_tracker<SimRelay, Relay, C, M>(self) -> Self::AddTracker<SimRelay, Relay, C, M>
     where
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+        Relay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait;
+    /// Updates the metric in `PostTrade` stage.
+    fn add_update_metric<C: Condition, M: MetricTrait>(self) -> Self::AddUpdateMetric<C, M>;
+    /// Updates the metric in `PostBlock` stage.
+    fn add_block_metric<C: Condition, M: MetricTrait>(self) -> Self::AddBlockMetric<C, M>;
+    fn add_update_tracker<C: Condition, M: MetricTrait>(self) -> Self::AddUpdateTracker<C, M>;
+    fn add_block_tracker<C: Condition, M: MetricTrait>(self) -> Self::AddBlockTracker<C, M>;
     fn skip<M: MetricTrait>(&mut self);
     fn finish(self) -> Self::Finish;
 }
 
-pub trait MetricsBuilderRelay<ExecutionOrder> {
+pub trait MetricsBuilderRelay {
     type AddMetric<B: MetricsBuilder, C: Condition, M: MetricTrait>: MetricsBuilder;
     type AddTracker<B: MetricsBuilder, C: Condition, M: MetricTrait>: MetricsBuilder;
     fn add_metric<B: MetricsBuilder, C: Condition, M: MetricTrait>(
@@ -562,164 +507,49 @@ pub trait MetricsBuilderRelay<ExecutionOrder> {
         builder
     }
 }
-impl<B: EcsBuilder>
-    MetricsBuilderStruct<
-        B,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-        StackedNest,
-    >
-{
+impl<B: EcsBuilder> MetricsBuilderStruct<B, StackedNest, StackedNest, StackedNest> {
     pub fn new(builder: B) -> Self {
         Self {
             builder,
             mems: StackedNest,
-            update_metrics0: StackedNest,
-            update_metrics1: StackedNest,
-            update_metrics2: StackedNest,
-            update_metrics3: StackedNest,
-            update_metrics4: StackedNest,
-            update_metrics5: StackedNest,
-            update_metrics6: StackedNest,
-            update_metrics7: StackedNest,
-            update_metrics8: StackedNest,
-            block_metrics0: StackedNest,
-            block_metrics1: StackedNest,
-            block_metrics2: StackedNest,
-            block_metrics3: StackedNest,
-            block_metrics4: StackedNest,
-            block_metrics5: StackedNest,
-            block_metrics6: StackedNest,
-            block_metrics7: StackedNest,
-            block_metrics8: StackedNest,
+            update_metrics: StackedNest,
+            block_metrics: StackedNest,
             field_offset: 0,
             n_trackers: 0,
         }
     }
 }
 
-macro_rules! impl_metrics_and_trackers {
-    ($update_metric:ident, $block_metric:ident, $update_tracker:ident, $block_tracker:ident) => {
-        type $update_metric<C: Condition, M: MetricTrait> = impl MetricsBuilder;
-        type $block_metric<C: Condition, M: MetricTrait> = impl MetricsBuilder;
-        type $update_tracker<C: Condition, M: MetricTrait> = impl MetricsBuilder;
-        type $block_tracker<C: Condition, M: MetricTrait> = impl MetricsBuilder;
-    };
-}
-
-impl<
-    B,
-    Mems,
-    UM0,
-    UM1,
-    UM2,
-    UM3,
-    UM4,
-    UM5,
-    UM6,
-    UM7,
-    UM8,
-    EM0,
-    EM1,
-    EM2,
-    EM3,
-    EM4,
-    EM5,
-    EM6,
-    EM7,
-    EM8,
-> MetricsBuilder
-    for MetricsBuilderStruct<
-        B,
-        Mems,
-        UM0,
-        UM1,
-        UM2,
-        UM3,
-        UM4,
-        UM5,
-        UM6,
-        UM7,
-        UM8,
-        EM0,
-        EM1,
-        EM2,
-        EM3,
-        EM4,
-        EM5,
-        EM6,
-        EM7,
-        EM8,
-    >
+impl<B, Mems, UM, BM> MetricsBuilder for MetricsBuilderStruct<B, Mems, UM, BM>
 where
     B: EcsBuilder,
     Mems: MetricMem + 'static,
-    UM0: MetricUpdateBuilder + 'static,
-    UM1: MetricUpdateBuilder + 'static,
-    UM2: MetricUpdateBuilder + 'static,
-    UM3: MetricUpdateBuilder + 'static,
-    UM4: MetricUpdateBuilder + 'static,
-    UM5: MetricUpdateBuilder + 'static,
-    UM6: MetricUpdateBuilder + 'static,
-    UM7: MetricUpdateBuilder + 'static,
-    UM8: MetricUpdateBuilder + 'static,
-    EM0: MetricUpdateBuilder + 'static,
-    EM1: MetricUpdateBuilder + 'static,
-    EM2: MetricUpdateBuilder + 'static,
-    EM3: MetricUpdateBuilder + 'static,
-    EM4: MetricUpdateBuilder + 'static,
-    EM5: MetricUpdateBuilder + 'static,
-    EM6: MetricUpdateBuilder + 'static,
-    EM7: MetricUpdateBuilder + 'static,
-    EM8: MetricUpdateBuilder + 'static,
+    UM: MetricStage,
+    BM: MetricStage,
 {
+    type AddBlockMetric<C: Condition, M: MetricTrait> = impl MetricsBuilder;
+    type AddBlockTracker<C: Condition, M: MetricTrait> = impl MetricsBuilder;
     type AddMetric<
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+        Relay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
     > = impl MetricsBuilder;
     type AddTracker<
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+        Relay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
     > = impl MetricsBuilder;
+    type AddUpdateMetric<C: Condition, M: MetricTrait> = impl MetricsBuilder;
+    type AddUpdateTracker<C: Condition, M: MetricTrait> = impl MetricsBuilder;
     type Finish = impl EcsBuilder;
 
-    repeat!(
-        impl_metrics_and_trackers,
-        0,
-        9,
-        AddUpdateMetric,
-        AddBlockMetric,
-        AddUpdateTracker,
-        AddBlockTracker
-    );
-
-    esl_macros::impl_add_metrics_and_trackers!();
-
     #[inline(always)]
     fn add_metric<SimRelay, Relay, C, M>(self) -> Self::AddMetric<SimRelay, Relay, C, M>
     where
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+        Relay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
     {
@@ -730,13 +560,121 @@ where
     fn add_tracker<SimRelay, Relay, C, M>(self) -> Self::AddTracker<SimRelay, Relay, C, M>
     where
         SimRelay: SimulationRelay<M::SimulationRelay>,
-        Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+        Relay: MetricsBuilderRelay,
         C: Condition,
         M: MetricTrait,
     {
         SimRelay::add_tracker::<Self, Relay, C, M>(self)
     }
 
+    #[inline(always)]
+    fn add_update_metric<C: Condition, M: MetricTrait>(self) -> Self::AddUpdateMetric<C, M> {
+        MetricsBuilderStruct {
+            builder: self
+                .builder
+                .extend_entities(MetricComponent::<M>::default()),
+            update_metrics: Nested::new(
+                MetricUpdateBuilderStruct {
+                    _m: PhantomData::<M>,
+                    _condition: PhantomData::<C>,
+                },
+                self.update_metrics,
+            ),
+            block_metrics: self.block_metrics,
+            mems: Nested::new(
+                MetricMemStruct {
+                    field_offset: self.field_offset,
+                    _m: PhantomData::<M>,
+                },
+                self.mems,
+            ),
+            field_offset: self.field_offset + core::mem::size_of::<M>(),
+            n_trackers: self.n_trackers,
+        }
+    }
+
+    #[inline(always)]
+    fn add_block_metric<C: Condition, M: MetricTrait>(self) -> Self::AddBlockMetric<C, M> {
+        MetricsBuilderStruct {
+            builder: self
+                .builder
+                .extend_entities(MetricComponent::<M>::default()),
+            update_metrics: self.update_metrics,
+            block_metrics: Nested::new(
+                MetricUpdateBuilderStruct {
+                    _m: PhantomData::<M>,
+                    _condition: PhantomData::<C>,
+                },
+                self.block_metrics,
+            ),
+            mems: Nested::new(
+                MetricMemStruct {
+                    field_offset: self.field_offset,
+                    _m: PhantomData::<M>,
+                },
+                self.mems,
+            ),
+            field_offset: self.field_offset + core::mem::size_of::<M>(),
+            n_trackers: self.n_trackers,
+        }
+    }
+
+    #[inline(always)]
+    fn add_update_tracker<C: Condition, M: MetricTrait>(self) -> Self::AddUpdateTracker<C, M> {
+        MetricsBuilderStruct {
+            builder: self
+                .builder
+                .extend_entities(MetricComponent::<M>::new(self.n_trackers)),
+            update_metrics: Nested::new(
+                TrackerUpdateBuilder {
+                    metric_builder: MetricUpdateBuilderStruct {
+                        _m: PhantomData::<M>,
+                        _condition: PhantomData::<C>,
+                    },
+                },
+                self.update_metrics,
+            ),
+            block_metrics: self.block_metrics,
+            mems: Nested::new(
+                MetricMemStruct {
+                    field_offset: self.field_offset,
+                    _m: PhantomData::<M>,
+                },
+                self.mems,
+            ),
+            field_offset: self.field_offset + core::mem::size_of::<M>(),
+            n_trackers: self.n_trackers + 1,
+        }
+    }
+
+    #[inline(always)]
+    fn add_block_tracker<C: Condition, M: MetricTrait>(self) -> Self::AddBlockTracker<C, M> {
+        MetricsBuilderStruct {
+            builder: self
+                .builder
+                .extend_entities(MetricComponent::<M>::new(self.n_trackers)),
+            update_metrics: self.update_metrics,
+            block_metrics: Nested::new(
+                TrackerUpdateBuilder {
+                    metric_builder: MetricUpdateBuilderStruct {
+                        _m: PhantomData::<M>,
+                        _condition: PhantomData::<C>,
+                    },
+                },
+                self.block_metrics,
+            ),
+            mems: Nested::new(
+                MetricMemStruct {
+                    field_offset: self.field_offset,
+                    _m: PhantomData::<M>,
+                },
+                self.mems,
+            ),
+            field_offset: self.field_offset + core::mem::size_of::<M>(),
+            n_trackers: self.n_trackers + 1,
+        }
+    }
+
     #[inline(always)]
     fn skip<M: MetricTrait>(&mut self) {
         self.field_offset += core::mem::size_of::<M>();
@@ -749,106 +687,12 @@ where
             .add_resource(NTrackers(self.n_trackers))
             .extend_entities(SampleRecorded::default())
             .extend_entities(SampleId::default());
-        // Manually expanding macro because `builder` is not accessible outside the macro
-        // macro_rules! add_systems {
-        //     (
-        //         $update_metrics:ident,
-        //         $update_order:ident,
-        //         $block_metrics:ident,
-        //         $block_order:ident
-        //     ) => {
-        //         let builder = builder
-        //             .add_system_to_stage_without_plugin(
-        //                 MetricSystemBuilder::new(self.$update_metrics),
-        //                 $update_order::new(),
-        //             )
-        //             .add_system_to_stage_without_plugin(
-        //                 MetricSystemBuilder::new(self.$block_metrics),
-        //                 $block_order::new(),
-        //             );
-        //     };
-        // }
-        let builder = builder
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.update_metrics0),
-                PostTrade0::new(),
-            )
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.block_metrics0),
-                PostBlock0::new(),
-            );
-        let builder = builder
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.update_metrics1),
-                PostTrade1::new(),
-            )
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.block_metrics1),
-                PostBlock1::new(),
-            );
-        let builder = builder
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.update_metrics2),
-                PostTrade2::new(),
-            )
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.block_metrics2),
-                PostBlock2::new(),
-            );
-        let builder = builder
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.update_metrics3),
-                PostTrade3::new(),
-            )
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.block_metrics3),
-                PostBlock3::new(),
-            );
-        let builder = builder
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.update_metrics4),
-                PostTrade4::new(),
-            )
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.block_metrics4),
-                PostBlock4::new(),
-            );
-        let builder = builder
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.update_metrics5),
-                PostTrade5::new(),
-            )
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.block_metrics5),
-                PostBlock5::new(),
-            );
-        let builder = builder
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.update_metrics6),
-                PostTrade6::new(),
-            )
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.block_metrics6),
-                PostBlock6::new(),
-            );
-        let builder = builder
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.update_metrics7),
-                PostTrade7::new(),
-            )
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.block_metrics7),
-                PostBlock7::new(),
-            );
-        let builder = builder
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.update_metrics8),
-                PostTrade8::new(),
-            )
-            .add_system_without_plugin(
-                MetricSystemBuilder::new(self.block_metrics8),
-                PostBlock8::new(),
-            );
+        let builder = self
+            .update_metrics
+            .add_systems::<_, _, UM>(builder, PostTrade::new);
+        let builder = self
+            .block_metrics
+            .add_systems::<_, _, BM>(builder, PostBlock::new);
         builder
             .add_system_without_plugin(inc_sample_id::new(), IndicatorCompute::new())
             .add_system_without_plugin(
@@ -861,6 +705,65 @@ where
     }
 }
 
+/// Metrics that are updated in one stage, each of them by its own system.
+pub trait MetricStage: 'static {
+    /// `MetricLabel` of each metric in the stage.
+    type Labels: NestTypes;
+    type AddSystems<B: EcsBuilder, S: StageLabel, Stage: MetricStage>: EcsBuilder;
+
+    /// Adds a system for each metric, `Stage` contains all metrics of the stage.
+    fn add_systems<B: EcsBuilder, S: StageLabel, Stage: MetricStage>(
+        self,
+        builder: B,
+        stage: fn() -> S,
+    ) -> Self::AddSystems<B, S, Stage>;
+}
+
+impl MetricStage for StackedNest {
+    type AddSystems<B: EcsBuilder, S: StageLabel, Stage: MetricStage> = B;
+    type Labels = StackedNest;
+
+    #[inline(always)]
+    fn add_systems<B: EcsBuilder, S: StageLabel, Stage: MetricStage>(
+        self,
+        builder: B,
+        _stage: fn() -> S,
+    ) -> Self::AddSystems<B, S, Stage> {
+        builder
+    }
+}
+
+impl<A: MetricStage, T: MetricUpdateBuilder + 'static> MetricStage for Nested<A, T> {
+    type Labels = Nested<A::Labels, MetricLabel<T::Metric>>;
+
+    type AddSystems<B: EcsBuilder, S: StageLabel, Stage: MetricStage> = impl EcsBuilder;
+
+    #[inline(always)]
+    fn add_systems<B: EcsBuilder, S: StageLabel, Stage: MetricStage>(
+        self,
+        builder: B,
+        stage: fn() -> S,
+    ) -> Self::AddSystems<B, S, Stage> {
+        // Metrics without ordering constraints run in the order they were added.
+        let builder = self.inner.add_systems::<B, S, Stage>(builder, stage);
+        builder.add_system_without_plugin(MetricSystemBuilder::<T, Stage>::new(self.item), stage())
+    }
+}
+
+/// Labels of `deps` that belong to metrics in the stage. Other dependencies are updated in an
+/// earlier stage or by plugins (e.g. `Balance`).
+const fn deps_in_stage<N: NestTypes>(deps: &[&'static str]) -> Labels {
+    let mut labels = Labels::EMPTY;
+    let mut i = 0;
+    while i < deps.len() {
+        if nest_contains::<N>(deps[i]) {
+            labels = labels.push(deps[i]);
+        }
+        i += 1;
+    }
+    labels
+}
+
 #[system]
 fn inc_sample_id(updated: &mut SampleRecorded, sample_id: &mut SampleId) {
     if **updated {
@@ -894,29 +797,6 @@ pub trait MetricUpdate<W: World> {
         E: EntityFetch;
 }
 
-impl<W: World> MetricUpdate<W> for StackedNest {
-    #[inline(always)]
-    fn update<E>(&mut self, _entity: &mut E, _world: &mut W) -> Skip
-    where
-        E: EntityFetch,
-    {
-        Skip::False
-    }
-}
-
-impl<W: World, A: MetricUpdate<W>, B: MetricUpdate<W>> MetricUpdate<W> for Nested<A, B> {
-    #[inline(always)]
-    fn update<E>(&mut self, entity: &mut E, world: &mut W) -> Skip
-    where
-        E: EntityFetch,
-        W: World,
-    {
-        self.inner.update(entity, world);
-        self.item.update(entity, world);
-        Skip::False
-    }
-}
-
 struct TrackerUpdate<M: MetricTrait, C: Condition, W: World, const N: usize> {
     metric: MetricStruct<M, C, W, N>,
     tracks_state: TracksState<M, N>,
@@ -951,6 +831,7 @@ struct TrackerUpdateBuilder<M, C> {
 
 impl<M: MetricTrait, C: Condition> MetricUpdateBuilder for TrackerUpdateBuilder<M, C> {
     type Build<W: World, const N: usize> = TrackerUpdate<M, C, W, N>;
+    type Metric = M;
 
     #[inline(always)]
     fn build<W: World, const N: usize>(self, world: &mut W) -> Self::Build<W, N> {
@@ -975,6 +856,7 @@ struct MetricUpdateBuilderStruct<M, C> {
 
 impl<M: MetricTrait, C: Condition> MetricUpdateBuilder for MetricUpdateBuilderStruct<M, C> {
     type Build<W: World, const N: usize> = MetricStruct<M, C, W, N>;
+    type Metric = M;
 
     #[inline(always)]
     fn build<W: World, const N: usize>(self, world: &mut W) -> Self::Build<W, N> {
@@ -1000,27 +882,11 @@ impl<M: MetricTrait, C: Condition> MetricUpdateBuilder for MetricUpdateBuilderSt
 
 pub trait MetricUpdateBuilder {
     type Build<W: World, const N: usize>: MetricUpdate<W>;
+    /// Metric that is updated.
+    type Metric: MetricTrait;
     fn build<W: World, const N: usize>(self, world: &mut W) -> Self::Build<W, N>;
 }
 
-impl MetricUpdateBuilder for StackedNest {
-    type Build<W: World, const N: usize> = StackedNest;
-
-    #[inline(always)]
-    fn build<W: World, const N: usize>(self, _world: &mut W) -> Self::Build<W, N> {
-        StackedNest
-    }
-}
-
-impl<A: MetricUpdateBuilder, B: MetricUpdateBuilder> MetricUpdateBuilder for Nested<A, B> {
-    type Build<W: World, const N: usize> = Nested<A::Build<W, N>, B::Build<W, N>>;
-
-    #[inline(always)]
-    fn build<W: World, const N: usize>(self, world: &mut W) -> Self::Build<W, N> {
-        Nested::new(self.item.build(world), self.inner.build(world))
-    }
-}
-
 struct MetricStruct<M: MetricTrait, C: Condition, W: World, const N: usize> {
     states: <M::UpdateParams<'static, 'static, W, N> as SystemParam>::State,
     condition_states: <C::Params<'static, 'static, W, N> as SystemParam>::State,
@@ -1029,8 +895,29 @@ struct MetricStruct<M: MetricTrait, C: Condition, W: World, const N: usize> {
 impl<M: MetricTrait, C: Condition, W: World, const N: usize> MetricUpdate<W>
     for MetricStruct<M, C, W, N>
 {
+    /// Updates the metric of the entity, then of each of its `EntityScope`s. Returns whether the
+    /// entity update was skipped.
     #[inline(always)]
     fn update<E>(&mut self, entity: &mut E, world: &mut W) -> Skip
+    where
+        E: EntityFetch,
+    {
+        let skip = self.update_scope(entity, world);
+        let n_scopes = entity.get_component::<EntityScope>().map_or(0, |x| x.len);
+        for scope in 0..n_scopes {
+            entity.component_mut::<EntityScope>().current = Some(scope);
+            self.update_scope(entity, world);
+        }
+        if n_scopes > 0 {
+            entity.component_mut::<EntityScope>().current = None;
+        }
+        skip
+    }
+}
+
+impl<M: MetricTrait, C: Condition, W: World, const N: usize> MetricStruct<M, C, W, N> {
+    #[inline(always)]
+    fn update_scope<E>(&mut self, entity: &mut E, world: &mut W) -> Skip
     where
         E: EntityFetch,
     {
@@ -1367,23 +1254,34 @@ impl<A: MetricMem, B: MetricMem> MetricMem for Nested<A, B> {
     }
 }
 
-struct MetricSystemBuilder<M> {
-    systems: M,
+struct MetricSystemBuilder<M, Stage> {
+    metric: M,
+    _stage: PhantomData<Stage>,
 }
-impl<M> MetricSystemBuilder<M> {
+
+impl<M, Stage> MetricSystemBuilder<M, Stage> {
     #[inline(always)]
-    fn new(systems: M) -> Self {
-        Self { systems }
+    fn new(metric: M) -> Self {
+        Self {
+            metric,
+            _stage: PhantomData,
+        }
     }
 }
 
-impl<'w, 's, M: MetricUpdateBuilder> SystemBuilder<'w, 's> for MetricSystemBuilder<M> {
+impl<'w, 's, M: MetricUpdateBuilder, Stage: MetricStage> SystemBuilder<'w, 's>
+    for MetricSystemBuilder<M, Stage>
+{
     type System<W: World, const N: usize> = MetricUpdateSystem<M::Build<W, N>>;
 
+    const AFTER: Labels = deps_in_stage::<Stage::Labels>(M::Metric::DEPS);
+    const LABELS: Labels = Labels::EMPTY.push(<MetricLabel<M::Metric> as SystemLabel>::NAME);
+    const NAME: &'static str = <MetricLabel<M::Metric> as SystemLabel>::NAME;
+
     #[inline(always)]
     fn build<W: World, const N: usize>(self, world: &mut W) -> Self::System<W, N> {
         MetricUpdateSystem {
-            systems: self.systems.build(world),
+            systems: self.metric.build(world),
         }
     }
 }
@@ -1417,11 +1315,11 @@ macro_rules! impl_mapper {
     };
 }
 
-impl_mapper!(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+inception_macros::all_param_ids!(impl_mapper);
 impl SystemParamNameMapper for MetricSystem {
     #[inline(always)]
     fn get_param_name<const PARAM_ID: usize>() -> Option<&'static str> {
-        if PARAM_ID < 16 {
+        if PARAM_ID < MAX_SYSTEM_PARAMS {
             Some(core::any::type_name::<Unknown>())
         } else {
             None
@@ -1445,3 +1343,75 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::prelude::v1::*;
+
+    use super::*;
+    use crate::{
+        block_relays::{BlockRelay, UpdateRelay},
+        simulation_relays::SimulationEnding,
+        stages::BacktestSchedule,
+        Balance, CorePlugin, MetricsPlugin,
+    };
+
+    #[metric]
+    #[derive(Default)]
+    struct Updates(u32);
+    #[impl_metric]
+    impl MetricTrait for Updates {
+        type SimulationRelay = SimulationEnding;
+
+        #[inline(always)]
+        fn update(mut updates: Metric<Updates>) {
+            *updates += 1;
+        }
+    }
+
+    #[metric]
+    #[derive(Default)]
+    struct SeenUpdates(u32);
+    #[impl_metric]
+    impl MetricTrait for SeenUpdates {
+        type SimulationRelay = SimulationEnding;
+
+        #[inline(always)]
+        fn update(
+            mut seen: Metric<SeenUpdates>,
+            updates: Metric<Updates>,
+            _balance: Metric<Balance>,
+        ) {
+            *seen = *updates;
+        }
+    }
+
+    #[test]
+    fn dependencies_run_first() {
+        let mut metrics = vec![0u8; 1024];
+        let builder = EcsBuilderStruct::new::<_, 6>(
+            BacktestSchedule::builder(),
+            EntitiesBuilderStruct1::new(),
+        )
+        .add_plugin(CorePlugin {
+            loop_start_bound: 0,
+            loop_end_bound_excluded: 5,
+        })
+        .add_plugin(MetricsPlugin::new(metrics.as_mut_ptr(), 5));
+        // `SeenUpdates` is added before its dependency. `Balance` is in another stage, so it
+        // doesn't constrain the order.
+        let mut ecs = MetricsBuilderStruct::new(builder)
+            .add_metric::<SimulationEnding, UpdateRelay, Always, SeenUpdates>()
+            .add_metric::<SimulationEnding, UpdateRelay, Always, Updates>()
+            .add_metric::<SimulationEnding, BlockRelay, Always, Balance>()
+            .finish()
+            .build();
+        ecs.run();
+        let updates = ecs.component::<MetricComponent<Updates>>(Entity(0)).get();
+        let seen = ecs
+            .component::<MetricComponent<SeenUpdates>>(Entity(0))
+            .get();
+        assert_ne!(updates, 0);
+        assert_eq!(seen, updates);
+    }
+}
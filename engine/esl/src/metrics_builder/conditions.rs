@@ -1,4 +1,5 @@
-//! Update a performance metric when some condition is met.
+//! Update a performance metric when some condition is met. Conditions are checked per entity, see
+//! `Condition`. Use `run_if` for systems outside of the metrics builder.
 use super::*;
 
 /// Executes an update function of a performance metric when the position is closed.
@@ -13,13 +13,13 @@ macro_rules! impl_simulation_relay {
         impl SimulationRelay<$required> for $actual {
             type AddMetric<
                 B: MetricsBuilder,
-                Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+                Relay: MetricsBuilderRelay,
                 C: Condition,
                 M: MetricTrait,
             > = impl MetricsBuilder;
             type AddTracker<
                 B: MetricsBuilder,
-                Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+                Relay: MetricsBuilderRelay,
                 C: Condition,
                 M: MetricTrait,
             > = impl MetricsBuilder;
@@ -28,7 +28,7 @@ macro_rules! impl_simulation_relay {
             fn add_metric<B, Relay, C, M>(builder: B) -> Self::AddMetric<B, Relay, C, M>
             where
                 B: MetricsBuilder,
-                Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+                Relay: MetricsBuilderRelay,
                 C: Condition,
                 M: MetricTrait,
             {
@@ -39,7 +39,7 @@ macro_rules! impl_simulation_relay {
             fn add_tracker<B, Relay, C, M>(builder: B) -> Self::AddTracker<B, Relay, C, M>
             where
                 B: MetricsBuilder,
-                Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+                Relay: MetricsBuilderRelay,
                 C: Condition,
                 M: MetricTrait,
             {
@@ -54,13 +54,13 @@ macro_rules! impl_simulation_relay_skip {
         impl SimulationRelay<$required> for $actual {
             type AddMetric<
                 B: MetricsBuilder,
-                Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+                Relay: MetricsBuilderRelay,
                 C: Condition,
                 M: MetricTrait,
             > = impl MetricsBuilder;
             type AddTracker<
                 B: MetricsBuilder,
-                Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+                Relay: MetricsBuilderRelay,
                 C: Condition,
                 M: MetricTrait,
             > = impl MetricsBuilder;
@@ -69,7 +69,7 @@ macro_rules! impl_simulation_relay_skip {
             fn add_metric<B, Relay, C, M>(builder: B) -> Self::AddMetric<B, Relay, C, M>
             where
                 B: MetricsBuilder,
-                Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+                Relay: MetricsBuilderRelay,
                 C: Condition,
                 M: MetricTrait,
             {
@@ -80,7 +80,7 @@ macro_rules! impl_simulation_relay_skip {
             fn add_tracker<B, Relay, C, M>(builder: B) -> Self::AddTracker<B, Relay, C, M>
             where
                 B: MetricsBuilder,
-                Relay: MetricsBuilderRelay<M::ExecutionOrder>,
+                Relay: MetricsBuilderRelay,
                 C: Condition,
                 M: MetricTrait,
             {
@@ -1,6 +1,6 @@
 use crate::{
     events::OrderPlaced,
-    types::{Direction, PositionAction, PositionSize, TriggerPrice},
+    types::{is_buy, Direction, OrderId, PositionAction, PositionSize, Trail, TriggerPrice},
 };
 
 pub struct StopMarketOpenFullShort {
@@ -19,6 +19,22 @@ pub struct StopMarketCloseFullLong {
     pub trigger: TriggerPrice,
 }
 
+pub struct TrailingStopOpenFullShort {
+    pub trail: Trail,
+}
+
+pub struct TrailingStopOpenFullLong {
+    pub trail: Trail,
+}
+
+pub struct TrailingStopCloseFullShort {
+    pub trail: Trail,
+}
+
+pub struct TrailingStopCloseFullLong {
+    pub trail: Trail,
+}
+
 pub struct StopMarketOpenShort {
     pub size: PositionSize,
     pub trigger: f32,
@@ -55,6 +71,65 @@ pub struct MarketCloseLong {
     pub size: PositionSize,
 }
 
+pub struct LimitOpenShort {
+    pub size: PositionSize,
+    pub limit: f32,
+}
+
+pub struct LimitOpenLong {
+    pub size: PositionSize,
+    pub limit: f32,
+}
+
+pub struct LimitCloseShort {
+    pub size: PositionSize,
+    pub limit: f32,
+}
+
+pub struct LimitCloseLong {
+    pub size: PositionSize,
+    pub limit: f32,
+}
+
+pub struct StopLimitOpenShort {
+    pub size: PositionSize,
+    pub trigger: f32,
+    pub limit: f32,
+}
+
+pub struct StopLimitOpenLong {
+    pub size: PositionSize,
+    pub trigger: f32,
+    pub limit: f32,
+}
+
+pub struct StopLimitCloseShort {
+    pub size: PositionSize,
+    pub trigger: f32,
+    pub limit: f32,
+}
+
+pub struct StopLimitCloseLong {
+    pub size: PositionSize,
+    pub trigger: f32,
+    pub limit: f32,
+}
+
+/// Entry with take profit and stop loss, send it with `Orders::send_bracket`.
+pub struct Bracket<E, T, S> {
+    pub entry: E,
+    pub take_profit: T,
+    pub stop_loss: S,
+}
+
+/// Ids of orders of a bracket.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BracketIds {
+    pub entry: OrderId,
+    pub take_profit: OrderId,
+    pub stop_loss: OrderId,
+}
+
 pub trait IntoOrder {
     fn into_order(self, entry_price: f32) -> OrderPlaced;
 }
@@ -74,9 +149,12 @@ macro_rules! impl_from_stop_market_order {
                 OrderPlaced::StopMarket {
                     trigger: match self.trigger {
                         TriggerPrice::Absolute(trigger) => trigger,
-                        TriggerPrice::RelativeToCurrentPrice(trigger) => match $direction {
-                            Direction::Long => current_price * (1. - trigger),
-                            Direction::Short => current_price * (1. + trigger),
+                        TriggerPrice::RelativeToCurrentPrice(trigger) => {
+                            if is_buy($action, $direction) {
+                                current_price * (1. + trigger)
+                            } else {
+                                current_price * (1. - trigger)
+                            }
                         },
                     },
                     size: PositionSize::Relative(1.),
@@ -109,6 +187,46 @@ impl_from_stop_market_order!(
     Direction::Long
 );
 
+macro_rules! impl_from_trailing_stop_order {
+    ($order:ty, $action:expr, $direction:expr) => {
+        impl IntoOrder for $order {
+            #[inline(always)]
+            fn into_order(self, current_price: f32) -> OrderPlaced {
+                OrderPlaced::TrailingStop {
+                    trigger: self
+                        .trail
+                        .stop_price(current_price, is_buy($action, $direction)),
+                    trail: self.trail,
+                    size: PositionSize::Relative(1.),
+                    position_action: $action,
+                    direction: $direction,
+                }
+            }
+        }
+    };
+}
+
+impl_from_trailing_stop_order!(
+    TrailingStopOpenFullShort,
+    PositionAction::Open,
+    Direction::Short
+);
+impl_from_trailing_stop_order!(
+    TrailingStopOpenFullLong,
+    PositionAction::Open,
+    Direction::Long
+);
+impl_from_trailing_stop_order!(
+    TrailingStopCloseFullShort,
+    PositionAction::Close,
+    Direction::Short
+);
+impl_from_trailing_stop_order!(
+    TrailingStopCloseFullLong,
+    PositionAction::Close,
+    Direction::Long
+);
+
 macro_rules! impl_from_market_order {
     ($order:ty, $action:expr, $direction:expr) => {
         impl From<$order> for OrderPlaced {
@@ -129,6 +247,49 @@ impl_from_market_order!(MarketOpenLong, PositionAction::Open, Direction::Long);
 impl_from_market_order!(MarketCloseShort, PositionAction::Close, Direction::Short);
 impl_from_market_order!(MarketCloseLong, PositionAction::Close, Direction::Long);
 
+macro_rules! impl_from_limit_order {
+    ($order:ty, $action:expr, $direction:expr) => {
+        impl From<$order> for OrderPlaced {
+            #[inline(always)]
+            fn from(value: $order) -> Self {
+                OrderPlaced::Limit {
+                    size: value.size,
+                    position_action: $action,
+                    direction: $direction,
+                    limit: value.limit,
+                }
+            }
+        }
+    };
+}
+
+impl_from_limit_order!(LimitOpenShort, PositionAction::Open, Direction::Short);
+impl_from_limit_order!(LimitOpenLong, PositionAction::Open, Direction::Long);
+impl_from_limit_order!(LimitCloseShort, PositionAction::Close, Direction::Short);
+impl_from_limit_order!(LimitCloseLong, PositionAction::Close, Direction::Long);
+
+macro_rules! impl_from_stop_limit_order {
+    ($order:ty, $action:expr, $direction:expr) => {
+        impl From<$order> for OrderPlaced {
+            #[inline(always)]
+            fn from(value: $order) -> Self {
+                OrderPlaced::StopLimit {
+                    size: value.size,
+                    position_action: $action,
+                    direction: $direction,
+                    trigger: value.trigger,
+                    limit: value.limit,
+                }
+            }
+        }
+    };
+}
+
+impl_from_stop_limit_order!(StopLimitOpenShort, PositionAction::Open, Direction::Short);
+impl_from_stop_limit_order!(StopLimitOpenLong, PositionAction::Open, Direction::Long);
+impl_from_stop_limit_order!(StopLimitCloseShort, PositionAction::Close, Direction::Short);
+impl_from_stop_limit_order!(StopLimitCloseLong, PositionAction::Close, Direction::Long);
+
 macro_rules! impl_stop_order_full {
     ($order:ty) => {
         impl $order {
@@ -155,6 +316,33 @@ impl_stop_order_full!(StopMarketOpenFullShort);
 impl_stop_order_full!(StopMarketCloseFullLong);
 impl_stop_order_full!(StopMarketCloseFullShort);
 
+macro_rules! impl_trailing_stop_order_full {
+    ($order:ty) => {
+        impl $order {
+            /// Trails by a fraction of the price.
+            #[inline(always)]
+            pub fn relative(trail: f32) -> Self {
+                Self {
+                    trail: Trail::Relative(trail),
+                }
+            }
+
+            /// Trails by a price distance.
+            #[inline(always)]
+            pub fn absolute(trail: f32) -> Self {
+                Self {
+                    trail: Trail::Absolute(trail),
+                }
+            }
+        }
+    };
+}
+
+impl_trailing_stop_order_full!(TrailingStopOpenFullLong);
+impl_trailing_stop_order_full!(TrailingStopOpenFullShort);
+impl_trailing_stop_order_full!(TrailingStopCloseFullLong);
+impl_trailing_stop_order_full!(TrailingStopCloseFullShort);
+
 macro_rules! impl_order {
     ($order:ty) => {
         impl $order {
@@ -186,3 +374,76 @@ impl_order!(MarketOpenLong);
 impl_order!(MarketOpenShort);
 impl_order!(MarketCloseLong);
 impl_order!(MarketCloseShort);
+
+macro_rules! impl_limit_order {
+    ($order:ty) => {
+        impl $order {
+            #[inline(always)]
+            pub fn full(limit: f32) -> Self {
+                Self {
+                    size: PositionSize::Relative(1.),
+                    limit,
+                }
+            }
+
+            #[inline(always)]
+            pub fn relative(size: f32, limit: f32) -> Self {
+                Self {
+                    size: PositionSize::Relative(size),
+                    limit,
+                }
+            }
+
+            #[inline(always)]
+            pub fn absolute(size: f32, limit: f32) -> Self {
+                Self {
+                    size: PositionSize::Absolute(size),
+                    limit,
+                }
+            }
+        }
+    };
+}
+
+impl_limit_order!(LimitOpenLong);
+impl_limit_order!(LimitOpenShort);
+impl_limit_order!(LimitCloseLong);
+impl_limit_order!(LimitCloseShort);
+
+macro_rules! impl_stop_limit_order {
+    ($order:ty) => {
+        impl $order {
+            #[inline(always)]
+            pub fn full(trigger: f32, limit: f32) -> Self {
+                Self {
+                    size: PositionSize::Relative(1.),
+                    trigger,
+                    limit,
+                }
+            }
+
+            #[inline(always)]
+            pub fn relative(size: f32, trigger: f32, limit: f32) -> Self {
+                Self {
+                    size: PositionSize::Relative(size),
+                    trigger,
+                    limit,
+                }
+            }
+
+            #[inline(always)]
+            pub fn absolute(size: f32, trigger: f32, limit: f32) -> Self {
+                Self {
+                    size: PositionSize::Absolute(size),
+                    trigger,
+                    limit,
+                }
+            }
+        }
+    };
+}
+
+impl_stop_limit_order!(StopLimitOpenLong);
+impl_stop_limit_order!(StopLimitOpenShort);
+impl_stop_limit_order!(StopLimitCloseLong);
+impl_stop_limit_order!(StopLimitCloseShort);
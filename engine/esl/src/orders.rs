@@ -1,11 +1,13 @@
+use ergnomics::some_loop;
 use inception::*;
 
 use crate::{
     events::OrderPlaced,
     metrics::Position,
-    types::{OrderId, PositionAction},
+    order::{Bracket, BracketIds},
+    types::{Direction, OrderId, PositionAction},
     value::Value,
-    IntoOrder, Metric, NOrders, Price,
+    IntoOrder, MaxPyramiding, MetricComponent, NEntries, NOrders, Price,
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -25,18 +27,59 @@ impl<const N: usize> Default for OrdersContainer<N> {
 pub struct ActiveOrder {
     pub id: OrderId,
     pub order: OrderPlaced,
+    /// Order waits until the parent is filled. If the parent is canceled, this order is canceled
+    /// too.
+    pub parent: Option<OrderId>,
+}
+
+/// Two orders where filling one cancels the other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OcoGroup {
+    pub first: OrderId,
+    pub second: OrderId,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcoGroups<const N: usize> {
+    pub groups: [Option<OcoGroup>; N],
+}
+
+impl<const N: usize> Default for OcoGroups<N> {
+    #[inline(always)]
+    fn default() -> Self {
+        const GROUP: Option<OcoGroup> = None;
+        OcoGroups { groups: [GROUP; N] }
+    }
+}
+
+/// Orders that were canceled by a strategy, `OrderCanceled` is sent for them when orders are
+/// filled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanceledOrders<const N: usize> {
+    pub ids: [Option<OrderId>; N],
+}
+
+impl<const N: usize> Default for CanceledOrders<N> {
+    #[inline(always)]
+    fn default() -> Self {
+        CanceledOrders { ids: [None; N] }
+    }
 }
 
 #[derive(Default, Clone, PartialEq, Debug)]
 pub struct OrdersComponent<const N: usize> {
     pub tmp_orders: OrdersContainer<N>,
     pub active_orders: OrdersContainer<N>,
+    pub oco_groups: OcoGroups<N>,
+    pub canceled: CanceledOrders<N>,
 }
 
 pub struct Orders<'w, 's, const N: usize> {
-    position: Metric<'w, 's, Position, N>,
+    direction: Option<Direction>,
+    n_entries: u32,
+    max_pyramiding: u32,
     current_price: f32,
-    n_orders: Metric<'w, 's, NOrders, N>,
+    n_orders: &'w mut u32,
     orders: &'w mut OrdersComponent<N>,
     _marker: PhantomSystemParam<'w, 's, N>,
 }
@@ -64,27 +107,22 @@ impl<'w, 's, const N: usize> SystemParam for Orders<'w, 's, N> {
     {
         let current_price =
             Price::<N>::get_param_for_entity::<Wrld, SB, ParamName, E>(entity, state, world)?.get();
+        let direction = entity
+            .get_component::<MetricComponent<Position>>()?
+            .metric
+            .direction();
+        let n_entries = **entity.get_component::<MetricComponent<NEntries>>()?;
         let entity = entity as *mut E;
-        let world = world as *mut Wrld;
-        let state = state as *mut Self::State;
         unsafe {
-            let position = Metric::<Position, N>::get_param_for_entity::<Wrld, SB, ParamName, E>(
-                &mut *entity,
-                &mut *state,
-                &mut *world,
-            )?;
-            let n_orders = Metric::<NOrders, N>::get_param_for_entity::<Wrld, SB, ParamName, E>(
-                &mut *entity,
-                &mut *state,
-                &mut *world,
-            )?;
-            Some(Orders {
-                orders: (&mut *entity).component_mut::<OrdersComponent<N>>(),
-                _marker: PhantomSystemParam::default(),
-                position,
+            let n_orders = (*entity).get_component_mut::<MetricComponent<NOrders>>()?;
+            Some(Orders::new(
+                direction,
+                n_entries,
+                world.resource::<MaxPyramiding>().0,
                 current_price,
-                n_orders,
-            })
+                &mut n_orders.metric,
+                (*entity).component_mut::<OrdersComponent<N>>(),
+            ))
         }
     }
 
@@ -96,6 +134,28 @@ impl<'w, 's, const N: usize> SystemParam for Orders<'w, 's, N> {
 }
 
 impl<'w, 's, const N: usize> Orders<'w, 's, N> {
+    /// Orders of an instrument with `direction` and `n_entries` of its position. Order ids are
+    /// taken from `n_orders`.
+    #[inline(always)]
+    pub(crate) fn new(
+        direction: Option<Direction>,
+        n_entries: u32,
+        max_pyramiding: u32,
+        current_price: f32,
+        n_orders: &'w mut u32,
+        orders: &'w mut OrdersComponent<N>,
+    ) -> Self {
+        Self {
+            direction,
+            n_entries,
+            max_pyramiding,
+            current_price,
+            n_orders,
+            orders,
+            _marker: PhantomSystemParam::default(),
+        }
+    }
+
     #[inline(always)]
     pub fn on(&mut self, condition: bool, order: impl IntoOrder) -> Option<OrderId> {
         if condition { self.send(order) } else { None }
@@ -110,9 +170,14 @@ impl<'w, 's, const N: usize> Orders<'w, 's, N> {
                 direction,
                 ..
             } => match position_action {
-                PositionAction::Open if self.position.metric().is_opened() => return None,
+                PositionAction::Open => match self.direction {
+                    Some(d) if d != *direction || self.n_entries >= self.max_pyramiding => {
+                        return None;
+                    },
+                    _ => {},
+                },
                 PositionAction::Close => {
-                    let discard = match self.position.metric().direction() {
+                    let discard = match self.direction {
                         Some(d) => d != *direction,
                         None => true,
                     };
@@ -120,7 +185,6 @@ impl<'w, 's, const N: usize> Orders<'w, 's, N> {
                         return None;
                     }
                 },
-                _ => {},
             },
             OrderPlaced::StopMarket {
                 position_action,
@@ -128,37 +192,117 @@ impl<'w, 's, const N: usize> Orders<'w, 's, N> {
                 size: _,
                 trigger: _,
             } => match position_action {
-                PositionAction::Close if self.position.metric().is_opened() => return None,
+                PositionAction::Close if self.direction.is_some() => return None,
                 _ => {},
             },
+            // Position isn't checked when these orders are sent. While they rest, they are
+            // canceled on the first bar on which they can't execute with the current position,
+            // e.g. open orders are canceled once a position in the other direction is opened or
+            // pyramiding is used up.
+            OrderPlaced::Limit { .. }
+            | OrderPlaced::StopLimit { .. }
+            | OrderPlaced::TrailingStop { .. } => {},
         }
         let id = OrderId(*self.n_orders);
         *self.n_orders += 1;
-        self.orders.tmp_orders.push(ActiveOrder { id, order });
+        self.orders.tmp_orders.push(ActiveOrder {
+            id,
+            order,
+            parent: None,
+        });
         Some(id)
     }
 
     #[inline(always)]
-    pub fn cancel(&mut self, id: OrderId) {
-        for maybe_order in &mut self.orders.active_orders.orders {
-            if let Some(order) = maybe_order {
-                if order.id == id {
-                    *maybe_order = None;
-                    return;
-                }
-            }
+    pub fn on_oco(
+        &mut self,
+        condition: bool,
+        first: impl IntoOrder,
+        second: impl IntoOrder,
+    ) -> Option<(OrderId, OrderId)> {
+        if condition {
+            self.send_oco(first, second)
+        } else {
+            None
+        }
+    }
+
+    /// Sends two orders where filling one cancels the other.
+    #[inline(always)]
+    pub fn send_oco(
+        &mut self,
+        first: impl IntoOrder,
+        second: impl IntoOrder,
+    ) -> Option<(OrderId, OrderId)> {
+        let first = self.send_child(first, None);
+        let second = self.send_child(second, None);
+        self.orders.oco_groups.push(OcoGroup { first, second });
+        Some((first, second))
+    }
+
+    #[inline(always)]
+    pub fn on_bracket<E: IntoOrder, T: IntoOrder, S: IntoOrder>(
+        &mut self,
+        condition: bool,
+        bracket: Bracket<E, T, S>,
+    ) -> Option<BracketIds> {
+        if condition {
+            self.send_bracket(bracket)
+        } else {
+            None
         }
-        panic!("Order with id: {:?} doesn't exist", id);
+    }
+
+    /// Sends an entry with take profit and stop loss. Take profit and stop loss wait until the
+    /// entry is filled, then filling one of them cancels the other.
+    #[inline(always)]
+    pub fn send_bracket<E: IntoOrder, T: IntoOrder, S: IntoOrder>(
+        &mut self,
+        bracket: Bracket<E, T, S>,
+    ) -> Option<BracketIds> {
+        let entry = self.send(bracket.entry)?;
+        let take_profit = self.send_child(bracket.take_profit, Some(entry));
+        let stop_loss = self.send_child(bracket.stop_loss, Some(entry));
+        self.orders.oco_groups.push(OcoGroup {
+            first: take_profit,
+            second: stop_loss,
+        });
+        Some(BracketIds {
+            entry,
+            take_profit,
+            stop_loss,
+        })
+    }
+
+    /// Sends an order without position checks.
+    #[inline(always)]
+    fn send_child(&mut self, order: impl IntoOrder, parent: Option<OrderId>) -> OrderId {
+        let order = order.into_order(self.current_price);
+        let id = OrderId(*self.n_orders);
+        *self.n_orders += 1;
+        self.orders
+            .tmp_orders
+            .push(ActiveOrder { id, order, parent });
+        id
+    }
+
+    /// Cancels an order and orders waiting on it. `OrderCanceled` is sent for each of them in the
+    /// `Trade` stage. Orders that were already filled or canceled are ignored.
+    #[inline(always)]
+    pub fn cancel(&mut self, id: OrderId) {
+        let mut pending = core::mem::take(&mut self.orders.canceled);
+        self.orders.cancel(id, &mut |id| pending.push(id));
+        self.orders.canceled = pending;
     }
 
     #[inline(always)]
     pub fn get(&self) -> &OrdersComponent<N> {
-        &self.orders
+        self.orders
     }
 
     #[inline(always)]
     pub fn get_mut(&mut self) -> &mut OrdersComponent<N> {
-        &mut self.orders
+        self.orders
     }
 }
 
@@ -173,4 +317,194 @@ impl<const N: usize> OrdersContainer<N> {
             panic!("Too many orders per iteration");
         }
     }
+
+    #[inline(always)]
+    pub fn remove(&mut self, id: OrderId) -> Option<ActiveOrder> {
+        self.orders
+            .iter_mut()
+            .find(|o| matches!(o, Some(o) if o.id == id))
+            .and_then(|o| o.take())
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, id: OrderId) -> bool {
+        self.orders.iter().flatten().any(|o| o.id == id)
+    }
+}
+
+impl<const N: usize> CanceledOrders<N> {
+    #[inline(always)]
+    pub fn push(&mut self, id: OrderId) {
+        match self.ids.iter_mut().find(|x| x.is_none()) {
+            Some(x) => *x = Some(id),
+            None => panic!("Too many canceled orders per iteration"),
+        }
+    }
+}
+
+impl<const N: usize> OcoGroups<N> {
+    #[inline(always)]
+    pub fn push(&mut self, group: OcoGroup) {
+        match self.groups.iter_mut().find(|g| g.is_none()) {
+            Some(g) => *g = Some(group),
+            None => panic!("Too many OCO groups"),
+        }
+    }
+
+    /// Removes the group of `id` and returns the other order of the group.
+    #[inline(always)]
+    pub fn take_linked(&mut self, id: OrderId) -> Option<OrderId> {
+        for maybe_group in &mut self.groups {
+            let group = some_loop!(maybe_group);
+            let linked = if group.first == id {
+                group.second
+            } else if group.second == id {
+                group.first
+            } else {
+                continue;
+            };
+            *maybe_group = None;
+            return Some(linked);
+        }
+        None
+    }
+}
+
+impl<const N: usize> OrdersComponent<N> {
+    /// Activates orders waiting on `id` and cancels the other order of its OCO group. `canceled`
+    /// is called for every canceled order.
+    #[inline(always)]
+    pub fn on_filled<F: FnMut(OrderId)>(&mut self, id: OrderId, mut canceled: F) {
+        for order in self
+            .tmp_orders
+            .orders
+            .iter_mut()
+            .chain(self.active_orders.orders.iter_mut())
+            .flatten()
+        {
+            if order.parent == Some(id) {
+                order.parent = None;
+            }
+        }
+        if let Some(linked) = self.oco_groups.take_linked(id) {
+            self.cancel(linked, &mut canceled);
+        }
+    }
+
+    /// Cancels an order and orders waiting on it. `canceled` is called for every canceled order.
+    pub fn cancel<F: FnMut(OrderId)>(&mut self, id: OrderId, canceled: &mut F) {
+        let removed = match self.active_orders.remove(id) {
+            Some(order) => Some(order),
+            None => self.tmp_orders.remove(id),
+        };
+        if removed.is_none() {
+            return;
+        }
+        canceled(id);
+        self.oco_groups.take_linked(id);
+        // A parent is cleared when it is filled, so a missing parent has been canceled. Looping
+        // until there are no such orders avoids recursion.
+        loop {
+            let orphan = self
+                .tmp_orders
+                .orders
+                .iter()
+                .chain(self.active_orders.orders.iter())
+                .flatten()
+                .find(|o| match o.parent {
+                    Some(parent) => {
+                        !self.tmp_orders.contains(parent) && !self.active_orders.contains(parent)
+                    },
+                    None => false,
+                })
+                .map(|o| o.id);
+            let orphan = match orphan {
+                Some(orphan) => orphan,
+                None => break,
+            };
+            if self.active_orders.remove(orphan).is_none() {
+                self.tmp_orders.remove(orphan);
+            }
+            canceled(orphan);
+            self.oco_groups.take_linked(orphan);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::prelude::v1::*;
+
+    use super::*;
+    use crate::types::{Direction, PositionSize};
+
+    fn order(id: u32, parent: Option<u32>) -> ActiveOrder {
+        ActiveOrder {
+            id: OrderId(id),
+            order: OrderPlaced::Limit {
+                size: PositionSize::Relative(1.),
+                position_action: PositionAction::Close,
+                direction: Direction::Long,
+                limit: 1.,
+            },
+            parent: parent.map(OrderId),
+        }
+    }
+
+    #[test]
+    fn bracket() {
+        let mut orders = OrdersComponent::<4>::default();
+        orders.active_orders.push(order(0, None));
+        orders.active_orders.push(order(1, Some(0)));
+        orders.active_orders.push(order(2, Some(0)));
+        orders.oco_groups.push(OcoGroup {
+            first: OrderId(1),
+            second: OrderId(2),
+        });
+        let mut canceled = Vec::new();
+        orders.active_orders.remove(OrderId(0));
+        orders.on_filled(OrderId(0), |id| canceled.push(id));
+        assert!(canceled.is_empty());
+        assert!(
+            orders
+                .active_orders
+                .orders
+                .iter()
+                .flatten()
+                .all(|o| o.parent.is_none())
+        );
+
+        orders.active_orders.remove(OrderId(2));
+        orders.on_filled(OrderId(2), |id| canceled.push(id));
+        assert_eq!(canceled, vec![OrderId(1)]);
+        assert!(orders.active_orders.orders.iter().all(|o| o.is_none()));
+        assert!(orders.oco_groups.groups.iter().all(|g| g.is_none()));
+    }
+
+    #[test]
+    fn cancel_children() {
+        let mut orders = OrdersComponent::<4>::default();
+        orders.active_orders.push(order(0, None));
+        orders.tmp_orders.push(order(1, Some(0)));
+        orders.tmp_orders.push(order(2, Some(0)));
+        let mut canceled = Vec::new();
+        orders.cancel(OrderId(0), &mut |id| canceled.push(id));
+        assert_eq!(canceled, vec![OrderId(0), OrderId(1), OrderId(2)]);
+    }
+
+    #[test]
+    fn cancel_grandchildren() {
+        let mut orders = OrdersComponent::<4>::default();
+        orders.active_orders.push(order(0, None));
+        orders.active_orders.push(order(1, Some(0)));
+        orders.tmp_orders.push(order(2, Some(1)));
+        orders.tmp_orders.push(order(3, None));
+        let mut canceled = Vec::new();
+        orders.cancel(OrderId(0), &mut |id| canceled.push(id));
+        assert_eq!(canceled, vec![OrderId(0), OrderId(1), OrderId(2)]);
+        assert!(orders.tmp_orders.contains(OrderId(3)));
+        canceled.clear();
+        orders.cancel(OrderId(0), &mut |id| canceled.push(id));
+        assert!(canceled.is_empty());
+    }
 }
@@ -4,6 +4,59 @@ use crate::Value;
 
 pub struct ParamConfig(pub f32);
 
+/// Range of values a hyperparameter can take, declared with `Param![lo..hi, step]`. Upper bound
+/// is excluded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParamRange {
+    pub name: &'static str,
+    pub lo: f32,
+    pub hi: f32,
+    pub step: f32,
+}
+
+/// Fraction of a step that is ignored when counting values of a `ParamRange`.
+const STEP_EPSILON: f32 = 1e-3;
+
+impl ParamRange {
+    /// Number of values inside the range.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        if self.step <= 0. || self.hi <= self.lo {
+            return 0;
+        }
+        // A value that lands on `hi` because of a rounding error is not inside the range.
+        let len = (self.hi - self.lo) / self.step - STEP_EPSILON;
+        let floor = len as usize;
+        if len > floor as f32 { floor + 1 } else { floor }
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `i`-th value of the range.
+    #[inline(always)]
+    pub fn value(&self, i: usize) -> f32 {
+        self.lo + i as f32 * self.step
+    }
+}
+
+/// Hyperparameters of a strategy. Implemented by `strategy` macro on the generated system for
+/// every `Param![lo..hi, step]` parameter.
+pub trait StrategyParams {
+    /// Ranges in the order of system parameters.
+    const PARAMS: &'static [ParamRange];
+    type AddConfigs<B: EcsBuilder, E: EntityRelay>: EcsBuilder;
+
+    /// Adds `ParamConfig` for each hyperparameter to entity `E`. `values` are in the same order
+    /// as `PARAMS`.
+    fn add_configs<B: EcsBuilder, E: EntityRelay>(
+        builder: B,
+        values: &[f32],
+    ) -> Self::AddConfigs<B, E>;
+}
+
 pub struct HyperParam<'w, 's, const N: usize> {
     value: f32,
     _marker: PhantomSystemParam<'w, 's, N>,
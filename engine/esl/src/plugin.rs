@@ -3,10 +3,12 @@ use inception::{resources::Break, *};
 use crate::{
     loop_index::LoopIndexResource,
     stages::{IncLoopIndex, IncPreLoopIndex},
-    LoopEndBoundExcluded, LoopIndex,
+    *,
 };
 
 pub struct CorePlugin {
+    /// Index of the first candle of the loop. Set this to 0 to backtest from the start.
+    pub loop_start_bound: usize,
     /// Set this to the number of candles provided.
     pub loop_end_bound_excluded: usize,
 }
@@ -23,7 +25,8 @@ impl Plugin for CorePlugin {
     #[inline(always)]
     fn build<B: EcsBuilder>(self, builder: B) -> Self::Build<B> {
         builder
-            .add_resource(LoopIndexResource(0))
+            .add_resource(LoopIndexResource(self.loop_start_bound))
+            .add_resource(LoopStartBound(self.loop_start_bound))
             .add_resource(LoopEndBoundExcluded(self.loop_end_bound_excluded))
             .add_system_without_plugin(inc_loop_index::new(), IncPreLoopIndex::new())
             .add_system_without_plugin(inc_loop_index::new(), IncLoopIndex::new())
@@ -37,3 +40,99 @@ fn inc_loop_index(mut index: LoopIndex, mut break_: Res<Break>, end: Res<LoopEnd
         **break_ = Break(true);
     }
 }
+
+/// Resources that are used by metrics. By default, metrics are of a single account that runs on a
+/// single thread.
+pub struct MetricsPlugin {
+    /// At the start of the backtest, metrics are loaded from this memory. At the end of the
+    /// backtest, metrics are written back.
+    pub metrics_ptr: *mut u8,
+    /// Set this to the number of candles provided.
+    pub n_samples: usize,
+    pub accounts_per_thread: usize,
+    pub threads_per_device: usize,
+    /// Index of the thread whose metrics are in `metrics_ptr`.
+    pub thread_id: usize,
+    /// Yearly bond yield (not in percentages)
+    pub risk_free_rate: f32,
+    pub trading_days_per_year: f32,
+}
+
+impl MetricsPlugin {
+    /// Metrics of a single account on thread 0, without a risk free rate and with 252 trading days
+    /// per year.
+    #[inline(always)]
+    pub fn new(metrics_ptr: *mut u8, n_samples: usize) -> Self {
+        Self {
+            metrics_ptr,
+            n_samples,
+            accounts_per_thread: 1,
+            threads_per_device: 1,
+            thread_id: 0,
+            risk_free_rate: 0.,
+            trading_days_per_year: 252.,
+        }
+    }
+}
+
+impl Plugin for MetricsPlugin {
+    type Deps<L: PluginLoader> = L;
+
+    type Build<B: EcsBuilder> = impl EcsBuilder;
+
+    #[inline(always)]
+    fn deps<L: PluginLoader>(&mut self, loader: L) -> Self::Deps<L> {
+        loader
+    }
+
+    #[inline(always)]
+    fn build<B: EcsBuilder>(self, builder: B) -> Self::Build<B> {
+        builder
+            .add_resource(MetricsPtr(self.metrics_ptr))
+            .add_resource(AccountsPerThread(self.accounts_per_thread))
+            .add_resource(ThreadsPerDevice(self.threads_per_device))
+            .add_resource(ThreadId(self.thread_id))
+            .add_resource(NSamples(self.n_samples))
+            .add_resource(RiskFreeRate(self.risk_free_rate))
+            .add_resource(TradingDaysPerYear(self.trading_days_per_year))
+    }
+}
+
+/// Plugins of a backtest over candles: `CorePlugin`, `MetricsPlugin` and `HlcvBacktestPlugin`.
+/// # Example
+/// ```ignore
+/// let builder = EcsBuilderStruct::new::<_, 6>(schedule, EntitiesBuilderStruct1::new())
+///     .add_plugins(
+///         DefaultBacktestPlugins {
+///             core: CorePlugin {
+///                 loop_start_bound: 0,
+///                 loop_end_bound_excluded: input_len,
+///             },
+///             metrics: MetricsPlugin::new(metrics.as_mut_ptr(), input_len),
+///             backtest: HlcvBacktestPlugin { .. },
+///         }
+///         .build()
+///         .replace(MetricsPlugin {
+///             risk_free_rate: 0.04,
+///             ..MetricsPlugin::new(metrics.as_mut_ptr(), input_len)
+///         }),
+///     );
+/// ```
+pub struct DefaultBacktestPlugins<I, S> {
+    pub core: CorePlugin,
+    pub metrics: MetricsPlugin,
+    pub backtest: HlcvBacktestPlugin<I, S>,
+}
+
+impl<I: Inputs + 'static, S: Nest + 'static> PluginGroup for DefaultBacktestPlugins<I, S> {
+    type Plugins =
+        Nested<Nested<Nested<StackedNest, CorePlugin>, MetricsPlugin>, HlcvBacktestPlugin<I, S>>;
+
+    #[inline(always)]
+    fn build(self) -> PluginGroupBuilder<Self::Plugins> {
+        PluginGroupBuilder::new()
+            .add(self.core)
+            .add(self.metrics)
+            .add(self.backtest)
+    }
+}
@@ -83,7 +83,7 @@ where
             State<P::Value>,
         > as EcsBuilder>::AddSystemToStage<
             update_prev::System<P, SB, ParamName>,
-            inception::stages::Stage3,
+            crate::stages::UpdatePrev,
         >;
     type Item<'world, 'state, Wrld: World> = Prev<'world, 'state, P, N>;
     type State = P::State;
@@ -245,3 +245,31 @@ mod prev2 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stages::{BacktestSchedule, Signal};
+
+    #[system]
+    fn watch(_price: Prev<&'static f32>) {}
+
+    #[test]
+    fn update_prev_stage() {
+        let description = EcsBuilderStruct::new::<_, 0>(
+            BacktestSchedule::builder(),
+            EntitiesBuilderStruct1::new(),
+        )
+        .add_plugin(inception::CorePlugin)
+        .add_component(0f32, Entity0)
+        .add_system(watch::new(), Signal::new())
+        .describe();
+        let stage = description
+            .stages
+            .iter()
+            .find(|x| x.name == "UpdatePrev")
+            .unwrap();
+        assert_eq!(stage.systems.len(), 1);
+        assert_eq!(ShortName(stage.systems[0]).to_string(), "update_prev");
+    }
+}
@@ -1,5 +1,7 @@
 pub use crate::types::{Fee, Slippage};
 
+/// Index of the first candle of the loop.
+pub struct LoopStartBound(pub usize);
 pub struct LoopEndBoundExcluded(pub usize);
 pub struct AccountsPerThread(pub usize);
 pub struct ThreadsPerDevice(pub usize);
@@ -9,10 +11,13 @@ pub struct NTrackers(pub usize);
 pub struct MetricsPtr(pub *mut u8);
 pub struct TracksPtr(pub *mut u8);
 pub struct StartingBalance(pub f32);
+/// Maximum number of entries in the same direction.
+pub struct MaxPyramiding(pub u32);
 pub struct TradingDaysPerYear(pub f32);
 /// Yearly bond yield (not in percentages)
 pub struct RiskFreeRate(pub f32);
-/// Elapsed time of a simulation in nanoseconds. e.g. `current_candle_index * timeframe_ns`.
+/// Elapsed time of a simulation in nanoseconds. e.g.
+/// `(current_candle_index - loop_start_bound) * timeframe_ns`.
 pub struct Elapsed(pub u64);
 impl Elapsed {
     pub fn years(&self) -> f32 {
@@ -24,5 +29,7 @@ impl Elapsed {
     }
 }
 pub struct TimeframeS(pub u32);
+/// Seconds between funding payments of perpetual swaps, 0 if there is no funding.
+pub struct FundingIntervalS(pub u32);
 pub struct StartTimestampNs(pub u64);
 // pub struct TimestampMS(pub u64);
@@ -0,0 +1,369 @@
+//! Grid search and random sampling over hyperparameters declared with `Param![lo..hi, step]`.
+use std::{marker::PhantomData, prelude::v1::*};
+
+use inception::*;
+
+use crate::param::{ParamRange, StrategyParams};
+
+/// Iterates over the cartesian product of parameter ranges. The last parameter changes the
+/// fastest.
+pub struct Grid<'a> {
+    ranges: &'a [ParamRange],
+    indices: Vec<usize>,
+    done: bool,
+}
+
+impl<'a> Grid<'a> {
+    pub fn new(ranges: &'a [ParamRange]) -> Self {
+        Self {
+            ranges,
+            indices: vec![0; ranges.len()],
+            done: ranges.iter().any(|x| x.is_empty()),
+        }
+    }
+
+    /// Total number of combinations.
+    pub fn len(&self) -> usize {
+        self.ranges.iter().map(|x| x.len()).product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a> Iterator for Grid<'a> {
+    type Item = Vec<f32>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let item = self
+            .ranges
+            .iter()
+            .zip(&self.indices)
+            .map(|(range, &i)| range.value(i))
+            .collect();
+        self.done = true;
+        for (range, i) in self.ranges.iter().zip(&mut self.indices).rev() {
+            *i += 1;
+            if *i < range.len() {
+                self.done = false;
+                break;
+            }
+            *i = 0;
+        }
+        Some(item)
+    }
+}
+
+/// Small deterministic pseudo random number generator (SplitMix64).
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..len`.
+    pub fn below(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+}
+
+/// Samples `n` combinations uniformly. Every value lies on the `step` grid of its range.
+pub fn random_samples(ranges: &[ParamRange], n: usize, seed: u64) -> Vec<Vec<f32>> {
+    if ranges.iter().any(|x| x.is_empty()) {
+        return Vec::new();
+    }
+    let mut rng = Rng::new(seed);
+    (0..n)
+        .map(|_| {
+            ranges
+                .iter()
+                .map(|range| range.value(rng.below(range.len())))
+                .collect()
+        })
+        .collect()
+}
+
+/// Samples `n` combinations with latin hypercube sampling. Each range is split into `n` strata and
+/// every stratum is used exactly once per parameter. Every value lies on the `step` grid of its
+/// range.
+pub fn latin_hypercube_samples(ranges: &[ParamRange], n: usize, seed: u64) -> Vec<Vec<f32>> {
+    if n == 0 || ranges.iter().any(|x| x.is_empty()) {
+        return Vec::new();
+    }
+    let mut rng = Rng::new(seed);
+    let mut samples = vec![Vec::with_capacity(ranges.len()); n];
+    let mut strata: Vec<usize> = (0..n).collect();
+    for range in ranges {
+        // Fisher-Yates shuffle
+        for i in (1..n).rev() {
+            strata.swap(i, rng.below(i + 1));
+        }
+        let len = range.len();
+        for (sample, &stratum) in samples.iter_mut().zip(&strata) {
+            let start = stratum * len / n;
+            let end = ((stratum + 1) * len / n).max(start + 1);
+            sample.push(range.value(start + rng.below(end - start)));
+        }
+    }
+    samples
+}
+
+/// Entity slots that receive one parameter combination each. Use `NestTy!` to list the entities,
+/// e.g. `NestTy!(Entity1, Entity0)`.
+pub trait SweepSlots {
+    const LEN: usize;
+    type AddConfigs<S: StrategyParams, B: EcsBuilder>: EcsBuilder;
+
+    /// Adds configs of `combinations[i]` to `i`-th entity. Slots without a combination reuse the
+    /// first one, their results are discarded.
+    fn add_configs<S: StrategyParams, B: EcsBuilder>(
+        builder: B,
+        combinations: &[Vec<f32>],
+    ) -> Self::AddConfigs<S, B>;
+}
+
+impl SweepSlots for StackedNest {
+    type AddConfigs<S: StrategyParams, B: EcsBuilder> = B;
+
+    const LEN: usize = 0;
+
+    #[inline(always)]
+    fn add_configs<S: StrategyParams, B: EcsBuilder>(
+        builder: B,
+        _combinations: &[Vec<f32>],
+    ) -> Self::AddConfigs<S, B> {
+        builder
+    }
+}
+
+impl<I: SweepSlots, E: EntityRelay> SweepSlots for Nested<I, E> {
+    type AddConfigs<S: StrategyParams, B: EcsBuilder> = S::AddConfigs<I::AddConfigs<S, B>, E>;
+
+    const LEN: usize = I::LEN + 1;
+
+    #[inline(always)]
+    fn add_configs<S: StrategyParams, B: EcsBuilder>(
+        builder: B,
+        combinations: &[Vec<f32>],
+    ) -> Self::AddConfigs<S, B> {
+        let builder = I::add_configs::<S, B>(builder, combinations);
+        let values = combinations.get(I::LEN).unwrap_or(&combinations[0]);
+        S::add_configs::<_, E>(builder, values)
+    }
+}
+
+/// Parameters of one combination and metrics at the end of its backtest.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepRow<M> {
+    pub params: Vec<f32>,
+    pub metrics: M,
+}
+
+/// Runs a backtest for every combination of strategy `S` parameters. Each batch of
+/// `Slots::LEN` combinations is assigned to entities, built and ran for one `BacktestSchedule`
+/// pass. The ECS is built again for each batch, so there are as many passes as there are batches.
+/// Use as many slots as there are combinations to run all of them in a single pass.
+pub struct Sweep<S, Slots> {
+    _p: PhantomData<(S, Slots)>,
+}
+
+impl<S: StrategyParams, Slots: SweepSlots> Sweep<S, Slots> {
+    /// All parameter combinations of `S`.
+    pub fn grid() -> Grid<'static> {
+        Grid::new(S::PARAMS)
+    }
+
+    /// `n` uniformly sampled parameter combinations of `S`.
+    pub fn random(n: usize, seed: u64) -> Vec<Vec<f32>> {
+        random_samples(S::PARAMS, n, seed)
+    }
+
+    /// `n` latin hypercube sampled parameter combinations of `S`.
+    pub fn latin_hypercube(n: usize, seed: u64) -> Vec<Vec<f32>> {
+        latin_hypercube_samples(S::PARAMS, n, seed)
+    }
+
+    /// Runs the grid.
+    ///
+    /// * `builder` - creates a builder with plugins and resources, it is called for each batch.
+    /// * `finish` - adds metrics and systems and builds the ecs.
+    /// * `read` - reads metrics of an entity after the backtest.
+    pub fn run<B, E, M, FB, FF, FR>(builder: FB, finish: FF, read: FR) -> Vec<SweepRow<M>>
+    where
+        B: EcsBuilder,
+        E: Ecs,
+        FB: FnMut() -> B,
+        FF: FnMut(Slots::AddConfigs<S, B>) -> E,
+        FR: FnMut(&E, Entity) -> M,
+    {
+        Self::run_combinations(Self::grid(), builder, finish, read)
+    }
+
+    /// Same as `run` but over provided combinations.
+    pub fn run_combinations<B, E, M, C, FB, FF, FR>(
+        combinations: C,
+        mut builder: FB,
+        mut finish: FF,
+        mut read: FR,
+    ) -> Vec<SweepRow<M>>
+    where
+        B: EcsBuilder,
+        E: Ecs,
+        C: IntoIterator<Item = Vec<f32>>,
+        FB: FnMut() -> B,
+        FF: FnMut(Slots::AddConfigs<S, B>) -> E,
+        FR: FnMut(&E, Entity) -> M,
+    {
+        assert!(Slots::LEN > 0, "sweep requires at least one entity slot");
+        let mut rows = Vec::new();
+        let mut batch = Vec::with_capacity(Slots::LEN);
+        let mut combinations = combinations.into_iter().peekable();
+        while combinations.peek().is_some() {
+            batch.clear();
+            batch.extend(combinations.by_ref().take(Slots::LEN));
+            let mut ecs = finish(Slots::add_configs::<S, B>(builder(), &batch));
+            ecs.run();
+            for (i, params) in batch.drain(..).enumerate() {
+                rows.push(SweepRow {
+                    params,
+                    metrics: read(&ecs, Entity(i)),
+                });
+            }
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    const RANGES: &[ParamRange] = &[
+        ParamRange {
+            name: "a",
+            lo: 1.,
+            hi: 3.,
+            step: 1.,
+        },
+        ParamRange {
+            name: "b",
+            lo: 0.,
+            hi: 1.,
+            step: 0.4,
+        },
+    ];
+
+    #[test]
+    fn grid() {
+        let grid = Grid::new(RANGES);
+        assert_eq!(grid.len(), 6);
+        let combinations: Vec<_> = grid.collect();
+        assert_eq!(
+            combinations,
+            vec![
+                vec![1., 0.],
+                vec![1., 0.4],
+                vec![1., 0.8],
+                vec![2., 0.],
+                vec![2., 0.4],
+                vec![2., 0.8],
+            ]
+        );
+    }
+
+    #[test]
+    fn random() {
+        let samples = random_samples(RANGES, 32, 7);
+        assert_eq!(samples.len(), 32);
+        assert_eq!(samples, random_samples(RANGES, 32, 7));
+        assert_ne!(samples, random_samples(RANGES, 32, 8));
+        let grid: Vec<_> = Grid::new(RANGES).collect();
+        assert!(samples.iter().all(|x| grid.contains(x)));
+    }
+
+    #[test]
+    fn latin_hypercube() {
+        let ranges = [ParamRange {
+            name: "a",
+            lo: 0.,
+            hi: 100.,
+            step: 1.,
+        }];
+        let samples = latin_hypercube_samples(&ranges, 10, 3);
+        assert_eq!(samples, latin_hypercube_samples(&ranges, 10, 3));
+        let mut strata: Vec<_> = samples.iter().map(|x| x[0] as usize / 10).collect();
+        strata.sort_unstable();
+        assert_eq!(strata, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn range_len() {
+        let range = |lo, hi, step| ParamRange {
+            name: "a",
+            lo,
+            hi,
+            step,
+        };
+        assert_eq!(range(0., 1., 0.1).len(), 10);
+        assert_eq!(range(0., 0.3, 0.1).len(), 3);
+        assert_eq!(range(0.7, 1., 0.1).len(), 3);
+        assert_eq!(range(1., 100., 1.).len(), 99);
+        assert_eq!(range(0., 1., 0.4).len(), 3);
+        let range = range(0., 0.3, 0.1);
+        assert!(range.value(range.len() - 1) < range.hi);
+    }
+
+    #[strategy]
+    fn strat(fast: Param![2..10, 2], slow: Param![0.5..1.5, 0.25]) {
+        let _ = (fast.get(), slow.get());
+    }
+
+    #[test]
+    fn strategy_ranges() {
+        assert_eq!(
+            <strat::System as StrategyParams>::PARAMS,
+            &[
+                ParamRange {
+                    name: "fast",
+                    lo: 2.,
+                    hi: 10.,
+                    step: 2.,
+                },
+                ParamRange {
+                    name: "slow",
+                    lo: 0.5,
+                    hi: 1.5,
+                    step: 0.25,
+                },
+            ]
+        );
+        let grid: Vec<_> = Sweep::<strat::System, NestTy!(Entity0)>::grid().collect();
+        assert_eq!(grid.len(), 16);
+        assert_eq!(grid[0], vec![2., 0.5]);
+        assert_eq!(grid[15], vec![8., 1.25]);
+    }
+
+    #[test]
+    fn empty_grid() {
+        let mut ranges = RANGES.to_vec();
+        ranges[1].hi = ranges[1].lo;
+        assert_eq!(Grid::new(&ranges).next(), None);
+    }
+}
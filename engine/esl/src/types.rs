@@ -7,6 +7,16 @@ pub enum PositionAction {
     Close,
 }
 
+/// Whether an order buys. Buy stops trigger above the price and buy limits fill below it, sell
+/// orders the other way around.
+#[inline(always)]
+pub(crate) fn is_buy(position_action: PositionAction, direction: Direction) -> bool {
+    matches!(
+        (position_action, direction),
+        (PositionAction::Open, Direction::Long) | (PositionAction::Close, Direction::Short)
+    )
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Direction {
     Long,
@@ -51,6 +61,60 @@ pub enum TriggerPrice {
     Absolute(f32),
 }
 
+/// Margin requirements of an account. Positions are cross margined by the whole balance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Margin {
+    /// Fraction of the position value needed to open it.
+    pub initial_margin: f32,
+    /// Fraction of the entry value the equity must stay above, otherwise the position is
+    /// liquidated.
+    pub maintenance_margin: f32,
+    pub max_leverage: f32,
+}
+
+impl Margin {
+    #[inline(always)]
+    pub fn leverage(&self) -> f32 {
+        self.max_leverage.min(1. / self.initial_margin)
+    }
+}
+
+impl Default for Margin {
+    /// No leverage.
+    fn default() -> Self {
+        Margin {
+            initial_margin: 1.,
+            maintenance_margin: 0.,
+            max_leverage: 1.,
+        }
+    }
+}
+
+/// Distance of a trailing stop from the best price since the order was placed.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Trail {
+    Relative(f32),
+    Absolute(f32),
+}
+
+impl Trail {
+    /// Stop price that trails `price`. Buy stops are above the price, sell stops below.
+    #[inline(always)]
+    pub fn stop_price(self, price: f32, buy: bool) -> f32 {
+        match (self, buy) {
+            (Trail::Relative(x), false) => price * (1. - x),
+            (Trail::Relative(x), true) => price * (1. + x),
+            (Trail::Absolute(x), false) => price - x,
+            (Trail::Absolute(x), true) => price + x,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct OrderId(pub u32);
 
+/// Index of an instrument in a portfolio.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InstrumentId(pub usize);
+
@@ -0,0 +1,273 @@
+//! Walk-forward optimization. Parameters are optimized on an in-sample window, then the winner is
+//! replayed on the following out-of-sample window. Windows roll forward by the out-of-sample
+//! length and out-of-sample equity curves are joined into one.
+use core::ops::Range;
+use std::prelude::v1::*;
+
+use inception::*;
+
+use crate::{
+    param::StrategyParams,
+    stages::PostTrade,
+    sweep::{Sweep, SweepRow, SweepSlots},
+    Balance, Metric, Value,
+};
+
+/// In-sample and out-of-sample candle ranges of one walk-forward step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Window {
+    pub in_sample: Range<usize>,
+    /// Candles before `out_of_sample` that are replayed so that indicators don't start cold. Their
+    /// equity is discarded, but positions that are opened during them are carried over.
+    pub warm_up: Range<usize>,
+    pub out_of_sample: Range<usize>,
+}
+
+/// Rolling walk-forward windows over a series.
+#[derive(Clone, Copy, Debug)]
+pub struct WalkForward {
+    /// Number of candles to optimize on.
+    pub in_sample: usize,
+    /// Number of candles to replay the winner on. Windows are moved by this amount.
+    pub out_of_sample: usize,
+    /// Number of candles before an out-of-sample window to warm up indicators on, e.g. the
+    /// longest indicator period.
+    pub warm_up: usize,
+}
+
+/// Result of an out-of-sample replay.
+#[derive(Clone, Debug)]
+pub struct WalkForwardStep {
+    pub window: Window,
+    /// Winning parameters of the in-sample window.
+    pub params: Vec<f32>,
+    /// Score of the winner on the in-sample window.
+    pub in_sample_score: f32,
+    /// Equity curve of the out-of-sample window.
+    pub equity: Vec<f32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct WalkForwardReport {
+    pub steps: Vec<WalkForwardStep>,
+    /// Out-of-sample equity curves joined together, see `join_equity_curves`.
+    pub equity: Vec<f32>,
+    /// Windows where every in-sample score was NaN, they weren't replayed.
+    pub skipped: Vec<Window>,
+}
+
+impl WalkForward {
+    /// Windows over `len` candles. The last out-of-sample window is truncated to fit.
+    pub fn windows(&self, len: usize) -> Vec<Window> {
+        let mut windows = Vec::new();
+        if self.in_sample == 0 || self.out_of_sample == 0 {
+            return windows;
+        }
+        let mut start = 0;
+        while start + self.in_sample < len {
+            let split = start + self.in_sample;
+            windows.push(Window {
+                in_sample: start..split,
+                warm_up: split.saturating_sub(self.warm_up)..split,
+                out_of_sample: split..(split + self.out_of_sample).min(len),
+            });
+            start += self.out_of_sample;
+        }
+        windows
+    }
+
+    /// Runs the walk-forward over `len` candles.
+    ///
+    /// * `combinations` - parameter combinations that are tried on every in-sample window, e.g.
+    ///   `Sweep::<S, Slots>::grid().collect()`.
+    /// * `builder` - creates a builder for a window, `CorePlugin` should use window bounds. Replays
+    ///   start at the start of the warm-up.
+    /// * `finish` - adds metrics and systems and builds the ecs.
+    /// * `score` - value to maximize, e.g. `CagrOverMaxDd` of an entity.
+    /// * `equity` - equity curve of an entity, e.g. `EquityCurve` when `EquityCurvePlugin` is
+    ///   added.
+    pub fn run<S, Slots, B, E, FB, FF, FS, FE>(
+        &self,
+        len: usize,
+        combinations: &[Vec<f32>],
+        mut builder: FB,
+        mut finish: FF,
+        mut score: FS,
+        mut equity: FE,
+    ) -> WalkForwardReport
+    where
+        S: StrategyParams,
+        Slots: SweepSlots,
+        B: EcsBuilder,
+        E: Ecs,
+        FB: FnMut(Range<usize>) -> B,
+        FF: FnMut(Slots::AddConfigs<S, B>) -> E,
+        FS: FnMut(&E, Entity) -> f32,
+        FE: FnMut(&E, Entity) -> Vec<f32>,
+    {
+        let mut steps = Vec::new();
+        let mut skipped = Vec::new();
+        if combinations.is_empty() {
+            return WalkForwardReport {
+                steps,
+                equity: Vec::new(),
+                skipped: self.windows(len),
+            };
+        }
+        for window in self.windows(len) {
+            let rows = Sweep::<S, Slots>::run_combinations(
+                combinations.iter().cloned(),
+                || builder(window.in_sample.clone()),
+                &mut finish,
+                &mut score,
+            );
+            let best = match best_row(rows) {
+                Some(best) => best,
+                None => {
+                    skipped.push(window);
+                    continue;
+                },
+            };
+            let mut replay = Sweep::<S, Slots>::run_combinations(
+                Some(best.params.clone()),
+                || builder(window.warm_up.start..window.out_of_sample.end),
+                &mut finish,
+                &mut equity,
+            );
+            let mut equity = replay.swap_remove(0).metrics;
+            equity.drain(..window.warm_up.len().min(equity.len()));
+            steps.push(WalkForwardStep {
+                window,
+                params: best.params,
+                in_sample_score: best.metrics,
+                equity,
+            });
+        }
+        let equity = join_equity_curves(steps.iter().map(|x| x.equity.as_slice()));
+        WalkForwardReport {
+            steps,
+            equity,
+            skipped,
+        }
+    }
+}
+
+/// Row with the highest score, NaN scores are ignored.
+fn best_row(rows: Vec<SweepRow<f32>>) -> Option<SweepRow<f32>> {
+    rows.into_iter()
+        .filter(|x| !x.metrics.is_nan())
+        .fold(None, |best, row| match best {
+            Some(best) if best.metrics >= row.metrics => Some(best),
+            _ => Some(row),
+        })
+}
+
+/// Joins equity curves by compounding. Each curve is scaled so that it starts where the previous
+/// one ended, the first point of a curve is the last point of the previous one, so it isn't
+/// repeated.
+pub fn join_equity_curves<'a, I: IntoIterator<Item = &'a [f32]>>(curves: I) -> Vec<f32> {
+    let mut joined: Vec<f32> = Vec::new();
+    for curve in curves {
+        let first = match curve.first() {
+            Some(&first) => first,
+            None => continue,
+        };
+        match joined.last() {
+            Some(&last) => {
+                let scale = if first != 0. { last / first } else { 1. };
+                joined.extend(curve[1..].iter().map(|x| x * scale));
+            },
+            None => joined.extend_from_slice(curve),
+        }
+    }
+    joined
+}
+
+/// Balance of an entity after each candle.
+#[derive(Clone, Debug, Default)]
+pub struct EquityCurve(pub Vec<f32>);
+
+/// Records `EquityCurve` of every entity.
+pub struct EquityCurvePlugin;
+
+impl Plugin for EquityCurvePlugin {
+    type Deps<L: PluginLoader> = L;
+
+    type Build<B: EcsBuilder> = impl EcsBuilder;
+
+    #[inline(always)]
+    fn deps<L: PluginLoader>(&mut self, loader: L) -> Self::Deps<L> {
+        loader
+    }
+
+    #[inline(always)]
+    fn build<B: EcsBuilder>(self, builder: B) -> Self::Build<B> {
+        builder
+            .extend_entities(EquityCurve::default())
+            .add_system(record_equity::new(), PostTrade::new())
+    }
+}
+
+#[system]
+fn record_equity(balance: Metric<Balance>, curve: &mut EquityCurve) {
+    curve.0.push(balance.get());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows() {
+        let walk_forward = WalkForward {
+            in_sample: 4,
+            out_of_sample: 2,
+            warm_up: 5,
+        };
+        assert_eq!(
+            walk_forward.windows(9),
+            vec![
+                Window {
+                    in_sample: 0..4,
+                    warm_up: 0..4,
+                    out_of_sample: 4..6,
+                },
+                Window {
+                    in_sample: 2..6,
+                    warm_up: 1..6,
+                    out_of_sample: 6..8,
+                },
+                Window {
+                    in_sample: 4..8,
+                    warm_up: 3..8,
+                    out_of_sample: 8..9,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn best() {
+        let row = |score| SweepRow {
+            params: vec![score],
+            metrics: score,
+        };
+        assert_eq!(
+            best_row(vec![row(1.), row(f32::NAN), row(3.), row(2.)]),
+            Some(row(3.))
+        );
+        assert_eq!(best_row(vec![row(f32::NAN), row(f32::NAN)]), None);
+    }
+
+    #[test]
+    fn join() {
+        let a = [1., 2.];
+        let b = [1., 1.5];
+        let c = [];
+        let d = [4., 2.];
+        assert_eq!(
+            join_equity_curves([&a[..], &b[..], &c[..], &d[..]]),
+            vec![1., 2., 3., 1.5]
+        );
+    }
+}
@@ -1,14 +1,14 @@
-use convert_base::Convert;
 use darling::FromMeta;
 use ergnomics::some_loop;
 use inception_macros_core::*;
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::{
-    parse_macro_input, parse_quote, AttributeArgs, DeriveInput, FieldsNamed, FieldsUnnamed,
-    GenericArgument, ItemFn, ItemImpl, ItemStruct, LitInt, PathArguments, Type,
+    parse::{Parse, ParseStream},
+    parse_macro_input, parse_quote, AttributeArgs, DeriveInput, Expr, ExprRange, FieldsNamed,
+    FieldsUnnamed, GenericArgument, ItemFn, ItemImpl, ItemStruct, LitInt, Pat, PathArguments,
+    RangeLimits, Token, Type,
 };
-const N_ORDERS: u64 = 9;
 
 /// Expands some spicific parameters related to `esl`. Then applies `#[system]`. In the future this
 /// will extract signal components of a system and combine them from other strategies to create a
@@ -18,13 +18,38 @@ pub fn strategy(_attributes: TokenStream, item: TokenStream) -> TokenStream {
     let esl = macros_util::crate_name("esl");
     let mut input = parse_macro_input!(item as ItemFn);
     input.attrs.push(parse_quote!(#[system]));
+    let mut param_names = Vec::new();
+    let mut param_ranges = Vec::new();
     for arg in &mut input.sig.inputs {
         match arg {
             syn::FnArg::Receiver(_) => {},
             syn::FnArg::Typed(x) => {
                 // Expand Prev<T> into Prev<T<'w, 's, N>>
                 match &mut *x.ty {
-                    Type::Macro(_) => {
+                    Type::Macro(mac) => {
+                        let range = match mac.mac.parse_body::<ParamRangeInput>() {
+                            Ok(range) => range,
+                            Err(e) => return e.into_compile_error().into(),
+                        };
+                        let name = match &*x.pat {
+                            Pat::Ident(ident) => ident.ident.clone(),
+                            pat => {
+                                return syn::Error::new_spanned(pat, "expected an identifier")
+                                    .into_compile_error()
+                                    .into();
+                            },
+                        };
+                        let name_str = name.to_string();
+                        let ParamRangeInput { lo, hi, step } = range;
+                        param_ranges.push(quote! {
+                            #esl::param::ParamRange {
+                                name: #name_str,
+                                lo: (#lo) as f32,
+                                hi: (#hi) as f32,
+                                step: (#step) as f32,
+                            }
+                        });
+                        param_names.push(name);
                         x.ty = parse_quote!(#esl::HyperParam);
                     },
                     Type::Path(path) => {
@@ -57,13 +82,67 @@ pub fn strategy(_attributes: TokenStream, item: TokenStream) -> TokenStream {
             },
         }
     }
+    let system = &input.sig.ident;
+    let (impl_generics, ty_generics, where_clause) = input.sig.generics.split_for_impl();
+    let mut add_configs_ty = quote! { B };
+    for name in &param_names {
+        add_configs_ty = quote! {
+            <#add_configs_ty as #esl::EcsBuilder>::AddConfig<#system::#name, E, #esl::ParamConfig>
+        };
+    }
+    let add_configs_ids = 0..param_names.len();
     let out = quote! {
         #input
+
+        impl #impl_generics #esl::param::StrategyParams for #system::System #ty_generics
+        #where_clause
+        {
+            const PARAMS: &'static [#esl::param::ParamRange] = &[#(#param_ranges),*];
+            type AddConfigs<B: #esl::EcsBuilder, E: #esl::EntityRelay> = #add_configs_ty;
+
+            #[inline(always)]
+            fn add_configs<B: #esl::EcsBuilder, E: #esl::EntityRelay>(
+                builder: B,
+                values: &[f32],
+            ) -> Self::AddConfigs<B, E> {
+                let _ = values;
+                #(
+                    let builder = #esl::EcsBuilder::add_config::<#system::#param_names, E, _>(
+                        builder,
+                        #esl::ParamConfig(values[#add_configs_ids]),
+                    );
+                )*
+                builder
+            }
+        }
     };
     // eprintln!("{}", out);
     out.into()
 }
 
+/// Parses `lo..hi, step` of `Param![lo..hi, step]`.
+struct ParamRangeInput {
+    lo: Expr,
+    hi: Expr,
+    step: Expr,
+}
+
+impl Parse for ParamRangeInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let range: ExprRange = input.parse()?;
+        let _: Token![,] = input.parse()?;
+        let step = input.parse()?;
+        match (range.from, range.limits, range.to) {
+            (Some(lo), RangeLimits::HalfOpen(_), Some(hi)) => Ok(Self {
+                lo: *lo,
+                hi: *hi,
+                step,
+            }),
+            _ => Err(input.error("expected `lo..hi, step`")),
+        }
+    }
+}
+
 #[proc_macro_attribute]
 pub fn metric(_attr: TokenStream, input: TokenStream) -> TokenStream {
     let _esl = macros_util::crate_name("esl");
@@ -354,57 +433,44 @@ pub fn impl_metric(_attr: TokenStream, input: TokenStream) -> TokenStream {
         }
     });
     let stmts = &method.block.stmts;
-    let mut has_execution_order = false;
+    let mut has_deps = false;
     for item in &item_impl.items {
-        if let syn::ImplItem::Type(ty) = item {
-            if ty.ident == "ExecutionOrder" {
-                has_execution_order = true;
+        if let syn::ImplItem::Const(item) = item {
+            if item.ident == "DEPS" {
+                has_deps = true;
                 break;
             }
         }
     }
-    if !has_execution_order {
-        let mut deps = method
-            .sig
-            .inputs
-            .iter()
-            .filter_map(|x| match x {
-                syn::FnArg::Receiver(_) => {
-                    panic!("Only functions without `self` receiver are allowed")
-                },
-                syn::FnArg::Typed(x) => match &*x.ty {
-                    Type::Path(path) => {
-                        let segment = path.path.segments.last().unwrap();
-                        if segment.ident == "Metric" {
-                            match &segment.arguments {
-                                PathArguments::AngleBracketed(args) => {
-                                    let n: Type = parse_quote! {N};
-                                    args.args.iter().find(|x| match x {
-                                        GenericArgument::Type(x) => {
-                                            *x != *item_impl.self_ty && *x != n
-                                        },
-                                        _ => false,
-                                    })
-                                },
-                                _ => panic!("`Metric` must be generic"),
-                            }
-                        } else {
-                            None
+    if !has_deps {
+        let deps = method.sig.inputs.iter().filter_map(|x| match x {
+            syn::FnArg::Receiver(_) => {
+                panic!("Only functions without `self` receiver are allowed")
+            },
+            syn::FnArg::Typed(x) => match &*x.ty {
+                Type::Path(path) => {
+                    let segment = path.path.segments.last().unwrap();
+                    if segment.ident == "Metric" {
+                        match &segment.arguments {
+                            PathArguments::AngleBracketed(args) => {
+                                let n: Type = parse_quote! {N};
+                                args.args.iter().find(|x| match x {
+                                    GenericArgument::Type(x) => *x != *item_impl.self_ty && *x != n,
+                                    _ => false,
+                                })
+                            },
+                            _ => panic!("`Metric` must be generic"),
                         }
-                    },
-                    _ => None,
+                    } else {
+                        None
+                    }
                 },
-            })
-            .peekable();
-        if deps.peek().is_none() {
-            item_impl.items.push(parse_quote! {
-                type ExecutionOrder = Order0;
-            });
-        } else {
-            item_impl.items.push(parse_quote! {
-            type ExecutionOrder = <MaxExecutionOrder::<(#(<#deps as MetricTrait>::ExecutionOrder,)*)> as ExecutionOrder>::Next;
+                _ => None,
+            },
+        });
+        item_impl.items.push(parse_quote! {
+            const DEPS: &'static [&'static str] = &[#(<MetricLabel<#deps> as SystemLabel>::NAME,)*];
         });
-        }
     }
 
     item_impl.items.push(parse_quote! {
@@ -558,158 +624,3 @@ pub fn indicator(_attributes: TokenStream, input: TokenStream) -> TokenStream {
     };
     out.into()
 }
-
-#[proc_macro]
-pub fn impl_max_execution_order(_input: TokenStream) -> TokenStream {
-    let implement = |x: Vec<u8>| {
-        let orders = x.iter().map(|x| format_ident!("Order{}", x));
-        let next = *x.iter().max().unwrap_or(&0) + 1;
-        let next_order = format_ident!("Order{}", next);
-        quote! {
-            impl ExecutionOrder for MaxExecutionOrder<(#(#orders,)*)> {
-                type Next = #next_order;
-            }
-        }
-    };
-    let n_orders_in_max = 3;
-    let impls = (1..=n_orders_in_max).flat_map(|n_orders| {
-        let mut base = Convert::new(10, N_ORDERS);
-        (0..N_ORDERS.pow(n_orders)).map(move |x| {
-            let mut output = base.convert::<u64, u8>(&[x]);
-            while output.len() != n_orders as usize {
-                output.push(0);
-            }
-            implement(output)
-        })
-    });
-    let out = quote! {
-        #(#impls)*
-    };
-    out.into()
-}
-
-#[proc_macro]
-pub fn impl_add_metrics_and_trackers(_input: TokenStream) -> TokenStream {
-    let impls = (0..N_ORDERS).map(|x| {
-        let add_update_metric_fn = format_ident!("add_update_metric{}", x);
-        let add_update_metric = format_ident!("AddUpdateMetric{}", x);
-        let add_block_metric_fn = format_ident!("add_block_metric{}", x);
-        let add_block_metric = format_ident!("AddBlockMetric{}", x);
-        let update_metrics = format_ident!("update_metrics{}", x);
-        let add_update_tracker_fn = format_ident!("add_update_tracker{}", x);
-        let add_update_tracker = format_ident!("AddUpdateTracker{}", x);
-        let add_block_tracker_fn = format_ident!("add_block_tracker{}", x);
-        let add_block_tracker = format_ident!("AddBlockTracker{}", x);
-        let block_metrics = format_ident!("block_metrics{}", x);
-        let fields = (0..N_ORDERS).filter(|i| *i != x ).map(|x| {
-            let update_metrics = format_ident!("update_metrics{}", x);
-            let block_metrics = format_ident!("block_metrics{}", x);
-            quote! {
-                #update_metrics: self.#update_metrics,
-                #block_metrics: self.#block_metrics,
-            }
-
-        });
-        let update_metrics_fields = quote! {
-            #block_metrics: self.#block_metrics,
-            #update_metrics: Nested::new(
-                MetricUpdateBuilderStruct {
-                    _m: PhantomData::<M>,
-                    _condition: PhantomData::<C>,
-                },
-                self.#update_metrics,
-            )
-        };
-        let block_metrics_fields = quote! {
-            #update_metrics: self.#update_metrics,
-            #block_metrics: Nested::new(
-                MetricUpdateBuilderStruct {
-                    _m: PhantomData::<M>,
-                    _condition: PhantomData::<C>,
-                },
-                self.#block_metrics,
-            )
-        };
-        let update_trackers_fields = quote! {
-            #block_metrics: self.#block_metrics,
-            #update_metrics: Nested::new(
-                TrackerUpdateBuilder {
-                    metric_builder: MetricUpdateBuilderStruct {
-                        _m: PhantomData::<M>,
-                        _condition: PhantomData::<C>,
-                    },
-                },
-                self.#update_metrics,
-            )
-        };
-        let block_trackers_fields = quote! {
-            #update_metrics: self.#update_metrics,
-            #block_metrics: Nested::new(
-                TrackerUpdateBuilder {
-                    metric_builder: MetricUpdateBuilderStruct {
-                        _m: PhantomData::<M>,
-                        _condition: PhantomData::<C>,
-                    },
-                },
-                self.#block_metrics,
-            )
-        };
-        let common_fields = quote! {
-            #(#fields)*
-            mems: Nested::new(
-                MetricMemStruct {
-                    field_offset: self.field_offset,
-                    _m: PhantomData::<M>,
-                },
-                self.mems,
-            ),
-            field_offset: self.field_offset + core::mem::size_of::<M>()
-        };
-        quote! {
-            #[inline(always)]
-            fn #add_update_metric_fn<C: Condition, M: MetricTrait>(self) -> Self::#add_update_metric<C, M> {
-                MetricsBuilderStruct {
-                    builder: self.builder.extend_entities(MetricComponent::<M>::default()),
-                    #update_metrics_fields,
-                    #common_fields,
-                    n_trackers: self.n_trackers,
-                }
-            }
-            #[inline(always)]
-            fn #add_block_metric_fn<C: Condition, M: MetricTrait>(self) -> Self::#add_block_metric<C, M> {
-                MetricsBuilderStruct {
-                    builder: self.builder.extend_entities(MetricComponent::<M>::default()),
-                    #block_metrics_fields,
-                    #common_fields,
-                    n_trackers: self.n_trackers,
-                }
-            }
-            #[inline(always)]
-            fn #add_update_tracker_fn<C: Condition, M: MetricTrait>(self) -> Self::#add_update_tracker<C, M> {
-                MetricsBuilderStruct {
-                    builder: self.builder.extend_entities(
-                        MetricComponent::<M>::new(self.n_trackers)
-                    ),
-                    #update_trackers_fields,
-                    #common_fields,
-                    n_trackers: self.n_trackers + 1,
-                }
-            }
-            #[inline(always)]
-            fn #add_block_tracker_fn<C: Condition, M: MetricTrait>(self) -> Self::#add_block_tracker<C, M> {
-                MetricsBuilderStruct {
-                    builder: self.builder.extend_entities(
-                        MetricComponent::<M>::new(self.n_trackers)
-                    ),
-                    #block_trackers_fields,
-                    #common_fields,
-                    n_trackers: self.n_trackers + 1,
-                }
-            }
-        }
-    });
-    let out = quote! {
-        #(#impls)*
-    };
-    out.into()
-}
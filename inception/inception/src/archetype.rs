@@ -0,0 +1,309 @@
+use core::marker::PhantomData;
+use std::prelude::v1::*;
+
+use all_tuples::repeat;
+
+use crate::{
+    change_detection::Tick,
+    config::ConfigValue,
+    describe::EcsVisitor,
+    entities::{
+        expect_components, EntitiesBuilder, EntityComponent, EntityData, EntityFnMut, EntityRelay,
+        WorldQuery,
+    },
+    nest_module::{Nested, StackedNest},
+    resource_check::Message,
+    Entities, Entity, ParamLabel,
+};
+
+/// Entities that share the same components. Unlike `EntitiesBuilderStruct*`, the number of
+/// entities doesn't change their type, so the same systems can run over thousands of entities.
+/// Entities are numbered from 0. Components are added to every entity, `extend_with` adds a
+/// different value to each of them.
+/// # Example
+/// ```
+/// use inception::*;
+/// let entities = VecArchetype::new(10_000)
+///     .extend_with(|entity| entity.0 as f32 / 100.)
+///     .extend_entities(0u64);
+/// ```
+/// Adding a component to a single entity fails to compile:
+/// ```compile_fail
+/// use inception::*;
+/// let entities = VecArchetype::new(10).add(0u64, Entity0);
+/// ```
+pub struct Archetype<S> {
+    storage: S,
+}
+
+/// Archetype with a number of entities that is known at compile time.
+pub type ArrayArchetype<C, const N: usize> = Archetype<[EntityData<C>; N]>;
+/// Archetype with a number of entities that is known at runtime.
+#[cfg(feature = "alloc")]
+pub type VecArchetype<C> = Archetype<Vec<EntityData<C>>>;
+
+type ExtendConfig<S, Param, T> = Archetype<
+    <S as ArchetypeStorage>::Extend<ConfigValue<<Param as ParamLabel>::System, Param, T>>,
+>;
+
+pub trait ArchetypeStorage: 'static {
+    type Components: EntityComponent + 'static;
+    type Extend<T: 'static>: ArchetypeStorage<Components = Nested<Self::Components, T>>;
+    fn entities(&self) -> &[EntityData<Self::Components>];
+    fn entities_mut(&mut self) -> &mut [EntityData<Self::Components>];
+    fn extend_with<T: 'static, F: FnMut(Entity) -> T>(self, f: F) -> Self::Extend<T>;
+}
+
+impl<C: EntityComponent + 'static, const N: usize> ArchetypeStorage for [EntityData<C>; N] {
+    type Components = C;
+    type Extend<T: 'static> = [EntityData<Nested<C, T>>; N];
+
+    #[inline(always)]
+    fn entities(&self) -> &[EntityData<C>] {
+        self
+    }
+
+    #[inline(always)]
+    fn entities_mut(&mut self) -> &mut [EntityData<C>] {
+        self
+    }
+
+    #[inline(always)]
+    fn extend_with<T: 'static, F: FnMut(Entity) -> T>(self, mut f: F) -> Self::Extend<T> {
+        self.map(|x| {
+            let component = f(x.entity);
+            x.add(component)
+        })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<C: EntityComponent + 'static> ArchetypeStorage for Vec<EntityData<C>> {
+    type Components = C;
+    type Extend<T: 'static> = Vec<EntityData<Nested<C, T>>>;
+
+    #[inline(always)]
+    fn entities(&self) -> &[EntityData<C>] {
+        self
+    }
+
+    #[inline(always)]
+    fn entities_mut(&mut self) -> &mut [EntityData<C>] {
+        self
+    }
+
+    #[inline(always)]
+    fn extend_with<T: 'static, F: FnMut(Entity) -> T>(self, mut f: F) -> Self::Extend<T> {
+        self.into_iter()
+            .map(|x| {
+                let component = f(x.entity);
+                x.add(component)
+            })
+            .collect()
+    }
+}
+
+impl<const N: usize> ArrayArchetype<StackedNest, N> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            storage: core::array::from_fn(|i| EntityData::new(Entity(i))),
+        }
+    }
+}
+
+impl<const N: usize> Default for ArrayArchetype<StackedNest, N> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl VecArchetype<StackedNest> {
+    #[inline(always)]
+    pub fn new(len: usize) -> Self {
+        Self {
+            storage: (0..len).map(|i| EntityData::new(Entity(i))).collect(),
+        }
+    }
+}
+
+impl<S: ArchetypeStorage> Archetype<S> {
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.storage.entities().len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds a component to each entity.
+    #[inline(always)]
+    pub fn extend_with<T: 'static, F: FnMut(Entity) -> T>(self, f: F) -> Archetype<S::Extend<T>> {
+        Archetype {
+            storage: self.storage.extend_with(f),
+        }
+    }
+
+    /// Adds a config of a system parameter to each entity.
+    #[inline(always)]
+    pub fn extend_config_with<Param, T, F>(self, mut f: F) -> ExtendConfig<S, Param, T>
+    where
+        Param: ParamLabel,
+        T: 'static,
+        F: FnMut(Entity) -> T,
+    {
+        self.extend_with(|entity| ConfigValue(f(entity), core::marker::PhantomData))
+    }
+}
+
+macro_rules! impl_archetype_insertions {
+    ($insert:ident, $insert_fn:ident) => {
+        type $insert<C: 'static> = Self;
+
+        #[inline(always)]
+        fn $insert_fn<C: 'static>(self, _component: C) -> Self::$insert<C> {
+            #[allow(clippy::let_unit_value)]
+            let () = SingleEntityCheck::<C>::CHECK;
+            self
+        }
+    };
+}
+
+impl<S: ArchetypeStorage> EntitiesBuilder for Archetype<S> {
+    type Add<C: 'static, ER: EntityRelay> = Self;
+    type ExtendEntities<C: Clone + 'static> = Archetype<S::Extend<C>>;
+
+    repeat!(impl_archetype_insertions, 0, 16, Add, add);
+
+    #[inline(always)]
+    fn add<C: 'static, ER: EntityRelay>(self, _component: C, _entity: ER) -> Self::Add<C, ER> {
+        #[allow(clippy::let_unit_value)]
+        let () = SingleEntityCheck::<C>::CHECK;
+        self
+    }
+
+    #[inline(always)]
+    fn extend_entities<C: Clone + 'static>(self, component: C) -> Self::ExtendEntities<C> {
+        self.extend_with(|_| component.clone())
+    }
+}
+
+impl<S: ArchetypeStorage> Entities for Archetype<S> {
+    #[inline(always)]
+    fn query<'w, F, Q>(&'w mut self, tick: Tick, mut f: F)
+    where
+        F: FnMut(<Q as WorldQuery>::Item<'w>),
+        Q: WorldQuery,
+    {
+        for entity in self.storage.entities_mut() {
+            entity.query::<F, Q>(tick, &mut f);
+        }
+    }
+
+    #[inline(always)]
+    fn query_entity<'w, F, Q>(&'w mut self, entity: Entity, tick: Tick, mut f: F)
+    where
+        F: FnMut(<Q as WorldQuery>::Item<'w>),
+        Q: WorldQuery,
+    {
+        if let Some(x) = self.storage.entities_mut().get_mut(entity.0) {
+            x.query::<F, Q>(tick, &mut f);
+        }
+    }
+
+    #[inline(always)]
+    fn for_each<F>(&mut self, mut f: F)
+    where
+        F: EntityFnMut,
+    {
+        for entity in self.storage.entities_mut() {
+            f.call_mut(entity);
+        }
+    }
+
+    #[inline(always)]
+    fn get_components<Q: WorldQuery>(&mut self, entity: Entity, tick: Tick) -> Option<Q::Item<'_>> {
+        let x = self.storage.entities_mut().get_mut(entity.0)?;
+        unsafe { Q::get_component(&x.components, tick) }
+    }
+
+    #[inline(always)]
+    fn components<Q: WorldQuery>(&mut self, entity: Entity, tick: Tick) -> Q::Item<'_> {
+        expect_components::<Q, _>(entity, self.get_components::<Q>(entity, tick))
+    }
+
+    #[inline(always)]
+    fn get_component<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        self.storage
+            .entities()
+            .get(entity.0)?
+            .components
+            .get_component()
+    }
+
+    #[inline(always)]
+    fn get_component_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.storage
+            .entities_mut()
+            .get_mut(entity.0)?
+            .components
+            .get_component_mut()
+    }
+
+    #[inline(always)]
+    fn describe<V: EcsVisitor>(&self, visitor: &mut V) {
+        for entity in self.storage.entities() {
+            entity.describe(visitor);
+        }
+    }
+}
+
+/// Fails to compile when component `C` is added to a single entity of an archetype.
+struct SingleEntityCheck<C>(PhantomData<C>);
+
+impl<C> SingleEntityCheck<C> {
+    const CHECK: () = {
+        let message = Message::EMPTY
+            .push("Failed to insert `")
+            .push(core::any::type_name::<C>())
+            .push("` component, components of an archetype can only be added to all entities");
+        panic!("{}", message.as_str());
+    };
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::*;
+
+    schedule! {
+        struct Schedule,
+        Update,
+    }
+
+    pub struct Total(f32);
+
+    #[system]
+    fn scale(value: &mut f32, factor: &u64, mut total: Res<Total>) {
+        *value *= *factor as f32;
+        total.0 += *value;
+    }
+
+    #[test]
+    fn vec_archetype() {
+        let entities = VecArchetype::new(1000)
+            .extend_with(|entity| entity.0 as f32)
+            .extend_entities(2u64);
+        let mut ecs = EcsBuilderStruct::new::<_, 0>(Schedule::builder(), entities)
+            .add_resource(Total(0.))
+            .add_system(scale::new(), Update::new())
+            .build();
+        ecs.run();
+        assert_eq!(*ecs.component::<f32>(Entity(0)), 0.);
+        assert_eq!(*ecs.component::<f32>(Entity(999)), 1998.);
+        assert_eq!(ecs.resource::<Total>().0, 999_000.);
+    }
+}
@@ -0,0 +1,443 @@
+use core::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{
+    entities::{EntityComponent, EntityFetch, WorldQuery},
+    impl_no_plugin,
+    resource_check::RequiredResource,
+    unimpl_get_param,
+    world::World,
+    EcsBuilder, PhantomSystemParam, SystemParam, SystemParamNameMapper,
+};
+
+/// Counter of loop iterations that is used to detect changes. It is stored in
+/// `inception::resources::ChangeTick` and incremented at the start of each iteration of a looped
+/// stage. Stages before the first iteration run at tick 0.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tick(pub u32);
+
+impl Tick {
+    #[inline(always)]
+    pub fn increment(&mut self) {
+        self.0 = self.0.wrapping_add(1);
+    }
+}
+
+/// Ticks at which a value was added and last changed.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChangeTicks {
+    pub added: Tick,
+    pub changed: Tick,
+}
+
+/// Wraps a component or a resource to track when it was added and changed. Mutable access
+/// requires the current tick, use `Mut` as a system parameter or `Query::change_tick` inside of
+/// queries.
+#[derive(Default, Clone, Debug)]
+pub struct Tracked<T> {
+    value: T,
+    ticks: ChangeTicks,
+}
+
+impl<T> Tracked<T> {
+    /// Creates a value that is added and changed at tick 0.
+    #[inline(always)]
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            ticks: ChangeTicks::default(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn ticks(&self) -> ChangeTicks {
+        self.ticks
+    }
+
+    #[inline(always)]
+    pub fn is_added(&self, tick: Tick) -> bool {
+        self.ticks.added == tick
+    }
+
+    /// Also true when the value was added at `tick`.
+    #[inline(always)]
+    pub fn is_changed(&self, tick: Tick) -> bool {
+        self.ticks.changed == tick
+    }
+
+    #[inline(always)]
+    pub fn set_changed(&mut self, tick: Tick) {
+        self.ticks.changed = tick;
+    }
+
+    /// Marks the value as changed at `tick`.
+    #[inline(always)]
+    pub fn get_mut(&mut self, tick: Tick) -> &mut T {
+        self.set_changed(tick);
+        &mut self.value
+    }
+
+    #[inline(always)]
+    pub fn set(&mut self, value: T, tick: Tick) {
+        *self.get_mut(tick) = value;
+    }
+
+    /// Mutable access that doesn't mark the value as changed.
+    #[inline(always)]
+    pub fn bypass_change_detection(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    #[inline(always)]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Tracked<T> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+/// Matches entities whose `Tracked<T>` component changed in the current loop iteration.
+pub struct Changed<T>(PhantomData<T>);
+
+impl<T: 'static> WorldQuery for Changed<T> {
+    type Component = ();
+    type Item<'w> = Changed<T>;
+
+    #[inline(always)]
+    unsafe fn get_component<'w, E: EntityComponent>(
+        entity: &'w E,
+        tick: Tick,
+    ) -> Option<Self::Item<'w>> {
+        match entity.get_component::<Tracked<T>>() {
+            Some(x) if x.is_changed(tick) => Some(Changed(PhantomData)),
+            _ => None,
+        }
+    }
+}
+
+/// Matches entities whose `Tracked<T>` component was added in the current loop iteration.
+pub struct Added<T>(PhantomData<T>);
+
+impl<T: 'static> WorldQuery for Added<T> {
+    type Component = ();
+    type Item<'w> = Added<T>;
+
+    #[inline(always)]
+    unsafe fn get_component<'w, E: EntityComponent>(
+        entity: &'w E,
+        tick: Tick,
+    ) -> Option<Self::Item<'w>> {
+        match entity.get_component::<Tracked<T>>() {
+            Some(x) if x.is_added(tick) => Some(Added(PhantomData)),
+            _ => None,
+        }
+    }
+}
+
+/// Reads `Tracked<T>` component of the current entity.
+/// # Example
+/// ```
+/// use inception::*;
+/// #[system]
+/// fn print(position: Ref<i32>) {
+///     if position.is_changed() {
+///         let _value: i32 = *position;
+///     }
+/// }
+/// ```
+pub struct Ref<'w, 's, T, const N: usize> {
+    value: &'w Tracked<T>,
+    tick: Tick,
+    _marker: PhantomSystemParam<'w, 's, N>,
+}
+
+impl<'w, 's, T, const N: usize> Ref<'w, 's, T, N> {
+    #[inline(always)]
+    pub fn is_added(&self) -> bool {
+        self.value.is_added(self.tick)
+    }
+
+    #[inline(always)]
+    pub fn is_changed(&self) -> bool {
+        self.value.is_changed(self.tick)
+    }
+
+    #[inline(always)]
+    pub fn ticks(&self) -> ChangeTicks {
+        self.value.ticks()
+    }
+}
+
+impl<'w, 's, T, const N: usize> Deref for Ref<'w, 's, T, N> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.value.value
+    }
+}
+
+impl<'w, 's, T: 'static, const N: usize> SystemParam for Ref<'w, 's, T, N> {
+    // cast lifetimes
+    type Item<'world, 'state, Wrld: World> = Ref<'world, 'state, T, N>;
+    type State = ();
+
+    unimpl_get_param!();
+
+    impl_no_plugin!();
+
+    #[inline(always)]
+    fn get_param_for_entity<'world, 'state, Wrld, SB, ParamName, E>(
+        entity: &'world mut E,
+        _state: &'state mut Self::State,
+        world: &'world mut Wrld,
+    ) -> Option<Self::Item<'world, 'state, Wrld>>
+    where
+        Wrld: World,
+        SB: SystemParamNameMapper,
+        E: EntityFetch,
+    {
+        Some(Ref {
+            value: entity.get_component::<Tracked<T>>()?,
+            tick: world.change_tick(),
+            _marker: Default::default(),
+        })
+    }
+}
+
+/// Mutably accesses `Tracked<T>` component of the current entity. Component is marked as changed
+/// when it is dereferenced mutably.
+pub struct Mut<'w, 's, T, const N: usize> {
+    value: &'w mut Tracked<T>,
+    tick: Tick,
+    _marker: PhantomSystemParam<'w, 's, N>,
+}
+
+impl<'w, 's, T, const N: usize> Mut<'w, 's, T, N> {
+    #[inline(always)]
+    pub fn is_added(&self) -> bool {
+        self.value.is_added(self.tick)
+    }
+
+    #[inline(always)]
+    pub fn is_changed(&self) -> bool {
+        self.value.is_changed(self.tick)
+    }
+
+    #[inline(always)]
+    pub fn ticks(&self) -> ChangeTicks {
+        self.value.ticks()
+    }
+
+    #[inline(always)]
+    pub fn set_changed(&mut self) {
+        self.value.set_changed(self.tick);
+    }
+
+    #[inline(always)]
+    pub fn bypass_change_detection(&mut self) -> &mut T {
+        self.value.bypass_change_detection()
+    }
+}
+
+impl<'w, 's, T, const N: usize> Deref for Mut<'w, 's, T, N> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.value.value
+    }
+}
+
+impl<'w, 's, T, const N: usize> DerefMut for Mut<'w, 's, T, N> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value.get_mut(self.tick)
+    }
+}
+
+impl<'w, 's, T: 'static, const N: usize> SystemParam for Mut<'w, 's, T, N> {
+    // cast lifetimes
+    type Item<'world, 'state, Wrld: World> = Mut<'world, 'state, T, N>;
+    type State = ();
+
+    unimpl_get_param!();
+
+    impl_no_plugin!();
+
+    #[inline(always)]
+    fn get_param_for_entity<'world, 'state, Wrld, SB, ParamName, E>(
+        entity: &'world mut E,
+        _state: &'state mut Self::State,
+        world: &'world mut Wrld,
+    ) -> Option<Self::Item<'world, 'state, Wrld>>
+    where
+        Wrld: World,
+        SB: SystemParamNameMapper,
+        E: EntityFetch,
+    {
+        Some(Mut {
+            tick: world.change_tick(),
+            value: entity.get_component_mut::<Tracked<T>>()?,
+            _marker: Default::default(),
+        })
+    }
+}
+
+/// Accesses `Tracked<T>` resource. Resource is marked as changed when it is dereferenced mutably.
+pub struct TrackedRes<'w, 's, T, const N: usize> {
+    value: &'w mut Tracked<T>,
+    tick: Tick,
+    _marker: PhantomSystemParam<'w, 's, N>,
+}
+
+impl<'w, 's, T, const N: usize> TrackedRes<'w, 's, T, N> {
+    #[inline(always)]
+    pub fn is_added(&self) -> bool {
+        self.value.is_added(self.tick)
+    }
+
+    #[inline(always)]
+    pub fn is_changed(&self) -> bool {
+        self.value.is_changed(self.tick)
+    }
+
+    #[inline(always)]
+    pub fn ticks(&self) -> ChangeTicks {
+        self.value.ticks()
+    }
+
+    #[inline(always)]
+    pub fn set_changed(&mut self) {
+        self.value.set_changed(self.tick);
+    }
+
+    #[inline(always)]
+    pub fn bypass_change_detection(&mut self) -> &mut T {
+        self.value.bypass_change_detection()
+    }
+}
+
+impl<'w, 's, T, const N: usize> Deref for TrackedRes<'w, 's, T, N> {
+    type Target = T;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.value.value
+    }
+}
+
+impl<'w, 's, T, const N: usize> DerefMut for TrackedRes<'w, 's, T, N> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.value.get_mut(self.tick)
+    }
+}
+
+impl<'w, 's, T: 'static, const N: usize> SystemParam for TrackedRes<'w, 's, T, N> {
+    // cast lifetimes
+    type Item<'world, 'state, Wrld: World> = TrackedRes<'world, 'state, T, N>;
+    type Resources = RequiredResource<Tracked<T>>;
+    type State = ();
+
+    impl_no_plugin!();
+
+    #[inline(always)]
+    fn get_param<'world, 'state, Wrld: World, SB: SystemParamNameMapper, ParamName>(
+        _state: &'state mut Self::State,
+        world: &'world mut Wrld,
+    ) -> Self::Item<'world, 'state, Wrld> {
+        TrackedRes {
+            tick: world.change_tick(),
+            value: world.resource_mut::<Tracked<T>>(),
+            _marker: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::prelude::v1::*;
+
+    use crate::{resources::Break, *};
+
+    schedule! {
+        struct Schedule,
+        Init,
+        loop {
+            Update,
+            Count,
+        },
+    }
+
+    #[derive(Default)]
+    pub struct Changes {
+        added: u32,
+        /// Number of changed entities in each iteration.
+        changed: Vec<u32>,
+        /// Number of times `Ref::is_changed` was true.
+        refs: u32,
+    }
+
+    #[system]
+    fn count_added(mut added: Query<Added<i32>>, mut changes: Res<Changes>) {
+        added.run(|_| changes.added += 1);
+    }
+
+    /// Changes the first entity in odd iterations.
+    #[system]
+    fn bump(mut values: Query<&mut Tracked<i32>>, mut iteration: Res<u32>, mut stop: Res<Break>) {
+        let tick = values.change_tick();
+        **iteration += 1;
+        if **iteration % 2 == 1 {
+            values.run_entity(Entity(0), |x| *x.get_mut(tick) += 1);
+        }
+        **stop = Break(**iteration == 3);
+    }
+
+    #[system]
+    fn count_changed(mut changed: Query<Changed<i32>>, mut changes: Res<Changes>) {
+        let mut n = 0;
+        changed.run(|_| n += 1);
+        changes.changed.push(n);
+    }
+
+    #[system]
+    fn count_refs(value: Ref<i32>, mut changes: Res<Changes>) {
+        if value.is_changed() {
+            changes.refs += 1;
+        }
+    }
+
+    #[test]
+    fn change_detection() {
+        let mut ecs =
+            EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct2::new())
+                .add_plugin(CorePlugin)
+                .add_resource(Changes::default())
+                .add_resource(0u32)
+                .add_component(Tracked::new(0i32), Entity0)
+                .add_component(Tracked::new(0i32), Entity1)
+                .add_system(count_added::new(), Init::new())
+                .add_system(bump::new(), Update::new())
+                .add_system(count_changed::new(), Count::new())
+                .add_system(count_refs::new(), Count::new())
+                .build();
+        ecs.run();
+        let changes = ecs.resource::<Changes>();
+        // Both were added before the loop.
+        assert_eq!(changes.added, 2);
+        assert_eq!(changes.changed, vec![1, 0, 1]);
+        assert_eq!(changes.refs, 2);
+        assert_eq!(**ecs.component::<Tracked<i32>>(Entity(0)), 2);
+    }
+}
@@ -0,0 +1,128 @@
+use core::fmt;
+#[cfg(feature = "alloc")]
+use std::prelude::v1::*;
+
+use crate::Entity;
+
+/// Receives the structure of an ECS, see `Ecs::visit`. Stages are visited in the order they are
+/// declared in a schedule, followed by their systems in the order they run. Then resources and
+/// entities followed by their components are visited. Names are full type names, use `ShortName`
+/// to print them.
+pub trait EcsVisitor {
+    #[inline(always)]
+    fn visit_stage(&mut self, _name: &'static str) {}
+    #[inline(always)]
+    fn visit_system(&mut self, _name: &'static str) {}
+    #[inline(always)]
+    fn visit_resource(&mut self, _name: &'static str) {}
+    #[inline(always)]
+    fn visit_entity(&mut self, _entity: Entity) {}
+    #[inline(always)]
+    fn visit_component(&mut self, _name: &'static str) {}
+}
+
+/// Displays a type name without module paths, `esl::prev::Prev<f32>` is displayed as `Prev<f32>`.
+#[derive(Clone, Copy, Debug)]
+pub struct ShortName<'a>(pub &'a str);
+
+impl<'a> fmt::Display for ShortName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.0.as_bytes();
+        let mut segment_start = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i..].starts_with(b"::") {
+                i += 2;
+                segment_start = i;
+            } else if bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' {
+                i += 1;
+            } else {
+                f.write_str(&self.0[segment_start..=i])?;
+                i += 1;
+                segment_start = i;
+            }
+        }
+        f.write_str(&self.0[segment_start..])
+    }
+}
+
+/// Structure of a built ECS, see `Ecs::describe`.
+#[cfg(feature = "alloc")]
+#[derive(Default, Clone, Debug)]
+pub struct EcsDescription {
+    pub stages: Vec<StageDescription>,
+    pub resources: Vec<&'static str>,
+    pub entities: Vec<EntityDescription>,
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct StageDescription {
+    pub name: &'static str,
+    pub systems: Vec<&'static str>,
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct EntityDescription {
+    pub entity: Entity,
+    pub components: Vec<&'static str>,
+}
+
+#[cfg(feature = "alloc")]
+impl EcsVisitor for EcsDescription {
+    fn visit_stage(&mut self, name: &'static str) {
+        self.stages.push(StageDescription {
+            name,
+            systems: Vec::new(),
+        });
+    }
+
+    fn visit_system(&mut self, name: &'static str) {
+        if let Some(stage) = self.stages.last_mut() {
+            stage.systems.push(name);
+        }
+    }
+
+    fn visit_resource(&mut self, name: &'static str) {
+        self.resources.push(name);
+    }
+
+    fn visit_entity(&mut self, entity: Entity) {
+        self.entities.push(EntityDescription {
+            entity,
+            components: Vec::new(),
+        });
+    }
+
+    fn visit_component(&mut self, name: &'static str) {
+        if let Some(entity) = self.entities.last_mut() {
+            entity.components.push(name);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for EcsDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Stages:")?;
+        for stage in &self.stages {
+            writeln!(f, "  {}", stage.name)?;
+            for system in &stage.systems {
+                writeln!(f, "    {}", ShortName(system))?;
+            }
+        }
+        writeln!(f, "Resources:")?;
+        for resource in &self.resources {
+            writeln!(f, "  {}", ShortName(resource))?;
+        }
+        writeln!(f, "Entities:")?;
+        for entity in &self.entities {
+            writeln!(f, "  {}", entity.entity)?;
+            for component in &entity.components {
+                writeln!(f, "    {}", ShortName(component))?;
+            }
+        }
+        Ok(())
+    }
+}
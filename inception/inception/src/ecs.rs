@@ -2,7 +2,12 @@ use std::prelude::v1::*;
 
 use either::Either;
 
-use crate::{entities::WorldQuery, resources::Break, stage::Stage, world::World, Entity};
+#[cfg(feature = "alloc")]
+use crate::describe::EcsDescription;
+use crate::{
+    describe::EcsVisitor, entities::WorldQuery, resources::Break, stage::Stage, world::World,
+    Entity,
+};
 
 pub trait Ecs {
     fn run(&mut self);
@@ -29,6 +34,42 @@ pub trait Ecs {
     where
         F: FnMut(<Q as WorldQuery>::Item<'w>),
         Q: WorldQuery;
+
+    /// Visits stages with their systems, resources and entities with their components.
+    fn visit<V: EcsVisitor>(&self, visitor: &mut V);
+
+    /// Returns the structure of the ECS without going through its type name.
+    /// # Example
+    /// ```
+    /// use inception::*;
+    /// schedule! {
+    ///     struct Schedule,
+    ///     Update,
+    /// }
+    /// #[system]
+    /// fn count(mut value: Res<u32>) {
+    ///     **value += 1;
+    /// }
+    /// let ecs = EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct1::new())
+    ///     .add_resource(0u32)
+    ///     .add_system(count::new(), Update::new())
+    ///     .build();
+    /// let description = ecs.describe();
+    /// assert_eq!(description.stages[0].name, "Update");
+    /// assert_eq!(
+    ///     ShortName(description.stages[0].systems[0]).to_string(),
+    ///     "count"
+    /// );
+    /// assert!(description.resources.contains(&"u32"));
+    /// println!("{}", description);
+    /// ```
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    fn describe(&self) -> EcsDescription {
+        let mut description = EcsDescription::default();
+        self.visit(&mut description);
+        description
+    }
 }
 
 pub struct EcsStruct<W, S> {
@@ -105,6 +146,12 @@ where
     {
         self.world.query::<F, Q>(f)
     }
+
+    #[inline(always)]
+    fn visit<V: EcsVisitor>(&self, visitor: &mut V) {
+        self.stages.describe(visitor);
+        self.world.describe(visitor);
+    }
 }
 
 impl<L: Ecs, R: Ecs> Ecs for Either<L, R> {
@@ -199,4 +246,12 @@ impl<L: Ecs, R: Ecs> Ecs for Either<L, R> {
             Either::Right(r) => r.query::<F, Q>(f),
         }
     }
+
+    #[inline(always)]
+    fn visit<V: EcsVisitor>(&self, visitor: &mut V) {
+        match self {
+            Either::Left(l) => l.visit(visitor),
+            Either::Right(r) => r.visit(visitor),
+        }
+    }
 }
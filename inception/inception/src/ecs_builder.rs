@@ -2,28 +2,23 @@ use std::prelude::v1::*;
 
 use either::Either;
 
+#[cfg(feature = "alloc")]
+use crate::describe::EcsDescription;
 use crate::{
     ecs::EcsStruct,
     entities::{DefaultEntitiesBuilder1, Entity, EntityRelay, WorldQuery},
     nest_module::{Nest, Nested, StackedNest},
-    plugin::{sealed::IntoEcsBuilder, Plugin, PluginLoader, Plugins, SystemParamPlugin},
-    schedule::ScheduleBuilderTrait,
+    plugin::{
+        sealed::IntoEcsBuilder, Plugin, PluginGroup, PluginList, PluginLoader, Plugins,
+        SystemParamPlugin,
+    },
+    schedule::{ScheduleBuilderTrait, StageCheck},
     stage::{StageBuilder, StageLabel},
     system::SystemBuilder,
     world::{BasicWorld, World, WorldStruct},
     Ecs, Entities, EntitiesBuilder, FromWorld, ParamLabel,
 };
 
-macro_rules! def_stage_items {
-    ($f:ident, $ret:ident) => {
-        type $ret<System: SystemBuilder<'static, 'static> + 'static>: EcsBuilder;
-        #[must_use]
-        fn $f<System>(self, system: System) -> Self::$ret<System>
-        where
-            System: SystemBuilder<'static, 'static> + 'static;
-    };
-}
-
 pub trait EcsBuilder: PluginLoader {
     type AddConfig<Param: ParamLabel, Entity: EntityRelay, T: 'static>: EcsBuilder;
     type AddGenericConfig<System: 'static, Param: 'static, Entity: EntityRelay, T: 'static>: EcsBuilder;
@@ -40,18 +35,11 @@ pub trait EcsBuilder: PluginLoader {
     type ExtendEntities<Component: Clone + 'static>: EcsBuilder;
     type AddPluginOnce<Plug: Plugin + 'static>: EcsBuilder;
     type AddPlugin<Plug: Plugin + 'static>: EcsBuilder;
+    type AddPlugins<G: PluginGroup>: EcsBuilder;
     type SetPluginLoaded<Plugin: 'static>: EcsBuilder;
     type ScheduleBuilder: StageBuilder;
     type Build: Ecs;
 
-    all_tuples::repeat!(
-        def_stage_items,
-        0,
-        32,
-        add_system_to_stage,
-        AddSystemToStage
-    );
-
     #[must_use]
     fn add_config<Param: ParamLabel, Entity: EntityRelay, T: 'static>(
         self,
@@ -117,6 +105,9 @@ pub trait EcsBuilder: PluginLoader {
     fn add_plugin_once<Plug: Plugin + 'static>(self, plugin: Plug) -> Self::AddPluginOnce<Plug>;
     #[must_use]
     fn add_plugin<Plug: Plugin + 'static>(self, plugin: Plug) -> Self::AddPlugin<Plug>;
+    /// Adds plugins of a group in the order they were added to it.
+    #[must_use]
+    fn add_plugins<G: PluginGroup>(self, group: G) -> Self::AddPlugins<G>;
     #[must_use]
     fn contains_plugin<Plugin: 'static>(&self) -> bool;
     #[must_use]
@@ -125,6 +116,15 @@ pub trait EcsBuilder: PluginLoader {
     // Taking Sched by value even, though we don't need it. This is a workaround of an ICE.
     // https://github.com/rust-lang/rust/issues/99945
     fn build(self) -> Self::Build;
+    /// Builds the ECS and returns its structure, see `Ecs::describe`.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    fn describe(self) -> EcsDescription
+    where
+        Self: Sized,
+    {
+        self.build().describe()
+    }
 
     #[must_use]
     fn get_resource<T: 'static>(&self) -> Option<&T>;
@@ -192,33 +192,6 @@ pub struct EcsBuilderStruct<P, SchedBuilder, W, E, Stages, const N_EVENTS: usize
     stages: Stages,
 }
 
-macro_rules! impl_stage_items_for_builder {
-    ($f:ident, $ret:ident) => {
-        type $ret<System: SystemBuilder<'static, 'static> + 'static> = EcsBuilderStruct<
-            P,
-            <SchedBuilder as ScheduleBuilderTrait>::$ret<System>,
-            W,
-            E,
-            S,
-            N_EVENTS,
-        >;
-        #[inline(always)]
-        fn $f<System>(self, system: System) -> Self::$ret<System>
-        where
-            System: SystemBuilder<'static, 'static> + 'static,
-        {
-            EcsBuilderStruct {
-                added_plugins: self.added_plugins,
-                world: self.world,
-                entities: self.entities,
-                entity_count: self.entity_count,
-                stages: self.stages,
-                schedule_builder: self.schedule_builder.$f(system),
-            }
-        }
-    };
-}
-
 impl<P, SchedBuilder, W, E, S, const N_EVENTS: usize> EcsBuilder
     for EcsBuilderStruct<P, SchedBuilder, W, E, S, N_EVENTS>
 where
@@ -246,19 +219,25 @@ where
     > as EcsBuilder>::SetPluginLoaded<Plug> as IntoEcsBuilder>::Builder;
     type AddPluginOnce<Plug: Plugin + 'static> =
         <<Self as PluginLoader>::Load<Plug> as IntoEcsBuilder>::Builder;
+    type AddPlugins<G: PluginGroup> = <G::Plugins as PluginList>::Build<Self>;
     type AddResource<T: 'static> =
         EcsBuilderStruct<P, SchedBuilder, W::AddResource<T>, E, S, N_EVENTS>;
     type AddSystemToStage<System: SystemBuilder<'static, 'static> + 'static, Stage: StageLabel> =
         <<System as SystemBuilder<'static, 'static>>::System<
             WorldStruct<StackedNest, DefaultEntitiesBuilder1>,
             N_EVENTS,
-        > as SystemParamPlugin>::Build<
-            Stage::AddSystem<System, EcsBuilderStruct<P, SchedBuilder, W, E, S, N_EVENTS>>,
-        >;
+        > as SystemParamPlugin>::Build<Self::AddSystemToStageWithoutPlugin<System, Stage>>;
     type AddSystemToStageWithoutPlugin<
         System: SystemBuilder<'static, 'static> + 'static,
         Stage: StageLabel,
-    > = Stage::AddSystem<System, EcsBuilderStruct<P, SchedBuilder, W, E, S, N_EVENTS>>;
+    > = EcsBuilderStruct<
+        P,
+        SchedBuilder::AddSystemToStage<Stage::Index, System>,
+        W,
+        E,
+        S,
+        N_EVENTS,
+    >;
     type Build = EcsStruct<W, <SchedBuilder as StageBuilder>::BuildStage<W, N_EVENTS>>;
     type ExtendConfig<Param: ParamLabel, T: Clone + 'static> =
         EcsBuilderStruct<P, SchedBuilder, W::ExtendConfig<Param, T>, E, S, N_EVENTS>;
@@ -276,14 +255,6 @@ where
         EcsBuilderStruct<P::Push<Plugin>, SchedBuilder, W, E, S, N_EVENTS>;
     type Spawn = EcsBuilderStruct<P, SchedBuilder, W, Nested<E, Entity>, S, N_EVENTS>;
 
-    all_tuples::repeat!(
-        impl_stage_items_for_builder,
-        0,
-        32,
-        add_system_to_stage,
-        AddSystemToStage
-    );
-
     #[inline(always)]
     fn add_config<Param: ParamLabel, Entity: EntityRelay, T: 'static>(
         self,
@@ -391,7 +362,18 @@ where
         System: SystemBuilder<'static, 'static> + 'static,
         Stage: StageLabel,
     {
-        Stage::add_system(system, self)
+        #[allow(clippy::let_unit_value)]
+        let () = StageCheck::<SchedBuilder, Stage>::CHECK;
+        EcsBuilderStruct {
+            added_plugins: self.added_plugins,
+            world: self.world,
+            entities: self.entities,
+            entity_count: self.entity_count,
+            stages: self.stages,
+            schedule_builder: self
+                .schedule_builder
+                .add_system_to_stage::<Stage::Index, System>(system),
+        }
     }
 
     #[inline(always)]
@@ -475,6 +457,11 @@ where
         PluginLoader::load(self, plugin).__into_builder()
     }
 
+    #[inline(always)]
+    fn add_plugins<G: PluginGroup>(self, group: G) -> Self::AddPlugins<G> {
+        group.build().add_to(self)
+    }
+
     #[inline(always)]
     fn contains_plugin<Plugin: 'static>(&self) -> bool {
         self.added_plugins.contains_plugin::<Plugin>()
@@ -569,7 +556,14 @@ where
     #[inline(always)]
     fn load_once<Plug: Plugin + 'static>(self, plugin: Plug) -> Self::Load<Plug> {
         if self.added_plugins.contains_plugin::<Plug>() {
-            Either::Left(self)
+            Either::Left(EcsBuilderStruct::<_, _, _, _, _, N_EVENTS> {
+                added_plugins: self.added_plugins,
+                schedule_builder: self.schedule_builder,
+                world: self.world.__loaded_once::<Plug, P>(),
+                entities: self.entities,
+                entity_count: self.entity_count,
+                stages: self.stages,
+            })
         } else {
             Either::Right(self.load(plugin))
         }
@@ -611,27 +605,12 @@ impl<O: PluginLoader + EcsBuilder, N: PluginLoader + EcsBuilder> PluginLoader fo
     }
 }
 
-macro_rules! impl_stage_items_for_either {
-    ($f:ident, $f_plugin:ident, $ret:ident, $ret_plugin:ident) => {
-        type $ret<System: SystemBuilder<'static, 'static> + 'static> = impl EcsBuilder;
-        #[inline(always)]
-        fn $f<System>(self, system: System) -> Self::$ret<System>
-        where
-            System: SystemBuilder<'static, 'static> + 'static,
-        {
-            match self {
-                Either::Left(original) => Either::Left(original.$f(system)),
-                Either::Right(new) => Either::Right(new.$f(system)),
-            }
-        }
-    };
-}
-
 impl<L: EcsBuilder, R: EcsBuilder> EcsBuilder for Either<L, R> {
     type AddPlugin<Plug: Plugin + 'static> =
         Either<<L as EcsBuilder>::AddPlugin<Plug>, <R as EcsBuilder>::AddPlugin<Plug>>;
     type AddPluginOnce<Plug: Plugin + 'static> =
         Either<L::AddPluginOnce<Plug>, R::AddPluginOnce<Plug>>;
+    type AddPlugins<G: PluginGroup> = Either<L::AddPlugins<G>, R::AddPlugins<G>>;
     type AddSystemToStage<System: SystemBuilder<'static, 'static> + 'static, Stage: StageLabel> =
         Either<L::AddSystemToStage<System, Stage>, R::AddSystemToStage<System, Stage>>;
     type AddSystemToStageWithoutPlugin<
@@ -661,16 +640,6 @@ impl<L: EcsBuilder, R: EcsBuilder> EcsBuilder for Either<L, R> {
     type SetPluginLoaded<P: 'static> = impl EcsBuilder;
     type Spawn = impl EcsBuilder;
 
-    all_tuples::repeat!(
-        impl_stage_items_for_either,
-        0,
-        32,
-        add_system_to_stage,
-        add_system_without_plugin_to_stage,
-        AddSystemToStage,
-        AddSystemWithoutPluginToStage
-    );
-
     #[inline(always)]
     fn add_config<Param: ParamLabel, Entity: EntityRelay, T: 'static>(
         self,
@@ -809,6 +778,14 @@ impl<L: EcsBuilder, R: EcsBuilder> EcsBuilder for Either<L, R> {
         }
     }
 
+    #[inline(always)]
+    fn add_plugins<G: PluginGroup>(self, group: G) -> Self::AddPlugins<G> {
+        match self {
+            Either::Left(x) => Either::Left(x.add_plugins(group)),
+            Either::Right(x) => Either::Right(x.add_plugins(group)),
+        }
+    }
+
     #[inline(always)]
     fn contains_plugin<Plugin: 'static>(&self) -> bool {
         match self {
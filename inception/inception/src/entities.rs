@@ -1,7 +1,11 @@
+use core::marker::PhantomData;
+
 use all_tuples::{all_tuples, repeat};
 
 use crate::{
+    change_detection::Tick,
     config::ConfigValue,
+    describe::EcsVisitor,
     nest_module::{Nested, StackedNest},
 };
 
@@ -104,6 +108,8 @@ pub trait EntityFetch {
 pub trait EntityComponent {
     fn get_component<T: 'static>(&self) -> Option<&T>;
     fn get_component_mut<T: 'static>(&mut self) -> Option<&mut T>;
+    /// Calls `f` with a type name of each component in the order they were added.
+    fn for_each_component_name<F: FnMut(&'static str)>(&self, f: &mut F);
 }
 
 impl<N, F> EntityComponent for Nested<N, F>
@@ -128,6 +134,12 @@ where
             self.inner.get_component_mut::<T>()
         }
     }
+
+    #[inline(always)]
+    fn for_each_component_name<Func: FnMut(&'static str)>(&self, f: &mut Func) {
+        self.inner.for_each_component_name(f);
+        f(core::any::type_name::<F>());
+    }
 }
 
 impl EntityComponent for StackedNest {
@@ -140,39 +152,51 @@ impl EntityComponent for StackedNest {
     fn get_component_mut<T: 'static>(&mut self) -> Option<&mut T> {
         None
     }
+
+    #[inline(always)]
+    fn for_each_component_name<F: FnMut(&'static str)>(&self, _f: &mut F) {
+        // Empty
+    }
 }
 
 #[derive(Debug)]
 pub struct EntityData<C> {
-    entity: Entity,
-    components: C,
+    pub(crate) entity: Entity,
+    pub(crate) components: C,
 }
 
 impl<C: EntityComponent> EntityData<C> {
     #[inline(always)]
-    fn query<'w, F, Q>(&'w mut self, f: &mut F)
+    pub(crate) fn query<'w, F, Q>(&'w mut self, tick: Tick, f: &mut F)
     where
         F: FnMut(<Q as WorldQuery>::Item<'w>),
         Q: WorldQuery,
     {
         unsafe {
-            if let Some(x) = Q::get_component(&self.components) {
+            if let Some(x) = Q::get_component(&self.components, tick) {
                 f(x);
             }
         }
     }
 
     #[inline(always)]
-    fn query_entity<'w, F, Q>(&'w mut self, entity: Entity, f: &mut F)
+    fn query_entity<'w, F, Q>(&'w mut self, entity: Entity, tick: Tick, f: &mut F)
     where
         F: FnMut(<Q as WorldQuery>::Item<'w>),
         Q: WorldQuery,
     {
         if entity == self.entity {
-            self.query::<F, Q>(f);
+            self.query::<F, Q>(tick, f);
         }
     }
 
+    #[inline(always)]
+    pub(crate) fn describe<V: EcsVisitor>(&self, visitor: &mut V) {
+        visitor.visit_entity(self.entity);
+        self.components
+            .for_each_component_name(&mut |x| visitor.visit_component(x));
+    }
+
     pub fn add<T: 'static>(self, component: T) -> EntityData<Nested<C, T>> {
         EntityData {
             entity: self.entity,
@@ -246,7 +270,10 @@ pub trait WorldQuery {
     type Component: 'static;
     type Item<'w>: WorldQuery<Component = Self::Component>;
 
-    unsafe fn get_component<'w, E: EntityComponent>(entity: &'w E) -> Option<Self::Item<'w>>;
+    unsafe fn get_component<'w, E: EntityComponent>(
+        entity: &'w E,
+        tick: Tick,
+    ) -> Option<Self::Item<'w>>;
 }
 
 impl<T: 'static> WorldQuery for &T {
@@ -254,7 +281,10 @@ impl<T: 'static> WorldQuery for &T {
     type Item<'w> = &'w T;
 
     #[inline(always)]
-    unsafe fn get_component<'w, E: EntityComponent>(entity: &'w E) -> Option<Self::Item<'w>> {
+    unsafe fn get_component<'w, E: EntityComponent>(
+        entity: &'w E,
+        _tick: Tick,
+    ) -> Option<Self::Item<'w>> {
         entity.get_component::<T>()
     }
 }
@@ -264,7 +294,10 @@ impl<T: 'static> WorldQuery for &mut T {
     type Item<'w> = &'w mut T;
 
     #[inline(always)]
-    unsafe fn get_component<'w, E: EntityComponent>(entity: &'w E) -> Option<Self::Item<'w>> {
+    unsafe fn get_component<'w, E: EntityComponent>(
+        entity: &'w E,
+        _tick: Tick,
+    ) -> Option<Self::Item<'w>> {
         // #[allow(clippy::cast_ref_to_mut)]
         // let entity = &mut *(entity as *const E as *mut E);
         #[allow(mutable_transmutes)]
@@ -273,6 +306,55 @@ impl<T: 'static> WorldQuery for &mut T {
     }
 }
 
+/// Matches entities that have component `T` without fetching it.
+pub struct With<T>(PhantomData<T>);
+
+impl<T: 'static> WorldQuery for With<T> {
+    type Component = ();
+    type Item<'w> = With<T>;
+
+    #[inline(always)]
+    unsafe fn get_component<'w, E: EntityComponent>(
+        entity: &'w E,
+        _tick: Tick,
+    ) -> Option<Self::Item<'w>> {
+        entity.get_component::<T>().map(|_| With(PhantomData))
+    }
+}
+
+/// Matches entities that don't have component `T`.
+pub struct Without<T>(PhantomData<T>);
+
+impl<T: 'static> WorldQuery for Without<T> {
+    type Component = ();
+    type Item<'w> = Without<T>;
+
+    #[inline(always)]
+    unsafe fn get_component<'w, E: EntityComponent>(
+        entity: &'w E,
+        _tick: Tick,
+    ) -> Option<Self::Item<'w>> {
+        match entity.get_component::<T>() {
+            Some(_) => None,
+            None => Some(Without(PhantomData)),
+        }
+    }
+}
+
+/// Matches all entities, `None` if the entity doesn't match `Q`.
+impl<Q: WorldQuery> WorldQuery for Option<Q> {
+    type Component = Q::Component;
+    type Item<'w> = Option<Q::Item<'w>>;
+
+    #[inline(always)]
+    unsafe fn get_component<'w, E: EntityComponent>(
+        entity: &'w E,
+        tick: Tick,
+    ) -> Option<Self::Item<'w>> {
+        Some(Q::get_component(entity, tick))
+    }
+}
+
 macro_rules! impl_component_ref {
     ($($param: ident),*) => {
         impl<$($param),*> WorldQuery for ($($param,)*)
@@ -284,8 +366,11 @@ macro_rules! impl_component_ref {
 
             #[allow(unused_variables)]
             #[inline(always)]
-            unsafe fn get_component<'w, E: EntityComponent>(entity: &'w E) -> Option<Self::Item<'w>> {
-                Some(($($param::get_component(entity)?,)*))
+            unsafe fn get_component<'w, E: EntityComponent>(
+                entity: &'w E,
+                tick: Tick,
+            ) -> Option<Self::Item<'w>> {
+                Some(($($param::get_component(entity, tick)?,)*))
             }
         }
     };
@@ -293,22 +378,28 @@ macro_rules! impl_component_ref {
 
 all_tuples!(impl_component_ref, 0, 16, P);
 
+/// Queries take a `tick` that is used by `Changed` and `Added` filters.
 pub trait Entities {
-    fn query<'w, F, Q>(&'w mut self, f: F)
+    fn query<'w, F, Q>(&'w mut self, tick: Tick, f: F)
     where
         F: FnMut(<Q as WorldQuery>::Item<'w>),
         Q: WorldQuery;
-    fn query_entity<'w, F, Q>(&'w mut self, entity: Entity, f: F)
+    fn query_entity<'w, F, Q>(&'w mut self, entity: Entity, tick: Tick, f: F)
     where
         F: FnMut(<Q as WorldQuery>::Item<'w>),
         Q: WorldQuery;
     fn for_each<F>(&mut self, f: F)
     where
         F: EntityFnMut;
-    fn get_components<'w, Q: WorldQuery>(&'w mut self, entity: Entity) -> Option<Q::Item<'w>>;
-    fn components<'w, Q: WorldQuery>(&'w mut self, entity: Entity) -> Q::Item<'w>;
+    fn get_components<'w, Q: WorldQuery>(
+        &'w mut self,
+        entity: Entity,
+        tick: Tick,
+    ) -> Option<Q::Item<'w>>;
+    fn components<'w, Q: WorldQuery>(&'w mut self, entity: Entity, tick: Tick) -> Q::Item<'w>;
     fn get_component<T: 'static>(&self, entity: Entity) -> Option<&T>;
     fn get_component_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T>;
+    fn describe<V: EcsVisitor>(&self, visitor: &mut V);
 }
 
 impl<N, C> Entities for Nested<N, EntityData<C>>
@@ -317,29 +408,29 @@ where
     C: EntityComponent,
 {
     #[inline(always)]
-    fn query<'w, F, Q>(&'w mut self, mut f: F)
+    fn query<'w, F, Q>(&'w mut self, tick: Tick, mut f: F)
     where
         F: FnMut(<Q as WorldQuery>::Item<'w>),
         Q: WorldQuery,
     {
         unsafe {
-            if let Some(x) = Q::get_component(&self.item.components) {
+            if let Some(x) = Q::get_component(&self.item.components, tick) {
                 f(x);
             }
-            self.inner.query::<F, Q>(f)
+            self.inner.query::<F, Q>(tick, f)
         }
     }
 
     #[inline(always)]
-    fn query_entity<'w, F, Q>(&'w mut self, entity: Entity, f: F)
+    fn query_entity<'w, F, Q>(&'w mut self, entity: Entity, tick: Tick, mut f: F)
     where
         F: FnMut(<Q as WorldQuery>::Item<'w>),
         Q: WorldQuery,
     {
         if entity == self.item.entity {
-            self.query::<F, Q>(f);
+            self.item.query::<F, Q>(tick, &mut f);
         } else {
-            self.query_entity::<F, Q>(entity, f);
+            self.inner.query_entity::<F, Q>(entity, tick, f);
         }
     }
 
@@ -353,19 +444,23 @@ where
     }
 
     #[inline(always)]
-    fn get_components<'w, Q: WorldQuery>(&'w mut self, entity: Entity) -> Option<Q::Item<'w>> {
+    fn get_components<'w, Q: WorldQuery>(
+        &'w mut self,
+        entity: Entity,
+        tick: Tick,
+    ) -> Option<Q::Item<'w>> {
         unsafe {
             if entity == self.item.entity {
-                Q::get_component(&self.item.components)
+                Q::get_component(&self.item.components, tick)
             } else {
-                self.inner.get_components::<Q>(entity)
+                self.inner.get_components::<Q>(entity, tick)
             }
         }
     }
 
     #[inline(always)]
-    fn components<'w, Q: WorldQuery>(&'w mut self, entity: Entity) -> Q::Item<'w> {
-        expect_components::<Q, _>(entity, self.get_components::<Q>(entity))
+    fn components<'w, Q: WorldQuery>(&'w mut self, entity: Entity, tick: Tick) -> Q::Item<'w> {
+        expect_components::<Q, _>(entity, self.get_components::<Q>(entity, tick))
     }
 
     #[inline(always)]
@@ -385,11 +480,17 @@ where
             self.inner.get_component_mut(entity)
         }
     }
+
+    #[inline(always)]
+    fn describe<V: EcsVisitor>(&self, visitor: &mut V) {
+        self.inner.describe(visitor);
+        self.item.describe(visitor);
+    }
 }
 
 impl Entities for StackedNest {
     #[inline(always)]
-    fn query<'w, F, Q>(&'w mut self, _f: F)
+    fn query<'w, F, Q>(&'w mut self, _tick: Tick, _f: F)
     where
         F: FnMut(<Q as WorldQuery>::Item<'w>),
         Q: WorldQuery,
@@ -397,7 +498,7 @@ impl Entities for StackedNest {
     }
 
     #[inline(always)]
-    fn query_entity<'w, F, Q>(&'w mut self, _entity: Entity, _f: F)
+    fn query_entity<'w, F, Q>(&'w mut self, _entity: Entity, _tick: Tick, _f: F)
     where
         F: FnMut(<Q as WorldQuery>::Item<'w>),
         Q: WorldQuery,
@@ -405,12 +506,16 @@ impl Entities for StackedNest {
     }
 
     #[inline(always)]
-    fn get_components<'w, Q: WorldQuery>(&'w mut self, _entity: Entity) -> Option<Q::Item<'w>> {
+    fn get_components<'w, Q: WorldQuery>(
+        &'w mut self,
+        _entity: Entity,
+        _tick: Tick,
+    ) -> Option<Q::Item<'w>> {
         None
     }
 
     #[inline(always)]
-    fn components<'w, Q: WorldQuery>(&'w mut self, entity: Entity) -> Q::Item<'w> {
+    fn components<'w, Q: WorldQuery>(&'w mut self, entity: Entity, _tick: Tick) -> Q::Item<'w> {
         expect_components::<Q, _>(entity, None)
     }
 
@@ -431,10 +536,15 @@ impl Entities for StackedNest {
     {
         // Empty
     }
+
+    #[inline(always)]
+    fn describe<V: EcsVisitor>(&self, _visitor: &mut V) {
+        // Empty
+    }
 }
 
 #[inline(always)]
-fn expect_components<Q, T>(entity: Entity, components: Option<T>) -> T {
+pub(crate) fn expect_components<Q, T>(entity: Entity, components: Option<T>) -> T {
     match components {
         Some(x) => x,
         None => panic!(
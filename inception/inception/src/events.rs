@@ -1,46 +1,244 @@
-use tinyvec::ArrayVec;
+use core::marker::PhantomData;
+
+use tinyvec::{Array, ArrayVec};
+
+use crate::{change_detection::Tick, entities::EntityFetch, input::Input, *};
+
+/// What happens when an event is sent while the buffer of the current tick is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventOverflow {
+    /// Removes the oldest event of the current tick to make room for the new one.
+    DropOldest,
+    /// Discards the new event.
+    DropNewest,
+    /// Panics. There is no option that fails to compile, the number of sent events is only known
+    /// at runtime.
+    Panic,
+}
+
+/// Type that can be sent with `Events` and `EntityEvents`.
+/// # Example
+/// ```
+/// use inception::*;
+/// #[derive(Clone)]
+/// struct Tick(u32);
+/// impl Event for Tick {
+///     type Capacity = Capacity<64>;
+///
+///     const OVERFLOW: EventOverflow = EventOverflow::DropOldest;
+/// }
+/// ```
+pub trait Event: 'static {
+    /// Number of events that can be sent during a tick. Defaults to the capacity that is set with
+    /// `EcsBuilder::set_max_events`.
+    type Capacity: EventCapacity = DefaultCapacity;
+
+    const OVERFLOW: EventOverflow = EventOverflow::Panic;
+}
 
-use crate::{entities::EntityFetch, *};
+/// Size of an event buffer, see `Event::Capacity`.
+pub trait EventCapacity: 'static {
+    /// Storage of a buffer, `N` is the capacity that is set with `EcsBuilder::set_max_events`.
+    type Buffer<T, const N: usize>: Array<Item = Option<T>>;
+}
+
+/// Uses the capacity that is set with `EcsBuilder::set_max_events`.
+pub struct DefaultCapacity;
+
+impl EventCapacity for DefaultCapacity {
+    type Buffer<T, const N: usize> = [Option<T>; N];
+}
+
+/// Fixed capacity of an event buffer.
+pub struct Capacity<const C: usize>;
+
+impl<const C: usize> EventCapacity for Capacity<C> {
+    type Buffer<T, const N: usize> = [Option<T>; C];
+}
+
+type EventBuffer<T, const N: usize> =
+    ArrayVec<<<T as Event>::Capacity as EventCapacity>::Buffer<EventInstance<T>, N>>;
 
 #[derive(Clone)]
-pub struct EventsContainer<T, const N: usize> {
-    events: ArrayVec<[Option<T>; N]>,
+struct EventInstance<T> {
+    id: usize,
+    /// `EntityScope` that the event was sent in.
+    scope: Option<usize>,
+    event: T,
+}
+
+/// Double buffer of events. Buffers are swapped when they are accessed during a new tick, so that
+/// systems that use the same events don't need to agree on who swaps them. Events are dropped on
+/// the second swap after the one they were sent in, even if ticks were skipped between them.
+pub struct EventsContainer<T: Event, const N: usize> {
+    /// Events that were sent during the previous tick.
+    previous: EventBuffer<T, N>,
+    /// Events that were sent during the current tick.
+    current: EventBuffer<T, N>,
+    /// Tick during which events in `current` were sent.
+    tick: Tick,
+    /// Id of the next event that is sent with `Events`. Ids of `EntityEvents` are shared between
+    /// entities, they are stored in `EntityEventsCount`.
+    event_count: usize,
 }
 
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct EventsTag;
 
-impl<T, const N: usize> Default for EventsContainer<T, N> {
+impl<T: Event, const N: usize> Default for EventsContainer<T, N> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            previous: ArrayVec::new(),
+            current: ArrayVec::new(),
+            tick: Tick::default(),
+            event_count: 0,
+        }
+    }
+}
+
+// Buffers are not `Clone` for every capacity.
+impl<T: Event + Clone, const N: usize> Clone for EventsContainer<T, N> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        Self {
+            previous: self.previous.iter().cloned().collect(),
+            current: self.current.iter().cloned().collect(),
+            tick: self.tick,
+            event_count: self.event_count,
+        }
+    }
+}
+
+impl<T: Event, const N: usize> EventsContainer<T, N> {
+    #[inline(always)]
+    fn send(&mut self, id: usize, scope: Option<usize>, event: T) {
+        if self.current.len() == self.current.capacity() {
+            match T::OVERFLOW {
+                EventOverflow::DropOldest => {
+                    if self.current.is_empty() {
+                        return;
+                    }
+                    self.current.remove(0);
+                },
+                EventOverflow::DropNewest => return,
+                EventOverflow::Panic => panic!(
+                    "More than {} `{}` events were sent during a tick.",
+                    self.current.capacity(),
+                    core::any::type_name::<T>()
+                ),
+            }
+        }
+        self.current.push(Some(EventInstance { id, scope, event }));
+    }
+
+    /// Events with ids in `start..end`. When `scope` is set, only events that were sent in it.
+    #[inline(always)]
+    fn read(&self, start: usize, end: usize, scope: Option<usize>) -> impl Iterator<Item = &T> {
+        self.previous
+            .iter()
+            .chain(self.current.iter())
+            .filter_map(move |x| match x {
+                Some(x) if x.id >= start && x.id < end && (scope.is_none() || x.scope == scope) => {
+                    Some(&x.event)
+                },
+                _ => None,
+            })
+    }
+
+    /// Swaps buffers when `tick` differs from the tick of the current buffer, which drops events
+    /// of the previous buffer.
+    #[inline(always)]
+    fn update(&mut self, tick: Tick) {
+        if tick == self.tick {
+            return;
+        }
+        core::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+        self.tick = tick;
+    }
+}
+
+/// Ids of events that a system reads during a single run. Each system has its own reader, so it
+/// reads every event once, no matter how many systems read the same events.
+#[derive(Default)]
+pub struct EventReader {
+    tick: Option<Tick>,
+    start: usize,
+    end: usize,
+}
+
+impl EventReader {
+    /// Moves the reader past events that were read during the previous run of a system. Systems
+    /// run at most once per tick, calls during the same tick don't move it.
+    #[inline(always)]
+    fn update(&mut self, tick: Tick, event_count: usize) {
+        if self.tick != Some(tick) {
+            self.tick = Some(tick);
+            self.start = self.end;
+            self.end = event_count;
+        }
+    }
+}
+
+impl SystemParamState for EventReader {
+    #[inline(always)]
+    fn init<W: World, SB: SystemParamNameMapper, ParamName: 'static, I: Input>(
+        _inputs: &mut I,
+        _world: &mut W,
+    ) -> Self {
+        Self::default()
+    }
+}
+
+/// Number of `EntityEvents<T>` that were sent to all entities.
+pub struct EntityEventsCount<T> {
+    count: usize,
+    _t: PhantomData<T>,
+}
+
+impl<T> Default for EntityEventsCount<T> {
     #[inline(always)]
     fn default() -> Self {
         Self {
-            events: ArrayVec::new(),
+            count: 0,
+            _t: PhantomData,
         }
     }
 }
 
-/// Stores events in a resource. It can only contain `N` events and they are cleared on
-/// `inception::stages::Last`. Events are shared between systems and cannot be consumed.
-pub struct Events<'w, 's, T, const N: usize> {
+/// Stores events in a resource. It can only contain `N` events per tick, see `Event::OVERFLOW`.
+/// Events are kept until the end of the next loop iteration. Each system reads events that were
+/// sent since its previous run, including the ones that were sent in earlier stages of the current
+/// iteration.
+pub struct Events<'w, 's, T: Event, const N: usize> {
     res: &'w mut EventsContainer<T, N>,
+    start: usize,
+    end: usize,
     _marker: PhantomSystemParam<'w, 's, N>,
 }
 
-impl<'w, 's, T: 'static, const N: usize> SystemParam for Events<'w, 's, T, N> {
+impl<'w, 's, T: Event, const N: usize> SystemParam for Events<'w, 's, T, N> {
     // cast lifetimes
     type Item<'world, 'state, Wrld: World> = Events<'world, 'state, T, N>;
-    type State = ();
+    type State = EventReader;
 
     type Build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static> =
         impl EcsBuilder;
 
     #[inline(always)]
     fn get_param<'world, 'state, Wrld: World, SB: SystemParamNameMapper, ParamName>(
-        _state: &'state mut Self::State,
+        state: &'state mut Self::State,
         world: &'world mut Wrld,
     ) -> Self::Item<'world, 'state, Wrld> {
+        let tick = world.change_tick();
+        let res = world.resource_mut::<EventsContainer<T, N>>();
+        res.update(tick);
+        state.update(tick, res.event_count);
         Events {
-            res: world.resource_mut::<EventsContainer<T, N>>(),
+            res,
+            start: state.start,
+            end: state.end,
             _marker: Default::default(),
         }
     }
@@ -49,68 +247,75 @@ impl<'w, 's, T: 'static, const N: usize> SystemParam for Events<'w, 's, T, N> {
     fn build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static>(
         builder: B,
     ) -> Self::Build<B, SB, ParamName> {
-        builder
-            .add_system_without_plugin(events::new::<T>(), crate::stages::Last::new())
-            .init_resource::<EventsContainer<T, N>>()
+        builder.init_resource::<EventsContainer<T, N>>()
     }
 }
 
-#[system]
-fn events<T: 'static>(mut channel: Res<EventsContainer<T, N>>) {
-    channel.events.clear();
-}
-
-impl<'w, 's, T, const N: usize> Events<'w, 's, T, N> {
+impl<'w, 's, T: Event, const N: usize> Events<'w, 's, T, N> {
+    /// Events that were sent since the previous run of a system.
     #[inline(always)]
     pub fn iter(&self) -> impl Iterator<Item = &T> {
-        self.res.events.iter().filter_map(|x| x.as_ref())
+        self.res.read(self.start, self.end, None)
     }
 
     #[inline(always)]
     pub fn send(&mut self, event: T) {
-        self.res.events.push(Some(event));
+        let id = self.res.event_count;
+        self.res.event_count += 1;
+        self.res.send(id, None, event);
     }
 
     #[inline(always)]
     pub fn is_empty(&self) -> bool {
-        self.res.events.is_empty()
+        self.iter().next().is_none()
     }
 
     #[inline(always)]
     pub fn len(&self) -> usize {
-        self.res.events.len()
-    }
-
-    #[inline(always)]
-    pub fn clear(&mut self) {
-        self.res.events.clear();
+        self.iter().count()
     }
 }
 
-impl<'w, 's, T: 'static, const N: usize> IntoIterator for Events<'w, 's, T, N> {
+impl<'w, 's, T: Event, const N: usize> IntoIterator for Events<'w, 's, T, N> {
     type Item = &'w T;
 
     type IntoIter = impl Iterator<Item = &'w T>;
 
     #[inline(always)]
     fn into_iter(self) -> Self::IntoIter {
-        self.res.events.iter().filter_map(|x| x.as_ref())
+        self.res.read(self.start, self.end, None)
     }
 }
 
+/// Splits an entity into `len` scopes, e.g. instruments of a portfolio. `EntityEvents` that are
+/// sent in a scope are read by the entity as usual, but reading them in a scope only returns the
+/// ones that were sent in it. Other params can use it to keep their data for each scope.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EntityScope {
+    pub len: usize,
+    /// Scope that params access, `None` is the whole entity.
+    pub current: Option<usize>,
+}
+
 /// Each entity that has an `EventTag` component will be extended with an `EventsContainer`. This
 /// allows to store multiple events per entity.
-/// It can only contain `N` events and they are cleared on `inception::stages::Last`.
-/// Events are shared between systems and cannot be consumed.
-pub struct EntityEvents<'w, 's, T, const N: usize> {
+/// It can only contain `N` events per tick, see `Event::OVERFLOW`. Events are kept until the end of
+/// the next loop iteration. Each system reads events that were sent since its previous run,
+/// including the ones that were sent in earlier stages of the current iteration.
+pub struct EntityEvents<'w, 's, T: Event, const N: usize> {
     container: &'w mut EventsContainer<T, N>,
+    count: &'w mut EntityEventsCount<T>,
+    start: usize,
+    end: usize,
+    /// `EntityScope::current` of the entity when the param was fetched.
+    scope: Option<usize>,
     _marker: PhantomSystemParam<'w, 's, N>,
 }
 
-impl<'w, 's, T: Clone + 'static, const N: usize> SystemParam for EntityEvents<'w, 's, T, N> {
+impl<'w, 's, T: Event + Clone, const N: usize> SystemParam for EntityEvents<'w, 's, T, N> {
     // cast lifetimes
     type Item<'world, 'state, Wrld: World> = EntityEvents<'world, 'state, T, N>;
-    type State = ();
+    type State = EventReader;
 
     type Build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static> =
         impl EcsBuilder;
@@ -120,8 +325,8 @@ impl<'w, 's, T: Clone + 'static, const N: usize> SystemParam for EntityEvents<'w
     #[inline(always)]
     fn get_param_for_entity<'world, 'state, Wrld, SB, ParamName, E>(
         entity: &'world mut E,
-        _state: &'state mut Self::State,
-        _world: &'world mut Wrld,
+        state: &'state mut Self::State,
+        world: &'world mut Wrld,
     ) -> Option<Self::Item<'world, 'state, Wrld>>
     where
         Wrld: World,
@@ -129,8 +334,20 @@ impl<'w, 's, T: Clone + 'static, const N: usize> SystemParam for EntityEvents<'w
         E: EntityFetch,
         ParamName: 'static,
     {
+        let tick = world.change_tick();
+        let count = world.resource_mut::<EntityEventsCount<T>>();
+        state.update(tick, count.count);
+        let scope = entity
+            .get_component::<EntityScope>()
+            .and_then(|x| x.current);
+        let container = entity.get_component_mut::<EventsContainer<T, N>>()?;
+        container.update(tick);
         Some(EntityEvents {
-            container: entity.get_component_mut::<EventsContainer<T, N>>()?,
+            container,
+            count,
+            start: state.start,
+            end: state.end,
+            scope,
             _marker: Default::default(),
         })
     }
@@ -140,53 +357,181 @@ impl<'w, 's, T: Clone + 'static, const N: usize> SystemParam for EntityEvents<'w
         builder: B,
     ) -> Self::Build<B, SB, ParamName> {
         builder
-            .add_system_without_plugin(
-                entity_events::new::<T>(),
-                crate::stages::Last::new(),
-            )
             .extend_entities(EventsContainer::<T, N>::default())
+            .init_resource::<EntityEventsCount<T>>()
     }
 }
 
-#[system]
-fn entity_events<T: Clone + 'static>(events: EntityEvents<T>) {
-    events.container.events.clear();
-}
-
-impl<'w, 's, T, const N: usize> EntityEvents<'w, 's, T, N> {
+impl<'w, 's, T: Event, const N: usize> EntityEvents<'w, 's, T, N> {
+    /// Events that were sent to the entity since the previous run of a system.
     #[inline(always)]
     pub fn iter(&self) -> impl Iterator<Item = &T> {
-        self.container.events.iter().filter_map(|x| x.as_ref())
+        self.container.read(self.start, self.end, self.scope)
     }
 
     #[inline(always)]
     pub fn send(&mut self, event: T) {
-        self.container.events.push(Some(event));
+        let id = self.count.count;
+        self.count.count += 1;
+        self.container.send(id, self.scope, event);
     }
 
+    /// Sends and reads events in `scope` instead of the one of the entity, see `EntityScope`.
     #[inline(always)]
-    pub fn is_empty(&self) -> bool {
-        self.container.events.is_empty()
+    pub fn set_scope(&mut self, scope: Option<usize>) {
+        self.scope = scope;
     }
 
     #[inline(always)]
-    pub fn len(&self) -> usize {
-        self.container.events.len()
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
     }
 
     #[inline(always)]
-    pub fn clear(&mut self) {
-        self.container.events.clear();
+    pub fn len(&self) -> usize {
+        self.iter().count()
     }
 }
 
-impl<'w, 's, T: 'static, const N: usize> IntoIterator for EntityEvents<'w, 's, T, N> {
+impl<'w, 's, T: Event, const N: usize> IntoIterator for EntityEvents<'w, 's, T, N> {
     type Item = &'w T;
 
     type IntoIter = impl Iterator<Item = &'w T>;
 
     #[inline(always)]
     fn into_iter(self) -> Self::IntoIter {
-        self.container.events.iter().filter_map(|x| x.as_ref())
+        self.container.read(self.start, self.end, self.scope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::prelude::v1::*;
+
+    use super::*;
+    use crate::{plugin::CorePlugin, resources::Break};
+
+    #[derive(Clone)]
+    pub struct Ping(u32);
+
+    impl Event for Ping {}
+
+    pub struct Oldest(u32);
+
+    impl Event for Oldest {
+        type Capacity = Capacity<4>;
+
+        const OVERFLOW: EventOverflow = EventOverflow::DropOldest;
+    }
+
+    pub struct Newest(u32);
+
+    impl Event for Newest {
+        type Capacity = Capacity<1>;
+
+        const OVERFLOW: EventOverflow = EventOverflow::DropNewest;
+    }
+
+    #[derive(Default)]
+    pub struct Seen {
+        early: Vec<u32>,
+        late: Vec<u32>,
+    }
+
+    #[system]
+    fn early(events: Events<Ping>, mut seen: Res<Seen>) {
+        seen.early.extend(events.iter().map(|x| x.0));
+    }
+
+    #[system]
+    fn send(mut events: Events<Ping>, mut count: Res<u32>, mut break_loop: Res<Break>) {
+        events.send(Ping(**count));
+        events.send(Ping(**count + 100));
+        **count += 1;
+        if **count == 3 {
+            **break_loop = Break(true);
+        }
+    }
+
+    #[system]
+    fn late(events: Events<Ping>, mut seen: Res<Seen>) {
+        seen.late.extend(events.iter().map(|x| x.0));
+    }
+
+    schedule! {
+        struct Schedule,
+        loop {
+            Early,
+            Send,
+            Late,
+        },
+    }
+
+    #[test]
+    fn readers() {
+        let mut ecs =
+            EcsBuilderStruct::new::<_, 2>(Schedule::builder(), EntitiesBuilderStruct1::new())
+                .add_plugin(CorePlugin)
+                .add_resource(0u32)
+                .add_resource(Seen::default())
+                .add_system(early::new(), Early::new())
+                .add_system(send::new(), Send::new())
+                .add_system(late::new(), Late::new())
+                .build();
+        ecs.run();
+        let seen = ecs.resource::<Seen>();
+        // Events of the last iteration are sent after `early` ran for the last time.
+        assert_eq!(seen.early, [0, 100, 1, 101]);
+        assert_eq!(seen.late, [0, 100, 1, 101, 2, 102]);
+    }
+
+    #[test]
+    fn skipped_ticks() {
+        let mut container = EventsContainer::<Ping, 2>::default();
+        container.update(Tick(1));
+        container.send(0, None, Ping(0));
+        container.update(Tick(4));
+        container.send(1, None, Ping(1));
+        let events: Vec<_> = container.read(0, 2, None).map(|x| x.0).collect();
+        assert_eq!(events, [0, 1]);
+        container.update(Tick(5));
+        let events: Vec<_> = container.read(0, 2, None).map(|x| x.0).collect();
+        assert_eq!(events, [1]);
+    }
+
+    #[test]
+    fn scopes() {
+        let mut container = EventsContainer::<Ping, 4>::default();
+        container.send(0, None, Ping(0));
+        container.send(1, Some(0), Ping(1));
+        container.send(2, Some(1), Ping(2));
+        let events: Vec<_> = container.read(0, 3, None).map(|x| x.0).collect();
+        assert_eq!(events, [0, 1, 2]);
+        let events: Vec<_> = container.read(0, 3, Some(1)).map(|x| x.0).collect();
+        assert_eq!(events, [2]);
+    }
+
+    #[test]
+    fn capacity() {
+        let mut oldest = EventsContainer::<Oldest, 2>::default();
+        let mut newest = EventsContainer::<Newest, 2>::default();
+        for i in 0..6 {
+            oldest.send(i, None, Oldest(i as u32));
+            newest.send(i, None, Newest(i as u32));
+        }
+        // Capacities are independent of `N`.
+        let events: Vec<_> = oldest.read(0, 6, None).map(|x| x.0).collect();
+        assert_eq!(events, [2, 3, 4, 5]);
+        let events: Vec<_> = newest.read(0, 6, None).map(|x| x.0).collect();
+        assert_eq!(events, [0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "More than 2 `inception::events::tests::Ping` events")]
+    fn overflow() {
+        let mut container = EventsContainer::<Ping, 2>::default();
+        for i in 0..3 {
+            container.send(i, None, Ping(i as u32));
+        }
     }
 }
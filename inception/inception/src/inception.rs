@@ -1,6 +1,8 @@
 #![feature(generic_associated_types)]
 #![feature(type_alias_impl_trait)]
 #![feature(type_name_of_val)]
+#![feature(const_type_name)]
+#![feature(associated_type_defaults)]
 // CUDA compiler uses old toolchain
 #![allow(stable_features)]
 #![no_std]
@@ -42,35 +44,74 @@
 //!   dynamically.
 //! - Parameters can be passed to systems. If a system has a parameter of type `In<T>` or
 //!   `Phantom<T>` then a parameter of type `T` would be required in the constructor of a system.
-//! - Plugins have dependencies.
+//! - Plugins have dependencies. `PluginGroup`s bundle plugins, plugins of a group can be disabled
+//!   or replaced before it is added.
 //! - System parameters can load plugins so that user doesn't forget to load them manually.
-//! - Schedule can contain loops with stages.
-//! - Events have a fixed size buffer. All event buffers have the same size, it can be configured
-//!   through `EcsBuilder`.
+//! - Schedule can contain nested loops and branches with stages. Stages are declared by
+//!   `schedule!`, there is no fixed number of them.
+//! - Systems inside of a stage are ordered with `before` and `after` labels at compile time. Cyclic
+//!   constraints fail to compile.
+//! - Change detection is opt-in. Only components and resources wrapped in `Tracked<T>` store ticks,
+//!   a tick is a loop iteration of a schedule.
+//! - Events have a fixed size buffer. Its default size can be configured through `EcsBuilder`,
+//!   `Event::Capacity` overrides it for an event type. Events are double buffered, each system
+//!   reads every event once and `Event::OVERFLOW` decides what happens to events that don't fit.
+//! - Resources that are used by systems and schedule flags are checked at compile time. Building an
+//!   ECS whose systems use a resource that wasn't added fails to compile with the name of the
+//!   system and the resource. The check is a constant that is evaluated after monomorphization, so
+//!   `cargo check` passes and only `cargo build` fails.
+//! - `Local<T>` keeps private state of a system between runs. Systems that run for each entity keep
+//!   it per entity.
 //! - There are no commands, so no dynamic insertion of components and resources.
+//! - `Ecs::describe` and `EcsBuilder::describe` list stages, systems, resources and components of
+//!   an ECS, so that its wiring can be checked without reading its type name.
 
 pub use inception_macros::{nest, schedule, system, system_param, SystemParamPlugin};
 pub use static_assertions;
 
+#[cfg(feature = "alloc")]
+pub use crate::archetype::VecArchetype;
+#[cfg(feature = "alloc")]
+pub use crate::describe::{EcsDescription, EntityDescription, StageDescription};
 pub use crate::{
+    archetype::{Archetype, ArchetypeStorage, ArrayArchetype},
+    change_detection::{Added, ChangeTicks, Changed, Mut, Ref, Tick, Tracked, TrackedRes},
     config::EntityConfig,
+    describe::{EcsVisitor, ShortName},
     ecs::Ecs,
     ecs_builder::{EcsBuilder, EcsBuilderIter, EcsBuilderOperation, EcsBuilderStruct},
     entities::*,
-    events::{EntityEvents, Events, EventsTag},
+    events::{
+        Capacity, DefaultCapacity, EntityEvents, EntityScope, Event, EventCapacity, EventOverflow,
+        Events, EventsTag,
+    },
     input::{In, Input, InputItem, PhantomIn},
-    nest_module::{Nest, Nestable, Nested, StackedNest},
-    plugin::{CorePlugin, Plugin, PluginLoader, SystemParamPlugin},
+    nest_module::{nest_contains, Nest, NestTypes, Nestable, Nested, StackedNest},
+    plugin::{
+        CorePlugin, LoadedOnce, Plugin, PluginAt, PluginGroup, PluginGroupBuilder, PluginList,
+        PluginLoader, SystemParamPlugin,
+    },
     query::Query,
-    schedule::ScheduleBuilderTrait,
-    stage::{AddSystemToStageCommand, Stage, StageBuilder},
+    resource_check::{RequiredResource, RequiredResources, ResourceCheck, ScheduleFlagCheck},
+    schedule::{
+        AnySchedule, EmptyStageList, FirstStage, LastStage, LastStageList, NextStage,
+        ScheduleBuilderTrait, StageCheck, StageIndex, StageList, StageListBuilder, StageListRun,
+    },
+    stage::{
+        AddSystemToStageCommand, Stage, StageBuilder, StageLabel, StageSystems, StageSystemsBuilder,
+    },
     stages::Last,
-    system::{IntoInferredSystem, System, SystemBuilder, SystemState},
+    system::{
+        IntoInferredSystem, RunCondition, RunConditionBuilder, RunIf, RunIfBuilder, System,
+        SystemBuilder, SystemState,
+    },
+    system_label::{After, Before, Labeled, Labels, SystemLabel, SystemOrder},
     system_param::{
-        EntityParam, Mapper, ParamLabel, PhantomSystemParam, Res, SystemParam,
-        SystemParamNameMapper, SystemParamState, UnknownSystem,
+        ChunkMapper, EntityParam, FieldName, Local, Mapper, ParamChunk, ParamChunkState,
+        ParamLabel, PhantomSystemParam, Res, ResRef, SystemParam, SystemParamNameMapper,
+        SystemParamState, UnknownSystem, MAX_SYSTEM_PARAMS,
     },
-    world::{FromWorld, World},
+    world::{BasicWorld, FromWorld, World},
 };
 
 extern crate no_std_compat as std;
@@ -81,7 +122,10 @@ extern crate derive_more;
 // Makes doctests and tests pass when they are using proc macros.
 extern crate self as inception;
 
+mod archetype;
+mod change_detection;
 pub mod config;
+mod describe;
 mod ecs;
 mod ecs_builder;
 mod entities;
@@ -90,10 +134,12 @@ mod input;
 mod nest_module;
 mod plugin;
 mod query;
+mod resource_check;
 pub mod resources;
 mod schedule;
 mod stage;
 pub mod stages;
 mod system;
+mod system_label;
 mod system_param;
 mod world;
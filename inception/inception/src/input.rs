@@ -48,15 +48,14 @@ impl Input for StackedNest {
 /// fn my_system(input: In<i32>) {
 ///     println!("input: {}", *input);
 /// }
-/// pub type Update0 = Stage0;
 /// schedule! {
 ///     struct Schedule,
-///     Stage0 as Update0,
+///     Update0,
 /// }
-/// let ecs = EcsBuilderStruct::new::<_, 0>(ConfigBuilder::new(), Schedule::builder())
-///     .add_system_to_stage(my_system::new(42), Update0::new())
-///     .build()
-///     .run();
+/// let mut ecs = EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct1::new())
+///     .add_system(my_system::new(42), Update0::new())
+///     .build();
+/// ecs.run();
 /// ```
 pub struct In<'w, 's, T, const N: usize> {
     data: &'s mut T,
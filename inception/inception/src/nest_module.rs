@@ -1,8 +1,9 @@
 use std::prelude::v1::*;
 use either::Either;
-use ergnomics::OptionExt;
 use inception_macros::SystemParamPlugin;
 
+use crate::system_label::str_eq;
+
 #[macro_export]
 macro_rules! NestTy {
     ($item: ident, $($items: ident),+) => {
@@ -35,25 +36,29 @@ pub trait Nest: Nestable {
     fn get_if_index_is_one<T: 'static>(&self, index: usize) -> Option<&T>;
     #[inline(always)]
     fn field<T: 'static>(&self) -> &T {
-        self.get().expect_or_else(|| {
-            format!(
+        match self.get() {
+            Some(x) => x,
+            None => panic!(
                 "Failed to get a field of type `{}`",
-                std::any::type_name::<T>()
-            )
-        })
+                core::any::type_name::<T>()
+            ),
+        }
     }
     #[inline(always)]
     fn field_mut<T: 'static>(&mut self) -> &mut T {
-        self.get_mut().expect_or_else(|| {
-            format!(
+        match self.get_mut() {
+            Some(x) => x,
+            None => panic!(
                 "Failed to get a field of type `{}`",
-                std::any::type_name::<T>()
-            )
-        })
+                core::any::type_name::<T>()
+            ),
+        }
     }
     fn get<T: 'static>(&self) -> Option<&T>;
     fn get_mut<T: 'static>(&mut self) -> Option<&mut T>;
     fn len(&self) -> usize;
+    /// Calls `f` with a type name of each item in the order they were pushed.
+    fn for_each_type_name<F: FnMut(&'static str)>(&self, f: &mut F);
 
     #[inline(always)]
     fn get_by_index<T: 'static>(&self, index: usize) -> Option<&T> {
@@ -61,6 +66,23 @@ pub trait Nest: Nestable {
     }
 }
 
+/// Types of items that were pushed into a nest, so that they can be checked at compile time.
+pub trait NestTypes: 'static {
+    type Inner: NestTypes;
+    /// Type name of the last pushed item or `None` if the nest is empty.
+    const NAME: Option<&'static str>;
+    /// True if the nest belongs to a branch that never runs, see `LoadedOnce`.
+    const UNREACHABLE: bool = false;
+}
+
+/// Returns true if nest `N` contains an item whose type name is `name`.
+pub const fn nest_contains<N: NestTypes>(name: &str) -> bool {
+    match N::NAME {
+        Some(x) => str_eq(x, name) || nest_contains::<N::Inner>(name),
+        None => false,
+    }
+}
+
 impl<N: Nestable> Nestable for Option<N> {}
 
 impl<A: Nest + Nestable> Nest for Option<A> {
@@ -92,6 +114,13 @@ impl<A: Nest + Nestable> Nest for Option<A> {
             None => 0,
         }
     }
+
+    #[inline(always)]
+    fn for_each_type_name<F: FnMut(&'static str)>(&self, f: &mut F) {
+        if let Some(x) = self {
+            x.for_each_type_name(f);
+        }
+    }
 }
 
 impl<L, R> Nestable for Either<L, R> {}
@@ -128,6 +157,14 @@ impl<L: Nest, R: Nest> Nest for Either<L, R> {
             Either::Right(x) => x.len(),
         }
     }
+
+    #[inline(always)]
+    fn for_each_type_name<F: FnMut(&'static str)>(&self, f: &mut F) {
+        match self {
+            Either::Left(x) => x.for_each_type_name(f),
+            Either::Right(x) => x.for_each_type_name(f),
+        }
+    }
 }
 
 #[derive(Debug, Clone, SystemParamPlugin)]
@@ -135,6 +172,12 @@ pub struct StackedNest;
 
 impl Nestable for StackedNest {}
 
+impl NestTypes for StackedNest {
+    type Inner = StackedNest;
+
+    const NAME: Option<&'static str> = None;
+}
+
 impl Nest for StackedNest {
     #[inline(always)]
     fn get_if_index_is_one<T: 'static>(&self, _index: usize) -> Option<&T> {
@@ -156,6 +199,11 @@ impl Nest for StackedNest {
         0
     }
 
+    #[inline(always)]
+    fn for_each_type_name<F: FnMut(&'static str)>(&self, _f: &mut F) {
+        // Empty
+    }
+
     #[inline(always)]
     fn get_by_index<T: 'static>(&self, _index: usize) -> Option<&T> {
         None
@@ -178,6 +226,13 @@ impl<A, T> Nested<A, T> {
 
 impl<N, T> Nestable for Nested<N, T> {}
 
+impl<A: NestTypes, T: 'static> NestTypes for Nested<A, T> {
+    type Inner = A;
+
+    const NAME: Option<&'static str> = Some(core::any::type_name::<T>());
+    const UNREACHABLE: bool = A::UNREACHABLE;
+}
+
 impl<S: 'static, A: Nest> Nest for Nested<A, S> {
     #[inline(always)]
     fn get_if_index_is_one<T: 'static>(&self, index: usize) -> Option<&T> {
@@ -214,4 +269,10 @@ impl<S: 'static, A: Nest> Nest for Nested<A, S> {
     fn len(&self) -> usize {
         self.inner.len() + 1
     }
+
+    #[inline(always)]
+    fn for_each_type_name<F: FnMut(&'static str)>(&self, f: &mut F) {
+        self.inner.for_each_type_name(f);
+        f(core::any::type_name::<S>());
+    }
 }
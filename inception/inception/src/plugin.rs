@@ -2,8 +2,8 @@ use core::marker::PhantomData;
 
 use crate::{
     ecs_builder::EcsBuilder,
-    nest_module::{Nest, Nestable, Nested, StackedNest},
-    resources::Break,
+    nest_module::{nest_contains, Nest, NestTypes, Nestable, Nested, StackedNest},
+    resources::{Break, ChangeTick},
 };
 
 pub trait Plugin {
@@ -14,6 +14,188 @@ pub trait Plugin {
     fn build<B: EcsBuilder>(self, builder: B) -> Self::Build<B>;
 }
 
+/// Plugins that are added together with `EcsBuilder::add_plugins`. The returned builder can
+/// be changed before it is added, plugins can be added, disabled or replaced with different
+/// settings.
+/// # Example
+/// ```
+/// # #![feature(type_alias_impl_trait)]
+/// use inception::*;
+/// struct Counter(u32);
+/// impl Plugin for Counter {
+///     type Deps<L: PluginLoader> = L;
+///
+///     type Build<B: EcsBuilder> = impl EcsBuilder;
+///
+///     fn deps<L: PluginLoader>(&mut self, loader: L) -> Self::Deps<L> {
+///         loader
+///     }
+///
+///     fn build<B: EcsBuilder>(self, builder: B) -> Self::Build<B> {
+///         builder.add_resource(self.0)
+///     }
+/// }
+/// struct DefaultPlugins;
+/// impl PluginGroup for DefaultPlugins {
+///     type Plugins = Nested<Nested<StackedNest, CorePlugin>, Counter>;
+///
+///     fn build(self) -> PluginGroupBuilder<Self::Plugins> {
+///         PluginGroupBuilder::new().add(CorePlugin).add(Counter(0))
+///     }
+/// }
+/// let group = DefaultPlugins.build().replace(Counter(10));
+/// ```
+pub trait PluginGroup {
+    type Plugins: PluginList;
+
+    fn build(self) -> PluginGroupBuilder<Self::Plugins>;
+}
+
+/// Plugins of a `PluginGroup`, they are `Nested` in the order they were added.
+pub trait PluginList {
+    type Build<B: EcsBuilder>: EcsBuilder;
+
+    fn build<B: EcsBuilder>(self, builder: B) -> Self::Build<B>;
+}
+
+impl PluginList for StackedNest {
+    type Build<B: EcsBuilder> = B;
+
+    #[inline(always)]
+    fn build<B: EcsBuilder>(self, builder: B) -> Self::Build<B> {
+        builder
+    }
+}
+
+impl<N: PluginList, P: Plugin + 'static> PluginList for Nested<N, P> {
+    type Build<B: EcsBuilder> = <N::Build<B> as EcsBuilder>::AddPlugin<P>;
+
+    #[inline(always)]
+    fn build<B: EcsBuilder>(self, builder: B) -> Self::Build<B> {
+        self.inner.build(builder).add_plugin(self.item)
+    }
+}
+
+/// Index of a plugin that is the last one in a `PluginList`.
+pub struct Here;
+/// Index of a plugin that is inside of the rest of a `PluginList`.
+pub struct There<I>(PhantomData<I>);
+
+/// Plugin list that contains plugin `P` at index `I`. The index is inferred, so that plugins can
+/// be found by their type, e.g. `group.disable::<CorePlugin, _>()`.
+pub trait PluginAt<P, I>: PluginList {
+    type Remove: PluginList;
+
+    fn remove(self) -> Self::Remove;
+    fn plugin_mut(&mut self) -> &mut P;
+}
+
+impl<N: PluginList, P: Plugin + 'static> PluginAt<P, Here> for Nested<N, P> {
+    type Remove = N;
+
+    #[inline(always)]
+    fn remove(self) -> Self::Remove {
+        self.inner
+    }
+
+    #[inline(always)]
+    fn plugin_mut(&mut self) -> &mut P {
+        &mut self.item
+    }
+}
+
+impl<N, Q, P, I> PluginAt<P, There<I>> for Nested<N, Q>
+where
+    N: PluginAt<P, I>,
+    Q: Plugin + 'static,
+{
+    type Remove = Nested<N::Remove, Q>;
+
+    #[inline(always)]
+    fn remove(self) -> Self::Remove {
+        Nested {
+            item: self.item,
+            inner: self.inner.remove(),
+        }
+    }
+
+    #[inline(always)]
+    fn plugin_mut(&mut self) -> &mut P {
+        self.inner.plugin_mut()
+    }
+}
+
+/// Plugins of a `PluginGroup`. Plugins are identified by their type, a group cannot contain
+/// multiple plugins of the same type.
+pub struct PluginGroupBuilder<P> {
+    plugins: P,
+}
+
+impl PluginGroupBuilder<StackedNest> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            plugins: StackedNest,
+        }
+    }
+}
+
+impl Default for PluginGroupBuilder<StackedNest> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: PluginList> PluginGroupBuilder<P> {
+    /// Adds a plugin after the plugins of a group.
+    #[inline(always)]
+    pub fn add<Plug: Plugin + 'static>(self, plugin: Plug) -> PluginGroupBuilder<Nested<P, Plug>> {
+        PluginGroupBuilder {
+            plugins: Nested {
+                item: plugin,
+                inner: self.plugins,
+            },
+        }
+    }
+
+    /// Removes a plugin from a group. Systems of other plugins that use its resources fail to
+    /// compile, unless the resources are added in some other way.
+    #[inline(always)]
+    pub fn disable<Plug, I>(self) -> PluginGroupBuilder<P::Remove>
+    where
+        P: PluginAt<Plug, I>,
+    {
+        PluginGroupBuilder {
+            plugins: self.plugins.remove(),
+        }
+    }
+
+    /// Replaces a plugin of a group with the same plugin that has different settings.
+    #[inline(always)]
+    pub fn replace<Plug, I>(mut self, plugin: Plug) -> Self
+    where
+        P: PluginAt<Plug, I>,
+    {
+        *self.plugins.plugin_mut() = plugin;
+        self
+    }
+
+    #[inline(always)]
+    pub(crate) fn add_to<B: EcsBuilder>(self, builder: B) -> P::Build<B> {
+        self.plugins.build(builder)
+    }
+}
+
+impl<P: PluginList> PluginGroup for PluginGroupBuilder<P> {
+    type Plugins = P;
+
+    #[inline(always)]
+    fn build(self) -> PluginGroupBuilder<Self::Plugins> {
+        self
+    }
+}
+
 pub trait SystemParamPlugin {
     type Build<B: EcsBuilder>: EcsBuilder;
 
@@ -29,25 +211,83 @@ pub trait PluginLoader: sealed::IntoEcsBuilder {
     fn load<Plug: Plugin + 'static>(self, plugin: Plug) -> Self::LoadMultiple<Plug>;
 }
 
-pub trait Plugins {
+pub trait Plugins: NestTypes {
     type Push<Plugin: 'static>: Plugins;
 
     fn contains_plugin<Plugin: 'static>(&self) -> bool;
     fn push<Plugin: 'static>(self) -> Self::Push<Plugin>;
 }
 
+/// Resources of a world in the `PluginLoader::load_once` branch that is taken when `Plug` was
+/// already loaded. Both branches are compiled, but if plugins `L` don't contain `Plug` then this
+/// one never runs and its systems aren't checked for missing resources.
+pub struct LoadedOnce<R, Plug, L> {
+    resources: R,
+    _p: PhantomData<(Plug, L)>,
+}
+
+impl<R, Plug, L> LoadedOnce<R, Plug, L> {
+    #[inline(always)]
+    pub(crate) fn new(resources: R) -> Self {
+        Self {
+            resources,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<R, Plug, L> Nestable for LoadedOnce<R, Plug, L> {}
+
+impl<R: Nest, Plug, L> Nest for LoadedOnce<R, Plug, L> {
+    #[inline(always)]
+    fn get_if_index_is_one<T: 'static>(&self, index: usize) -> Option<&T> {
+        self.resources.get_if_index_is_one(index)
+    }
+
+    #[inline(always)]
+    fn get<T: 'static>(&self) -> Option<&T> {
+        self.resources.get()
+    }
+
+    #[inline(always)]
+    fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.resources.get_mut()
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.resources.len()
+    }
+
+    #[inline(always)]
+    fn for_each_type_name<F: FnMut(&'static str)>(&self, f: &mut F) {
+        self.resources.for_each_type_name(f);
+    }
+}
+
+impl<R: NestTypes, Plug: 'static, L: NestTypes> NestTypes for LoadedOnce<R, Plug, L> {
+    type Inner = R::Inner;
+
+    const NAME: Option<&'static str> = R::NAME;
+    const UNREACHABLE: bool =
+        R::UNREACHABLE || !nest_contains::<L>(core::any::type_name::<PhantomData<Plug>>());
+}
+
 pub struct CorePlugin;
 
 impl Plugin for CorePlugin {
-    type Build<B: EcsBuilder> = B::AddResource<Break>;
     type Deps<L: PluginLoader> = L;
 
+    type Build<B: EcsBuilder> = impl EcsBuilder;
+
     fn deps<L: PluginLoader>(&mut self, loader: L) -> Self::Deps<L> {
         loader
     }
 
     fn build<B: EcsBuilder>(self, builder: B) -> Self::Build<B> {
-        builder.add_resource(Break(false))
+        builder
+            .add_resource(Break(false))
+            .add_resource(ChangeTick::default())
     }
 }
 
@@ -99,3 +339,71 @@ impl Plugins for StackedNest {
         <Self as Nestable>::push(self, PhantomData::<Plugin>)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    schedule! {
+        struct Schedule,
+        Update,
+    }
+
+    macro_rules! resource_plugin {
+        ($name:ident, $ty:ty) => {
+            struct $name($ty);
+
+            impl Plugin for $name {
+                type Deps<L: PluginLoader> = L;
+
+                type Build<B: EcsBuilder> = impl EcsBuilder;
+
+                #[inline(always)]
+                fn deps<L: PluginLoader>(&mut self, loader: L) -> Self::Deps<L> {
+                    loader
+                }
+
+                #[inline(always)]
+                fn build<B: EcsBuilder>(self, builder: B) -> Self::Build<B> {
+                    builder.add_resource(self.0)
+                }
+            }
+        };
+    }
+
+    resource_plugin!(Counter, u32);
+    resource_plugin!(Flag, bool);
+    resource_plugin!(Extra, i64);
+
+    struct TestPlugins;
+
+    impl PluginGroup for TestPlugins {
+        type Plugins = Nested<Nested<StackedNest, Counter>, Flag>;
+
+        fn build(self) -> PluginGroupBuilder<Self::Plugins> {
+            PluginGroupBuilder::new().add(Counter(0)).add(Flag(true))
+        }
+    }
+
+    #[test]
+    fn plugin_group() {
+        let ecs = EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct1::new())
+            .add_plugins(TestPlugins)
+            .build();
+        assert_eq!(*ecs.resource::<u32>(), 0);
+        assert_eq!(ecs.get_resource::<bool>(), Some(&true));
+        assert_eq!(ecs.get_resource::<i64>(), None);
+
+        let group = TestPlugins
+            .build()
+            .replace(Counter(10))
+            .disable::<Flag, _>()
+            .add(Extra(-1));
+        let ecs = EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct1::new())
+            .add_plugins(group)
+            .build();
+        assert_eq!(*ecs.resource::<u32>(), 10);
+        assert_eq!(ecs.get_resource::<bool>(), None);
+        assert_eq!(*ecs.resource::<i64>(), -1);
+    }
+}
@@ -1,6 +1,7 @@
 use core::marker::PhantomData;
 
 use crate::{
+    change_detection::Tick,
     entities::WorldQuery,
     impl_no_plugin,
     system_param::{SystemParam},
@@ -8,7 +9,10 @@ use crate::{
     EcsBuilder, Entity, PhantomSystemParam, SystemParamNameMapper,
 };
 
-/// Reads entities that have specific components and executes a function on them.
+/// Reads entities that have specific components and executes a function on them. Entities can be
+/// filtered with `With<T>` and `Without<T>`, `Option<&T>` reads a component if the entity has it.
+/// `Changed<T>` and `Added<T>` match entities whose `Tracked<T>` component changed or was added in
+/// the current loop iteration.
 /// # Example
 /// ```
 /// use inception::*;
@@ -40,6 +44,11 @@ impl<'w, 's, C: WorldQuery, W: World, const N: usize> Query<'w, 's, C, W, N> {
     pub fn run_entity<'this, F: FnMut(QueryItem<'this, C>)>(&'this mut self, entity: Entity, f: F) {
         self.world.query_entity::<F, C>(entity, f);
     }
+
+    /// Used to mutate `Tracked` components, e.g. `tracked.get_mut(tick)`.
+    pub fn change_tick(&self) -> Tick {
+        self.world.change_tick()
+    }
 }
 
 impl<'w, 's, W: World + 'static, Params, const N: usize> SystemParam
@@ -64,37 +73,64 @@ impl<'w, 's, W: World + 'static, Params, const N: usize> SystemParam
 
 #[cfg(test)]
 mod tests {
-    use crate::{stages::*, *};
+    use crate::*;
 
-    pub type Update0 = Stage0;
     schedule! {
         struct Schedule,
-        Stage0 as Update0,
+        Update0,
+    }
+
+    #[system]
+    fn sum(mut query: Query<(&i32, &mut u32)>, mut total: Res<u32>) {
+        query.run(|(a, b)| {
+            *b += *a as u32;
+            **total += *b;
+        });
+    }
+
+    /// Sums of `i32` components of entities with `u32`, without `u32` and of all entities with
+    /// `u32` added to them if they have it.
+    #[system]
+    fn filter(
+        mut with: Query<(&i32, With<u32>)>,
+        mut without: Query<(&i32, Without<u32>)>,
+        mut optional: Query<(&i32, Option<&u32>)>,
+        mut sums: Res<[i32; 3]>,
+    ) {
+        with.run(|(a, _)| sums[0] += *a);
+        without.run(|(a, _)| sums[1] += *a);
+        optional.run(|(a, b)| sums[2] += *a + b.map_or(0, |b| *b as i32));
     }
 
     #[test]
     fn query() {
-        #[system]
-        fn sum(mut query: Query<(&i32, &mut u32)>, mut total: Res<u32>) {
-            query.run(|(a, b)| {
-                *b += *a as u32;
-                **total += *b;
-            });
-            assert_eq!(**total, 6);
-        }
-        let mut a = Entity::default();
-        let mut b = Entity::default();
-        let mut ecs = EcsBuilderStruct::new::<_, 0>(ConfigBuilder::new(), Schedule::builder())
-            .spawn(&mut a)
-            .spawn(&mut b)
-            .add_resource(0u32)
-            .insert_component(1i32, a)
-            .insert_component(1u32, a)
-            .insert_component(2i32, b)
-            .insert_component(2u32, b)
-            .add_system_to_stage(sum::new(), Update0::new())
-            .build();
+        let mut ecs =
+            EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct2::new())
+                .add_resource(0u32)
+                .add_component(1i32, Entity0)
+                .add_component(1u32, Entity0)
+                .add_component(2i32, Entity1)
+                .add_component(2u32, Entity1)
+                .add_system(sum::new(), Update0::new())
+                .build();
+        ecs.run();
+        assert_eq!(*ecs.resource::<u32>(), 6);
+        assert_eq!(*ecs.component::<u32>(Entity(1)), 4);
+    }
+
+    #[test]
+    fn filters() {
+        let mut ecs =
+            EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct3::new())
+                .add_resource([0i32; 3])
+                .add_component(1i32, Entity0)
+                .add_component(10u32, Entity0)
+                .add_component(2i32, Entity1)
+                .add_component(4i32, Entity2)
+                .add_component(20u32, Entity2)
+                .add_system(filter::new(), Update0::new())
+                .build();
         ecs.run();
-        // *ecs.get_resource::<u32>().unwrap()
+        assert_eq!(*ecs.resource::<[i32; 3]>(), [5, 2, 37]);
     }
 }
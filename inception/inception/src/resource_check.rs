@@ -0,0 +1,199 @@
+use core::marker::PhantomData;
+
+use crate::{
+    nest_module::{nest_contains, NestTypes},
+    system::SystemBuilder,
+    system_param::SystemParam,
+    world::World,
+};
+
+/// Maximum length of an error message of a missing resource, longer messages are truncated.
+const MAX_MESSAGE_LEN: usize = 1024;
+
+/// Resources that a system parameter uses, see `SystemParam::Resources`. It is a tree of
+/// `RequiredResource`s that are joined with pairs, `()` doesn't require any resource.
+pub trait RequiredResources: 'static {
+    type Left: RequiredResources;
+    type Right: RequiredResources;
+    /// Type name of a resource if this is a `RequiredResource`.
+    const NAME: Option<&'static str> = None;
+    const IS_EMPTY: bool = false;
+}
+
+/// Resource of type `T` must be added to the world.
+pub struct RequiredResource<T>(PhantomData<T>);
+
+impl RequiredResources for () {
+    type Left = ();
+    type Right = ();
+
+    const IS_EMPTY: bool = true;
+}
+
+impl<T: 'static> RequiredResources for RequiredResource<T> {
+    type Left = ();
+    type Right = ();
+
+    const NAME: Option<&'static str> = Some(core::any::type_name::<T>());
+}
+
+impl<A: RequiredResources, B: RequiredResources> RequiredResources for (A, B) {
+    type Left = A;
+    type Right = B;
+}
+
+/// Returns the type name of the first resource in `Req` that isn't in resources `R`.
+pub const fn missing_resource<Req: RequiredResources, R: NestTypes>() -> Option<&'static str> {
+    if Req::IS_EMPTY {
+        return None;
+    }
+    if let Some(name) = Req::NAME {
+        return if nest_contains::<R>(name) {
+            None
+        } else {
+            Some(name)
+        };
+    }
+    match missing_resource::<Req::Left, R>() {
+        Some(name) => Some(name),
+        None => missing_resource::<Req::Right, R>(),
+    }
+}
+
+/// Fails to compile if system `S` with parameters `P` uses a resource that isn't in world `W`.
+/// `#[system]` evaluates `CHECK` when a system is built. It is evaluated after monomorphization,
+/// so `cargo check` passes and only `cargo build` fails.
+/// # Example
+/// ```compile_fail,E0080
+/// use inception::*;
+/// schedule! {
+///     struct Schedule,
+///     Update,
+/// }
+/// #[system]
+/// fn count(mut value: Res<u32>) {
+///     **value += 1;
+/// }
+/// // System `rust_out::count` uses resource `u32` that wasn't added, add it with a plugin or
+/// // `EcsBuilder::add_resource`
+/// let mut ecs = EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct1::new())
+///     .add_system(count::new(), Update::new())
+///     .build();
+/// ```
+pub struct ResourceCheck<W, P, S>(PhantomData<(W, P, S)>);
+
+impl<W, P, S> ResourceCheck<W, P, S>
+where
+    W: World,
+    P: SystemParam,
+    S: for<'w, 's> SystemBuilder<'w, 's>,
+{
+    pub const CHECK: () = {
+        let missing = if <W::Resources as NestTypes>::UNREACHABLE {
+            None
+        } else {
+            missing_resource::<P::Resources, W::Resources>()
+        };
+        if let Some(name) = missing {
+            let message = Message::EMPTY
+                .push("System `")
+                .push(<S as SystemBuilder>::NAME)
+                .push("` uses resource `")
+                .push(name)
+                .push("` that wasn't added, add it with a plugin or `EcsBuilder::add_resource`");
+            panic!("{}", message.as_str());
+        }
+    };
+}
+
+/// Fails to compile if schedule `S` uses a resource of `Flags` that isn't in world `W`. Flags are
+/// resources of `loop(T)` and `if T`, loops also use `Break` and `ChangeTick`. `schedule!`
+/// evaluates `CHECK` when a schedule is built.
+/// # Example
+/// ```compile_fail,E0080
+/// use inception::*;
+/// pub struct Skip(bool);
+/// impl resources::ScheduleFlag for Skip {
+///     fn is_set(&self) -> bool {
+///         self.0
+///     }
+///
+///     fn reset(&mut self) {
+///         self.0 = false;
+///     }
+/// }
+/// schedule! {
+///     struct Schedule,
+///     if !Skip {
+///         Update,
+///     },
+/// }
+/// // Schedule `rust_out::Schedule<()>` uses resource `rust_out::Skip` that wasn't added, add it
+/// // with a plugin or `EcsBuilder::add_resource`
+/// let mut ecs =
+///     EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct1::new()).build();
+/// ```
+pub struct ScheduleFlagCheck<W, Flags, S>(PhantomData<(W, Flags, S)>);
+
+impl<W, Flags, S> ScheduleFlagCheck<W, Flags, S>
+where
+    W: World,
+    Flags: RequiredResources,
+    S: 'static,
+{
+    pub const CHECK: () = {
+        let missing = if <W::Resources as NestTypes>::UNREACHABLE {
+            None
+        } else {
+            missing_resource::<Flags, W::Resources>()
+        };
+        if let Some(name) = missing {
+            let message = Message::EMPTY
+                .push("Schedule `")
+                .push(core::any::type_name::<S>())
+                .push("` uses resource `")
+                .push(name)
+                .push("` that wasn't added, add it with a plugin or `EcsBuilder::add_resource`");
+            panic!("{}", message.as_str());
+        }
+    };
+}
+
+/// Error message that is built at compile time.
+pub(crate) struct Message {
+    bytes: [u8; MAX_MESSAGE_LEN],
+    len: usize,
+}
+
+impl Message {
+    pub(crate) const EMPTY: Message = Message {
+        bytes: [0; MAX_MESSAGE_LEN],
+        len: 0,
+    };
+
+    pub(crate) const fn push(mut self, s: &str) -> Self {
+        let s = s.as_bytes();
+        let mut i = 0;
+        while i < s.len() && self.len < MAX_MESSAGE_LEN {
+            self.bytes[self.len] = s[i];
+            self.len += 1;
+            i += 1;
+        }
+        // Doesn't split a character when the message is truncated.
+        while i < s.len() && i > 0 && s[i] & 0b1100_0000 == 0b1000_0000 {
+            i -= 1;
+            self.len -= 1;
+        }
+        self
+    }
+
+    pub(crate) const fn as_str(&self) -> &str {
+        // Only whole characters of `&str`s are pushed.
+        unsafe {
+            core::str::from_utf8_unchecked(core::slice::from_raw_parts(
+                self.bytes.as_ptr(),
+                self.len,
+            ))
+        }
+    }
+}
@@ -1,2 +1,25 @@
 /// Used to break the system execution loop of a schedule that uses looped stage.
 pub struct Break(pub bool);
+
+impl ScheduleFlag for Break {
+    #[inline(always)]
+    fn is_set(&self) -> bool {
+        self.0
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.0 = false;
+    }
+}
+
+/// Resource that controls the flow of a schedule. `loop(T) { ... }` runs until `T` is set and
+/// resets it afterwards, `if T { ... }` runs when `T` is set.
+pub trait ScheduleFlag: 'static {
+    fn is_set(&self) -> bool;
+    fn reset(&mut self);
+}
+
+/// Current change tick, see `inception::Tick`.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChangeTick(pub crate::Tick);
@@ -1,30 +1,472 @@
-use crate::{stage::StageBuilder, *};
-
-macro_rules! def_stage_items {
-    ($f:ident, $ret:ident) => {
-        type $ret<System: SystemBuilder<'static, 'static> + 'static>: ScheduleBuilderTrait
-            + StageBuilder;
-        #[must_use]
-        fn $f<System>(self, system: System) -> Self::$ret<System>
-        where
-            System: SystemBuilder<'static, 'static> + 'static;
-    };
-}
+use core::marker::PhantomData;
+
+use crate::{
+    describe::EcsVisitor,
+    resource_check::Message,
+    stage::{AddSystemToStageCommand, StageBuilder, StageLabel, StageSystemsBuilder},
+    system_label::str_eq,
+    *,
+};
+
 // Ends with `Trait` to avoid name clashing with schedule macro.
 pub trait ScheduleBuilderTrait {
-    all_tuples::repeat!(
-        def_stage_items,
-        0,
-        32,
-        add_system_to_stage,
-        AddSystemToStage
+    /// Identifies the schedule, stage labels generated by `schedule!` have the same
+    /// `StageLabel::Schedule`.
+    type Schedule: 'static;
+    /// Whether the schedule has `inception::stages::Last`.
+    const HAS_LAST: bool;
+    type AddSystemToStage<I: StageIndex, System: SystemBuilder<'static, 'static> + 'static>: ScheduleBuilderTrait
+        + StageBuilder;
+    #[must_use]
+    fn add_system_to_stage<I, System>(self, system: System) -> Self::AddSystemToStage<I, System>
+    where
+        I: StageIndex,
+        System: SystemBuilder<'static, 'static> + 'static;
+}
+
+/// Stages of a schedule stored as a list. Stage labels generated by `schedule!` point into it with
+/// a type level index.
+pub struct StageList<H, T> {
+    pub head: H,
+    pub tail: T,
+}
+
+/// End of a stage list of a schedule that doesn't have `inception::stages::Last`.
+pub struct EmptyStageList;
+
+/// End of a stage list that holds `inception::stages::Last`.
+pub struct LastStageList<S>(pub S);
+
+pub trait StageListBuilder {
+    type AddSystem<I: StageIndex, System: SystemBuilder<'static, 'static> + 'static>: StageListBuilder;
+    type Build<W: World, const N_EVENTS: usize>: StageListRun<W> + 'static;
+    fn add_system<I, System>(self, system: System) -> Self::AddSystem<I, System>
+    where
+        I: StageIndex,
+        System: SystemBuilder<'static, 'static> + 'static;
+    fn build<W: World, const N_EVENTS: usize>(self, world: &mut W) -> Self::Build<W, N_EVENTS>;
+}
+
+pub trait StageListRun<W> {
+    fn run_stage<I: StageIndex>(&mut self, world: &mut W);
+    fn describe_stage<I: StageIndex, V: EcsVisitor>(&self, visitor: &mut V);
+}
+
+/// Type level index of a stage in a `StageList`.
+pub trait StageIndex: 'static {
+    type AddSystem<H, T, System>: StageListBuilder
+    where
+        H: StageSystemsBuilder,
+        T: StageListBuilder,
+        System: SystemBuilder<'static, 'static> + 'static;
+    type AddSystemToLast<S, System>: StageListBuilder
+    where
+        S: StageSystemsBuilder,
+        System: SystemBuilder<'static, 'static> + 'static;
+    fn add_system<H, T, System>(
+        list: StageList<H, T>,
+        system: System,
+    ) -> Self::AddSystem<H, T, System>
+    where
+        H: StageSystemsBuilder,
+        T: StageListBuilder,
+        System: SystemBuilder<'static, 'static> + 'static;
+    fn add_system_to_last<S, System>(
+        list: LastStageList<S>,
+        system: System,
+    ) -> Self::AddSystemToLast<S, System>
+    where
+        S: StageSystemsBuilder,
+        System: SystemBuilder<'static, 'static> + 'static;
+    fn run_stage<W, H: Stage<W>, T: StageListRun<W>>(list: &mut StageList<H, T>, world: &mut W);
+    fn run_last<W, S: Stage<W>>(list: &mut LastStageList<S>, world: &mut W);
+    fn describe_stage<W, H, T, V>(list: &StageList<H, T>, visitor: &mut V)
+    where
+        H: Stage<W>,
+        T: StageListRun<W>,
+        V: EcsVisitor;
+    fn describe_last<W, S: Stage<W>, V: EcsVisitor>(list: &LastStageList<S>, visitor: &mut V);
+}
+
+/// Index of the first stage in a schedule.
+pub struct FirstStage;
+
+/// Index of the stage after `I`.
+pub struct NextStage<I>(PhantomData<I>);
+
+/// Index of `inception::stages::Last`, it is always stored at the end of a list.
+pub struct LastStage;
+
+/// `StageLabel::Schedule` of stages that can be used in any schedule that has them.
+pub enum AnySchedule {}
+
+/// Fails to compile if stage `L` isn't a stage of schedule `S`. `EcsBuilder::add_system`
+/// evaluates `CHECK` when a system is added.
+/// # Example
+/// ```compile_fail
+/// use inception::*;
+/// mod a {
+///     inception::schedule! {
+///         struct Schedule,
+///         Update,
+///     }
+/// }
+/// mod b {
+///     inception::schedule! {
+///         struct Schedule,
+///         Update,
+///     }
+/// }
+/// #[system]
+/// fn my_system() {}
+/// let mut ecs = EcsBuilderStruct::new::<_, 0>(a::Schedule::builder(), EntitiesBuilderStruct1::new())
+///     // Stage of another schedule
+///     .add_system(my_system::new(), b::Update::new())
+///     .build();
+/// ecs.run();
+/// ```
+pub struct StageCheck<S, L>(PhantomData<(S, L)>);
+
+impl<S: ScheduleBuilderTrait, L: StageLabel> StageCheck<S, L> {
+    pub const CHECK: () = {
+        let stage = core::any::type_name::<L>();
+        let schedule = core::any::type_name::<S::Schedule>();
+        let label_schedule = core::any::type_name::<L::Schedule>();
+        if str_eq(label_schedule, core::any::type_name::<AnySchedule>()) {
+            if !S::HAS_LAST {
+                let message = Message::EMPTY
+                    .push("Schedule `")
+                    .push(schedule)
+                    .push("` doesn't have stage `")
+                    .push(stage)
+                    .push("`");
+                panic!("{}", message.as_str());
+            }
+        } else if !str_eq(label_schedule, schedule) {
+            let message = Message::EMPTY
+                .push("Stage `")
+                .push(stage)
+                .push("` belongs to schedule `")
+                .push(label_schedule)
+                .push("`, not to `")
+                .push(schedule)
+                .push("`");
+            panic!("{}", message.as_str());
+        }
+    };
+}
+
+impl StageIndex for FirstStage {
+    type AddSystem<H, T, System> = StageList<Nested<H, AddSystemToStageCommand<System>>, T>
+    where
+        H: StageSystemsBuilder,
+        T: StageListBuilder,
+        System: SystemBuilder<'static, 'static> + 'static;
+    type AddSystemToLast<S, System> = LastStageList<S>
+    where
+        S: StageSystemsBuilder,
+        System: SystemBuilder<'static, 'static> + 'static;
+
+    #[inline(always)]
+    fn add_system<H, T, System>(
+        list: StageList<H, T>,
+        system: System,
+    ) -> Self::AddSystem<H, T, System>
+    where
+        H: StageSystemsBuilder,
+        T: StageListBuilder,
+        System: SystemBuilder<'static, 'static> + 'static,
+    {
+        StageList {
+            head: Nested {
+                item: AddSystemToStageCommand { builder: system },
+                inner: list.head,
+            },
+            tail: list.tail,
+        }
+    }
+
+    fn add_system_to_last<S, System>(
+        _list: LastStageList<S>,
+        _system: System,
+    ) -> Self::AddSystemToLast<S, System>
+    where
+        S: StageSystemsBuilder,
+        System: SystemBuilder<'static, 'static> + 'static,
+    {
+        panic_missing_stage::<Self>()
+    }
+
+    #[inline(always)]
+    fn run_stage<W, H: Stage<W>, T: StageListRun<W>>(list: &mut StageList<H, T>, world: &mut W) {
+        list.head.run(world);
+    }
+
+    #[inline(always)]
+    fn run_last<W, S: Stage<W>>(_list: &mut LastStageList<S>, _world: &mut W) {
+        // Index out of range
+    }
+
+    #[inline(always)]
+    fn describe_stage<W, H, T, V>(list: &StageList<H, T>, visitor: &mut V)
+    where
+        H: Stage<W>,
+        T: StageListRun<W>,
+        V: EcsVisitor,
+    {
+        list.head.describe(visitor);
+    }
+
+    #[inline(always)]
+    fn describe_last<W, S: Stage<W>, V: EcsVisitor>(_list: &LastStageList<S>, _visitor: &mut V) {
+        // Index out of range
+    }
+}
+
+impl<I: StageIndex> StageIndex for NextStage<I> {
+    type AddSystem<H, T, System> = StageList<H, T::AddSystem<I, System>>
+    where
+        H: StageSystemsBuilder,
+        T: StageListBuilder,
+        System: SystemBuilder<'static, 'static> + 'static;
+    type AddSystemToLast<S, System> = LastStageList<S>
+    where
+        S: StageSystemsBuilder,
+        System: SystemBuilder<'static, 'static> + 'static;
+
+    #[inline(always)]
+    fn add_system<H, T, System>(
+        list: StageList<H, T>,
+        system: System,
+    ) -> Self::AddSystem<H, T, System>
+    where
+        H: StageSystemsBuilder,
+        T: StageListBuilder,
+        System: SystemBuilder<'static, 'static> + 'static,
+    {
+        StageList {
+            head: list.head,
+            tail: list.tail.add_system::<I, System>(system),
+        }
+    }
+
+    fn add_system_to_last<S, System>(
+        _list: LastStageList<S>,
+        _system: System,
+    ) -> Self::AddSystemToLast<S, System>
+    where
+        S: StageSystemsBuilder,
+        System: SystemBuilder<'static, 'static> + 'static,
+    {
+        panic_missing_stage::<Self>()
+    }
+
+    #[inline(always)]
+    fn run_stage<W, H: Stage<W>, T: StageListRun<W>>(list: &mut StageList<H, T>, world: &mut W) {
+        list.tail.run_stage::<I>(world);
+    }
+
+    #[inline(always)]
+    fn run_last<W, S: Stage<W>>(_list: &mut LastStageList<S>, _world: &mut W) {
+        // Index out of range
+    }
+
+    #[inline(always)]
+    fn describe_stage<W, H, T, V>(list: &StageList<H, T>, visitor: &mut V)
+    where
+        H: Stage<W>,
+        T: StageListRun<W>,
+        V: EcsVisitor,
+    {
+        list.tail.describe_stage::<I, V>(visitor);
+    }
+
+    #[inline(always)]
+    fn describe_last<W, S: Stage<W>, V: EcsVisitor>(_list: &LastStageList<S>, _visitor: &mut V) {
+        // Index out of range
+    }
+}
+
+impl StageIndex for LastStage {
+    type AddSystem<H, T, System> = StageList<H, T::AddSystem<Self, System>>
+    where
+        H: StageSystemsBuilder,
+        T: StageListBuilder,
+        System: SystemBuilder<'static, 'static> + 'static;
+    type AddSystemToLast<S, System> = LastStageList<Nested<S, AddSystemToStageCommand<System>>>
+    where
+        S: StageSystemsBuilder,
+        System: SystemBuilder<'static, 'static> + 'static;
+
+    #[inline(always)]
+    fn add_system<H, T, System>(
+        list: StageList<H, T>,
+        system: System,
+    ) -> Self::AddSystem<H, T, System>
+    where
+        H: StageSystemsBuilder,
+        T: StageListBuilder,
+        System: SystemBuilder<'static, 'static> + 'static,
+    {
+        StageList {
+            head: list.head,
+            tail: list.tail.add_system::<Self, System>(system),
+        }
+    }
+
+    #[inline(always)]
+    fn add_system_to_last<S, System>(
+        list: LastStageList<S>,
+        system: System,
+    ) -> Self::AddSystemToLast<S, System>
+    where
+        S: StageSystemsBuilder,
+        System: SystemBuilder<'static, 'static> + 'static,
+    {
+        LastStageList(Nested {
+            item: AddSystemToStageCommand { builder: system },
+            inner: list.0,
+        })
+    }
+
+    #[inline(always)]
+    fn run_stage<W, H: Stage<W>, T: StageListRun<W>>(list: &mut StageList<H, T>, world: &mut W) {
+        list.tail.run_stage::<Self>(world);
+    }
+
+    #[inline(always)]
+    fn run_last<W, S: Stage<W>>(list: &mut LastStageList<S>, world: &mut W) {
+        list.0.run(world);
+    }
+
+    #[inline(always)]
+    fn describe_stage<W, H, T, V>(list: &StageList<H, T>, visitor: &mut V)
+    where
+        H: Stage<W>,
+        T: StageListRun<W>,
+        V: EcsVisitor,
+    {
+        list.tail.describe_stage::<Self, V>(visitor);
+    }
+
+    #[inline(always)]
+    fn describe_last<W, S: Stage<W>, V: EcsVisitor>(list: &LastStageList<S>, visitor: &mut V) {
+        list.0.describe(visitor);
+    }
+}
+
+impl<H: StageSystemsBuilder, T: StageListBuilder> StageListBuilder for StageList<H, T> {
+    type AddSystem<I: StageIndex, System: SystemBuilder<'static, 'static> + 'static> =
+        I::AddSystem<H, T, System>;
+    type Build<W: World, const N_EVENTS: usize> =
+        StageList<H::BuildStage<W, N_EVENTS>, T::Build<W, N_EVENTS>>;
+
+    #[inline(always)]
+    fn add_system<I, System>(self, system: System) -> Self::AddSystem<I, System>
+    where
+        I: StageIndex,
+        System: SystemBuilder<'static, 'static> + 'static,
+    {
+        I::add_system(self, system)
+    }
+
+    #[inline(always)]
+    fn build<W: World, const N_EVENTS: usize>(self, world: &mut W) -> Self::Build<W, N_EVENTS> {
+        StageList {
+            head: self.head.build_stage(world),
+            tail: self.tail.build(world),
+        }
+    }
+}
+
+impl StageListBuilder for EmptyStageList {
+    type AddSystem<I: StageIndex, System: SystemBuilder<'static, 'static> + 'static> = Self;
+    type Build<W: World, const N_EVENTS: usize> = Self;
+
+    fn add_system<I, System>(self, _system: System) -> Self::AddSystem<I, System>
+    where
+        I: StageIndex,
+        System: SystemBuilder<'static, 'static> + 'static,
+    {
+        panic_missing_stage::<I>()
+    }
+
+    #[inline(always)]
+    fn build<W: World, const N_EVENTS: usize>(self, _world: &mut W) -> Self::Build<W, N_EVENTS> {
+        self
+    }
+}
+
+impl<S: StageSystemsBuilder> StageListBuilder for LastStageList<S> {
+    type AddSystem<I: StageIndex, System: SystemBuilder<'static, 'static> + 'static> =
+        I::AddSystemToLast<S, System>;
+    type Build<W: World, const N_EVENTS: usize> = LastStageList<S::BuildStage<W, N_EVENTS>>;
+
+    #[inline(always)]
+    fn add_system<I, System>(self, system: System) -> Self::AddSystem<I, System>
+    where
+        I: StageIndex,
+        System: SystemBuilder<'static, 'static> + 'static,
+    {
+        I::add_system_to_last(self, system)
+    }
+
+    #[inline(always)]
+    fn build<W: World, const N_EVENTS: usize>(self, world: &mut W) -> Self::Build<W, N_EVENTS> {
+        LastStageList(self.0.build_stage(world))
+    }
+}
+
+impl<W, H: Stage<W>, T: StageListRun<W>> StageListRun<W> for StageList<H, T> {
+    #[inline(always)]
+    fn run_stage<I: StageIndex>(&mut self, world: &mut W) {
+        I::run_stage(self, world);
+    }
+
+    #[inline(always)]
+    fn describe_stage<I: StageIndex, V: EcsVisitor>(&self, visitor: &mut V) {
+        I::describe_stage::<W, H, T, V>(self, visitor);
+    }
+}
+
+impl<W> StageListRun<W> for EmptyStageList {
+    #[inline(always)]
+    fn run_stage<I: StageIndex>(&mut self, _world: &mut W) {
+        // Index out of range
+    }
+
+    #[inline(always)]
+    fn describe_stage<I: StageIndex, V: EcsVisitor>(&self, _visitor: &mut V) {
+        // Index out of range
+    }
+}
+
+impl<W, S: Stage<W>> StageListRun<W> for LastStageList<S> {
+    #[inline(always)]
+    fn run_stage<I: StageIndex>(&mut self, world: &mut W) {
+        I::run_last(self, world);
+    }
+
+    #[inline(always)]
+    fn describe_stage<I: StageIndex, V: EcsVisitor>(&self, visitor: &mut V) {
+        I::describe_last::<W, S, V>(self, visitor);
+    }
+}
+
+fn panic_missing_stage<I>() -> ! {
+    panic!(
+        "Schedule does not have a stage with index `{}`.",
+        core::any::type_name::<I>()
     );
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{plugin::CorePlugin, resources::Break, stages::*};
+    use crate::{
+        plugin::CorePlugin,
+        resources::{Break, ScheduleFlag},
+    };
     #[system]
     fn system_a(mut value: Res<i32>) {
         assert_eq!(**value, 0);
@@ -80,47 +522,151 @@ mod tests {
         **value += 1;
     }
 
-    pub type A = Stage0;
-    pub type BA = Stage1;
-    pub type BB = Stage2;
-    pub type BC = Stage3;
-    pub type CA = Stage4;
-    pub type CB = Stage5;
-    pub type CC = Stage6;
-
     schedule! {
         struct Schedule,
-        Stage0 as A,
+        A,
         loop {
-            Stage1 as BA,
-            Stage2 as BB,
-            Stage3 as BC,
+            BA,
+            BB,
+            BC,
         },
-        Stage4 as CA,
-        Stage5 as CB,
-        Stage6 as CC,
+        CA,
+        CB,
+        CC,
     }
     #[test]
     pub fn schedule() {
         let schedule = Schedule::builder();
-        let ecs = EcsBuilderStruct::new::<_, 0>(ConfigBuilder::new(), schedule)
+        let ecs = EcsBuilderStruct::new::<_, 0>(schedule, EntitiesBuilderStruct1::new())
             .add_plugin(CorePlugin)
-            .insert_resource(0i32)
-            .insert_resource(0usize)
-            .add_system_to_stage(system_a::new(), A::new())
-            .add_system_to_stage(system_ba::new(), BA::new())
-            .add_system_to_stage(system_bb::new(), BB::new())
-            .add_system_to_stage(system_bc::new(), BC::new())
-            .add_system_to_stage(system_ca::new(), CA::new())
-            .add_system_to_stage(system_cb::new(), CB::new())
-            .add_system_to_stage(system_cc::new(), CC::new());
+            .add_resource(0i32)
+            .add_resource(0usize)
+            .add_system(system_a::new(), A::new())
+            .add_system(system_ba::new(), BA::new())
+            .add_system(system_bb::new(), BB::new())
+            .add_system(system_bc::new(), BC::new())
+            .add_system(system_ca::new(), CA::new())
+            .add_system(system_cb::new(), CB::new())
+            .add_system(system_cc::new(), CC::new());
         let mut ecs = ecs.build();
         ecs.run();
-        let value = ecs.get_resource::<i32>().unwrap();
-        assert_eq!(*value, 37);
-        let value = ecs.get_resource::<usize>().unwrap();
-        assert_eq!(*value, 2);
-        let value = ecs.get_resource::<Break>().unwrap();
-        assert_eq!(value.0, false);
+        assert_eq!(*ecs.resource::<i32>(), 37);
+        assert_eq!(*ecs.resource::<usize>(), 2);
+        assert!(!ecs.resource::<Break>().0);
+    }
+
+    pub struct Outer(pub bool);
+
+    impl ScheduleFlag for Outer {
+        fn is_set(&self) -> bool {
+            self.0
+        }
+
+        fn reset(&mut self) {
+            self.0 = false;
+        }
+    }
+
+    pub struct Odd(pub bool);
+
+    impl ScheduleFlag for Odd {
+        fn is_set(&self) -> bool {
+            self.0
+        }
+
+        fn reset(&mut self) {
+            self.0 = false;
+        }
+    }
+
+    #[derive(Default)]
+    pub struct Counts {
+        passes: u32,
+        inner: u32,
+        odd: u32,
+        even: u32,
+        not_odd: u32,
+    }
+
+    #[system]
+    fn start(mut counts: Res<Counts>, mut odd: Res<Odd>) {
+        counts.passes += 1;
+        **odd = Odd(counts.passes % 2 == 1);
+    }
+
+    #[system]
+    fn inner(mut counts: Res<Counts>, mut break_loop: Res<Break>) {
+        counts.inner += 1;
+        if counts.inner % 2 == 0 {
+            **break_loop = Break(true);
+        }
+    }
+
+    #[system]
+    fn odd(mut counts: Res<Counts>) {
+        counts.odd += 1;
+    }
+
+    #[system]
+    fn even(mut counts: Res<Counts>) {
+        counts.even += 1;
+    }
+
+    #[system]
+    fn not_odd(mut counts: Res<Counts>) {
+        counts.not_odd += 1;
+    }
+
+    #[system]
+    fn end(counts: Res<Counts>, mut outer: Res<Outer>) {
+        if counts.passes == 3 {
+            **outer = Outer(true);
+        }
+    }
+
+    schedule! {
+        struct NestedSchedule,
+        loop(Outer) {
+            Start,
+            loop {
+                Inner,
+            },
+            if Odd {
+                OddPass,
+            } else {
+                EvenPass,
+            },
+            if !Odd {
+                NotOddPass,
+            },
+            End,
+        },
+    }
+
+    #[test]
+    pub fn nested_loops_and_branches() {
+        let mut ecs =
+            EcsBuilderStruct::new::<_, 0>(NestedSchedule::builder(), EntitiesBuilderStruct1::new())
+                .add_plugin(CorePlugin)
+                .add_resource(Counts::default())
+                .add_resource(Outer(false))
+                .add_resource(Odd(false))
+                .add_system(start::new(), Start::new())
+                .add_system(inner::new(), Inner::new())
+                .add_system(odd::new(), OddPass::new())
+                .add_system(even::new(), EvenPass::new())
+                .add_system(not_odd::new(), NotOddPass::new())
+                .add_system(end::new(), End::new())
+                .build();
+        ecs.run();
+        let counts = ecs.resource::<Counts>();
+        assert_eq!(counts.passes, 3);
+        // Inner loop breaks after 2 iterations and starts again in the next pass.
+        assert_eq!(counts.inner, 6);
+        assert_eq!(counts.odd, 2);
+        assert_eq!(counts.even, 1);
+        assert_eq!(counts.not_odd, 1);
+        assert!(!ecs.resource::<Outer>().0);
+        assert!(!ecs.resource::<Break>().0);
     }
 }
@@ -3,10 +3,13 @@ use core::marker::PhantomData;
 use either::Either;
 
 use crate::{
+    describe::EcsVisitor,
     nest_module::{Nested, StackedNest},
+    schedule::StageIndex,
     system::System,
+    system_label::{topological_order, SystemOrder, MAX_STAGE_SYSTEMS},
     world::World,
-    EcsBuilder, SystemBuilder,
+    SystemBuilder,
 };
 
 pub struct AddSystemToStageCommand<System> {
@@ -14,19 +17,17 @@ pub struct AddSystemToStageCommand<System> {
 }
 
 pub trait StageLabel {
-    type AddSystem<System: SystemBuilder<'static, 'static> + 'static, Builder: EcsBuilder>: EcsBuilder;
-    #[must_use]
-    fn add_system<System, Builder>(
-        system: System,
-        builder: Builder,
-    ) -> Self::AddSystem<System, Builder>
-    where
-        Builder: EcsBuilder,
-        System: SystemBuilder<'static, 'static> + 'static;
+    type Index: StageIndex;
+    /// Schedule that the stage belongs to, see `ScheduleBuilderTrait::Schedule`.
+    type Schedule: 'static;
 }
 
 pub trait Stage<W> {
     fn run(&mut self, world: &mut W);
+
+    /// Visits systems in the order they run.
+    #[inline(always)]
+    fn describe<V: EcsVisitor>(&self, _visitor: &mut V) {}
 }
 
 pub trait StageBuilder {
@@ -49,23 +50,59 @@ impl StageBuilder for StackedNest {
     }
 }
 
-impl<N: StageBuilder, S: SystemBuilder<'static, 'static> + 'static> StageBuilder
+impl<N: StageSystemsBuilder, S: SystemBuilder<'static, 'static> + 'static> StageBuilder
     for Nested<N, AddSystemToStageCommand<S>>
 {
     type BuildStage<W: World, const N_EVENTS: usize> =
-        Nested<N::BuildStage<W, N_EVENTS>, StageData<(), S::System<W, N_EVENTS>>>;
+        <Self as StageSystemsBuilder>::BuildSystems<W, N_EVENTS>;
 
     #[inline(always)]
     fn build_stage<W: World, const N_EVENTS: usize>(
         self,
         world: &mut W,
     ) -> Self::BuildStage<W, N_EVENTS> {
+        self.build_systems(world)
+    }
+}
+
+/// Builds systems of a stage that can be ordered.
+pub trait StageSystemsBuilder: StageBuilder {
+    type BuildSystems<W: World, const N_EVENTS: usize>: StageSystems<W> + 'static;
+    fn build_systems<W: World, const N_EVENTS: usize>(
+        self,
+        world: &mut W,
+    ) -> Self::BuildSystems<W, N_EVENTS>;
+}
+
+impl StageSystemsBuilder for StackedNest {
+    type BuildSystems<W: World, const N_EVENTS: usize> = StackedNest;
+
+    #[inline(always)]
+    fn build_systems<W: World, const N_EVENTS: usize>(
+        self,
+        _world: &mut W,
+    ) -> Self::BuildSystems<W, N_EVENTS> {
+        StackedNest
+    }
+}
+
+impl<N: StageSystemsBuilder, S: SystemBuilder<'static, 'static> + 'static> StageSystemsBuilder
+    for Nested<N, AddSystemToStageCommand<S>>
+{
+    type BuildSystems<W: World, const N_EVENTS: usize> =
+        Nested<N::BuildSystems<W, N_EVENTS>, StageData<S, S::System<W, N_EVENTS>>>;
+
+    #[inline(always)]
+    fn build_systems<W: World, const N_EVENTS: usize>(
+        self,
+        world: &mut W,
+    ) -> Self::BuildSystems<W, N_EVENTS> {
         Nested {
             item: StageData {
-                stage: PhantomData::<()>::default(),
+                stage: PhantomData::<S>::default(),
                 systems: self.item.builder.build(world),
             },
-            inner: self.inner.build_stage(world),
+            inner: self.inner.build_systems(world),
         }
     }
 }
@@ -97,8 +134,16 @@ impl<L: Stage<W>, R: Stage<W>, W: World> Stage<W> for Either<L, R> {
             Either::Right(r) => r.run(world),
         }
     }
+
+    fn describe<V: EcsVisitor>(&self, visitor: &mut V) {
+        match self {
+            Either::Left(l) => l.describe(visitor),
+            Either::Right(r) => r.describe(visitor),
+        }
+    }
 }
 
+/// `S` is the builder of the system, it holds ordering constraints.
 pub struct StageData<S, F> {
     stage: PhantomData<S>,
     systems: F,
@@ -122,10 +167,95 @@ impl<W: World> Stage<W> for StackedNest {
     }
 }
 
-impl<W: World, N: Stage<W>, I: Stage<W>> Stage<W> for Nested<N, I> {
+/// Systems of a stage that are ordered by their labels at compile time.
+pub trait StageSystems<W> {
+    const LEN: usize;
+    const SYSTEMS: [SystemOrder; MAX_STAGE_SYSTEMS];
+    /// Order in which systems are called. Misordered systems fail to compile when it is
+    /// evaluated.
+    const ORDER: [usize; MAX_STAGE_SYSTEMS] = topological_order(&Self::SYSTEMS, Self::LEN);
+
+    /// Runs `i`-th system in the order they were added.
+    fn run_nth(&mut self, i: usize, world: &mut W);
+    /// Visits `i`-th system in the order they were added.
+    fn describe_nth<V: EcsVisitor>(&self, i: usize, visitor: &mut V);
+}
+
+impl<W: World> StageSystems<W> for StackedNest {
+    const LEN: usize = 0;
+    const SYSTEMS: [SystemOrder; MAX_STAGE_SYSTEMS] = [SystemOrder::EMPTY; MAX_STAGE_SYSTEMS];
+
+    #[inline(always)]
+    fn run_nth(&mut self, _i: usize, _world: &mut W) {
+        // Empty
+    }
+
+    #[inline(always)]
+    fn describe_nth<V: EcsVisitor>(&self, _i: usize, _visitor: &mut V) {
+        // Empty
+    }
+}
+
+impl<W, N, S, F> StageSystems<W> for Nested<N, StageData<S, F>>
+where
+    W: World,
+    N: StageSystems<W>,
+    S: SystemBuilder<'static, 'static> + 'static,
+    F: System<'static, 'static, W> + 'static,
+{
+    const LEN: usize = N::LEN + 1;
+    const SYSTEMS: [SystemOrder; MAX_STAGE_SYSTEMS] = {
+        if N::LEN == MAX_STAGE_SYSTEMS {
+            panic!("Too many systems in a stage");
+        }
+        let mut systems = N::SYSTEMS;
+        systems[N::LEN] = SystemOrder {
+            name: S::NAME,
+            labels: S::LABELS,
+            before: S::BEFORE,
+            after: S::AFTER,
+        };
+        systems
+    };
+
+    #[inline(always)]
+    fn run_nth(&mut self, i: usize, world: &mut W) {
+        if i == N::LEN {
+            self.item.run(world);
+        } else {
+            self.inner.run_nth(i, world);
+        }
+    }
+
+    #[inline(always)]
+    fn describe_nth<V: EcsVisitor>(&self, i: usize, visitor: &mut V) {
+        if i == N::LEN {
+            visitor.visit_system(S::NAME);
+        } else {
+            self.inner.describe_nth(i, visitor);
+        }
+    }
+}
+
+impl<W: World, N: StageSystems<W>, S, F> Stage<W> for Nested<N, StageData<S, F>>
+where
+    Self: StageSystems<W>,
+{
     #[inline(always)]
     fn run(&mut self, world: &mut W) {
-        self.inner.run(world);
-        self.item.run(world);
+        let mut i = 0;
+        while i < Self::LEN {
+            self.run_nth(Self::ORDER[i], world);
+            i += 1;
+        }
+    }
+
+    #[inline(always)]
+    fn describe<V: EcsVisitor>(&self, visitor: &mut V) {
+        let mut i = 0;
+        while i < Self::LEN {
+            self.describe_nth(Self::ORDER[i], visitor);
+            i += 1;
+        }
     }
 }
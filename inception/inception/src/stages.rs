@@ -1,42 +1,35 @@
-use crate::{stage::StageLabel, *};
+use crate::{
+    schedule::{AnySchedule, LastStage},
+    stage::StageLabel,
+};
 
-pub type Last = Stage31;
+/// Stage that can be placed anywhere in a schedule, plugins use it to run systems at the end of a
+/// loop iteration. Other stage labels are generated by `schedule!`.
+/// Adding a system to `Last` fails to compile if the schedule doesn't have it:
+/// ```compile_fail
+/// use inception::*;
+/// schedule! {
+///     struct Schedule,
+///     Update,
+/// }
+/// #[system]
+/// fn my_system() {}
+/// let mut ecs = EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct1::new())
+///     .add_system(my_system::new(), Last::new())
+///     .build();
+/// ecs.run();
+/// ```
+#[derive(Default)]
+pub struct Last;
 
-macro_rules! def_stage {
-    ($stage:ident, $f:ident, $ret:ident) => {
-        pub struct $stage;
-
-        impl $stage {
-            #[inline(always)]
-            pub fn new() -> Self {
-                $stage
-            }
-        }
-
-        impl StageLabel for $stage {
-            type AddSystem<System: SystemBuilder<'static, 'static> + 'static, Builder: EcsBuilder> =
-                Builder::$ret<System>;
-
-            #[inline(always)]
-            fn add_system<System, Builder>(
-                system: System,
-                builder: Builder,
-            ) -> Self::AddSystem<System, Builder>
-            where
-                Builder: EcsBuilder,
-                System: SystemBuilder<'static, 'static> + 'static,
-            {
-                builder.$f(system)
-            }
-        }
-    };
+impl Last {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Last
+    }
 }
 
-all_tuples::repeat!(
-    def_stage,
-    0,
-    32,
-    Stage,
-    add_system_to_stage,
-    AddSystemToStage
-);
+impl StageLabel for Last {
+    type Index = LastStage;
+    type Schedule = AnySchedule;
+}
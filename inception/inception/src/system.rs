@@ -1,11 +1,12 @@
 use core::marker::PhantomData;
 
-use all_tuples::all_tuples;
 use either::Either;
 use ergnomics::*;
+use inception_macros::{all_param_ids, all_system_params};
 
 use crate::{
     entities::{EntityFetch, EntityFnMut},
+    system_label::{After, Before, Labeled, Labels, SystemLabel},
     world::BasicWorld,
     *,
 };
@@ -131,7 +132,7 @@ macro_rules! impl_into_inferred_system {
     };
 }
 
-all_tuples!(impl_into_inferred_system, 0, 16, P);
+all_system_params!(impl_into_inferred_system, P);
 
 macro_rules! impl_mapper_for_fn {
     ($($param_id: expr),*; $param: tt) => {
@@ -146,9 +147,13 @@ macro_rules! impl_mapper_for_fn {
     };
 }
 
-const PARAM_NAMES: [&'static str; 16] = [
-    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15",
-];
+macro_rules! param_names {
+    ($($param_id: literal),*) => {
+        [$(stringify!($param_id)),*]
+    };
+}
+
+const PARAM_NAMES: [&'static str; MAX_SYSTEM_PARAMS] = all_param_ids!(param_names);
 
 macro_rules! impl_param_name_mapper {
     ($($param: ident),*) => {
@@ -158,11 +163,11 @@ macro_rules! impl_param_name_mapper {
                 PARAM_NAMES.get(PARAM_ID).copied()
             }
         }
-        impl_mapper_for_fn!(0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15; ($($param),*));
+        all_param_ids!(impl_mapper_for_fn; ($($param),*));
     };
 }
 
-all_tuples!(impl_param_name_mapper, 0, 16, P);
+all_system_params!(impl_param_name_mapper, P);
 
 impl<'w, 's, W, F, P> SystemWithParams<'w, 's, W, P> for CustomFn<F>
 where
@@ -170,9 +175,11 @@ where
     W: World,
     P: SystemParam<Item<'w, 's, W> = P>,
 {
+    type Out = F::Out;
+
     #[inline(always)]
-    fn call(&mut self, param: P) {
-        self.system.call(param);
+    fn call(&mut self, param: P) -> Self::Out {
+        self.system.call(param)
     }
 }
 
@@ -222,6 +229,36 @@ where
     }
 }
 
+struct AnyEntity<'a, 'w, 's, W, SB, F, P: SystemParam> {
+    world: *mut W,
+    states: &'s mut P::State,
+    system: &'a mut F,
+    passed: &'a mut bool,
+    _w: PhantomData<&'w SB>,
+}
+
+impl<'a, 'w, 's, W, P, SB, F> EntityFnMut for AnyEntity<'a, 'w, 's, W, SB, F, P>
+where
+    W: World,
+    P: SystemParam,
+    SB: SystemParamNameMapper,
+    F: SystemWithParams<'w, 's, W, P, Out = bool>,
+{
+    #[inline(always)]
+    fn call_mut<E: EntityFetch>(&mut self, entity: &mut E) {
+        unsafe {
+            let lifetime_params = some!(P::get_param_for_entity::<_, SB, (), E>(
+                entity,
+                self.states,
+                &mut *self.world,
+            ));
+            let params = core::mem::transmute_copy(&lifetime_params);
+            core::mem::forget(lifetime_params);
+            *self.passed |= self.system.call(params);
+        };
+    }
+}
+
 pub struct SystemState<P: SystemParam> {
     states: P::State,
 }
@@ -264,6 +301,39 @@ impl<P: SystemParam> SystemState<P> {
             system.call(params);
         }
     }
+
+    /// Calls a system that returns `bool`. If it operates on entities then it returns true when
+    /// it returns true for any entity.
+    #[inline(always)]
+    pub fn check<
+        'w,
+        's,
+        W: World,
+        SB: SystemParamNameMapper + 'static,
+        S: SystemWithParams<'w, 's, W, P, Out = bool>,
+    >(
+        &'s mut self,
+        system: &mut S,
+        world: &'w mut W,
+    ) -> bool {
+        if P::IS_QUERY {
+            let mut passed = false;
+            let any_entity = AnyEntity {
+                world,
+                states: &mut self.states,
+                system,
+                passed: &mut passed,
+                _w: PhantomData::<&SB>,
+            };
+            world.for_each(any_entity);
+            passed
+        } else {
+            let lifetime_params = P::get_param::<_, SB, ()>(&mut self.states, world);
+            let params = unsafe { core::mem::transmute_copy(&lifetime_params) };
+            core::mem::forget(lifetime_params);
+            system.call(params)
+        }
+    }
 }
 
 impl<'w, 's, W: World, F, P, SB> System<'w, 's, W> for SystemContainer<SB, F, P>
@@ -293,6 +363,12 @@ where
 
 pub trait SystemBuilder<'w, 's> {
     type System<W: World + 'static + 'w + 's, const N: usize>: System<'w, 's, W> + SystemParamPlugin;
+    /// Labels and ordering constraints that are used to order systems inside of a stage.
+    const LABELS: Labels = Labels::EMPTY;
+    const BEFORE: Labels = Labels::EMPTY;
+    const AFTER: Labels = Labels::EMPTY;
+    /// Name of the system that is used by `Ecs::describe`.
+    const NAME: &'static str = core::any::type_name::<Self>();
 
     fn build<W: World + 'static, const N: usize>(self, world: &mut W) -> Self::System<W, N>;
 
@@ -302,6 +378,138 @@ pub trait SystemBuilder<'w, 's> {
     ) -> <Self::System<BasicWorld, N> as SystemParamPlugin>::Build<B> {
         Self::System::build(builder)
     }
+
+    #[inline(always)]
+    fn label<L: SystemLabel>(self, _label: L) -> Labeled<Self, L>
+    where
+        Self: Sized,
+    {
+        Labeled::new(self)
+    }
+
+    /// Runs the system before systems with label `L` in the same stage.
+    #[inline(always)]
+    fn before<L: SystemLabel>(self, _label: L) -> Before<Self, L>
+    where
+        Self: Sized,
+    {
+        Before::new(self)
+    }
+
+    /// Runs the system after systems with label `L` in the same stage.
+    #[inline(always)]
+    fn after<L: SystemLabel>(self, _label: L) -> After<Self, L>
+    where
+        Self: Sized,
+    {
+        After::new(self)
+    }
+
+    /// Runs the system only when `condition` returns true. Calling it multiple times requires all
+    /// conditions to pass.
+    /// # Example
+    /// ```
+    /// use inception::*;
+    /// #[system]
+    /// fn is_positive(value: Res<i32>) -> bool {
+    ///     **value > 0
+    /// }
+    /// #[system]
+    /// fn decrement(mut value: Res<i32>) {
+    ///     **value -= 1;
+    /// }
+    /// let _system = decrement::new().run_if(is_positive::new());
+    /// ```
+    #[inline(always)]
+    fn run_if<C: RunConditionBuilder<'w, 's>>(self, condition: C) -> RunIfBuilder<Self, C>
+    where
+        Self: Sized,
+    {
+        RunIfBuilder {
+            system: self,
+            condition,
+        }
+    }
+}
+
+/// A system that returns `bool`, `#[system]` implements it for functions that return `bool`.
+pub trait RunCondition<'w, 's, W: World> {
+    fn check(&'s mut self, world: &'w mut W) -> bool;
+}
+
+pub trait RunConditionBuilder<'w, 's> {
+    type Condition<W: World + 'static + 'w + 's, const N: usize>: RunCondition<'w, 's, W>
+        + SystemParamPlugin;
+
+    fn build_condition<W: World + 'static, const N: usize>(
+        self,
+        world: &mut W,
+    ) -> Self::Condition<W, N>;
+}
+
+pub struct RunIfBuilder<S, C> {
+    system: S,
+    condition: C,
+}
+
+impl<'w, 's, S, C> SystemBuilder<'w, 's> for RunIfBuilder<S, C>
+where
+    S: SystemBuilder<'w, 's>,
+    C: RunConditionBuilder<'w, 's>,
+{
+    type System<W: World, const N: usize> = RunIf<S::System<W, N>, C::Condition<W, N>>;
+
+    const AFTER: Labels = S::AFTER;
+    const BEFORE: Labels = S::BEFORE;
+    const LABELS: Labels = S::LABELS;
+    const NAME: &'static str = S::NAME;
+
+    #[inline(always)]
+    fn build<W: World, const N: usize>(self, world: &mut W) -> Self::System<W, N> {
+        RunIf {
+            condition: self.condition.build_condition(world),
+            system: self.system.build(world),
+        }
+    }
+}
+
+/// Calls the system when the condition passes. Conditions that operate on entities pass if they
+/// pass for any entity.
+pub struct RunIf<S, C> {
+    system: S,
+    condition: C,
+}
+
+impl<'w, 's, W, S, C> System<'w, 's, W> for RunIf<S, C>
+where
+    W: World,
+    S: System<'w, 's, W>,
+    C: RunCondition<'w, 's, W>,
+{
+    #[inline(always)]
+    fn call(&'s mut self, world: &'w mut W) {
+        let world_ptr = world as *mut W;
+        // SAFETY: See `Nested`.
+        unsafe {
+            if self.condition.check(&mut *world_ptr) {
+                self.system.call(&mut *world_ptr);
+            }
+        }
+    }
+}
+
+impl<S, C> SystemParamPlugin for RunIf<S, C>
+where
+    S: SystemParamPlugin,
+    C: SystemParamPlugin,
+{
+    type Build<B: EcsBuilder> = C::Build<S::Build<B>>;
+
+    #[inline(always)]
+    fn build<B: EcsBuilder>(builder: B) -> Self::Build<B> {
+        let builder = S::build(builder);
+        C::build(builder)
+    }
 }
 
 impl<'w, 's, Inner, F> SystemBuilder<'w, 's> for Nested<Inner, F>
@@ -344,23 +552,27 @@ impl<'w, 's> SystemBuilder<'w, 's> for StackedNest {
 }
 
 pub trait SystemWithParams<'w, 's, W, P> {
-    fn call(&mut self, param: P);
+    type Out;
+
+    fn call(&mut self, param: P) -> Self::Out;
 }
 
 macro_rules! impl_system {
     ($($param:ident),*) => {
-        impl<'w, 's, W, F, $($param),*> SystemWithParams<'w, 's, W, ($($param,)*)> for F
+        impl<'w, 's, W, F, Out, $($param),*> SystemWithParams<'w, 's, W, ($($param,)*)> for F
         where
             W: World,
-            F: FnMut($($param),*),
+            F: FnMut($($param),*) -> Out,
         {
+            type Out = Out;
+
             #[inline(always)]
-            fn call(&mut self, params: ($($param,)*)) {
+            fn call(&mut self, params: ($($param,)*)) -> Self::Out {
                 #[allow(non_snake_case)]
                 let ($($param,)*) = params;
-                (self)($($param),*);
+                (self)($($param),*)
             }
         }
     };
 }
-all_tuples!(impl_system, 0, 16, P);
+all_system_params!(impl_system, P);
@@ -0,0 +1,309 @@
+use core::marker::PhantomData;
+
+use crate::{resource_check::Message, system::SystemBuilder, world::World};
+
+/// Maximum number of labels, `before` or `after` constraints of a system.
+pub const MAX_SYSTEM_LABELS: usize = 8;
+/// Maximum number of systems in a stage.
+pub const MAX_STAGE_SYSTEMS: usize = 128;
+
+/// Labels are used to order systems inside of a stage. `#[system]` creates a `Label` for each
+/// system.
+/// # Example
+/// ```
+/// use inception::*;
+/// pub struct Compute;
+/// impl SystemLabel for Compute {}
+/// #[system]
+/// fn a(mut value: Res<i32>) {
+///     **value += 1;
+/// }
+/// #[system]
+/// fn b(mut value: Res<i32>) {
+///     **value *= 2;
+/// }
+/// // `a` runs after `b`
+/// let _a = a::new().label(Compute).after(b::Label);
+/// ```
+pub trait SystemLabel: 'static {
+    const NAME: &'static str = core::any::type_name::<Self>();
+}
+
+/// Fixed size set of label names.
+#[derive(Clone, Copy, Debug)]
+pub struct Labels {
+    names: [&'static str; MAX_SYSTEM_LABELS],
+    len: usize,
+}
+
+impl Labels {
+    pub const EMPTY: Labels = Labels {
+        names: [""; MAX_SYSTEM_LABELS],
+        len: 0,
+    };
+
+    #[must_use]
+    pub const fn push(mut self, name: &'static str) -> Self {
+        if self.len == MAX_SYSTEM_LABELS {
+            panic!("Too many labels or ordering constraints for a system");
+        }
+        self.names[self.len] = name;
+        self.len += 1;
+        self
+    }
+
+    pub const fn contains(&self, name: &str) -> bool {
+        let mut i = 0;
+        while i < self.len {
+            if str_eq(self.names[i], name) {
+                return true;
+            }
+            i += 1;
+        }
+        false
+    }
+
+    pub const fn intersects(&self, other: &Labels) -> bool {
+        let mut i = 0;
+        while i < self.len {
+            if other.contains(self.names[i]) {
+                return true;
+            }
+            i += 1;
+        }
+        false
+    }
+}
+
+pub(crate) const fn str_eq(a: &str, b: &str) -> bool {
+    let a = a.as_bytes();
+    let b = b.as_bytes();
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Labels and ordering constraints of a system in a stage.
+#[derive(Clone, Copy, Debug)]
+pub struct SystemOrder {
+    pub name: &'static str,
+    pub labels: Labels,
+    pub before: Labels,
+    pub after: Labels,
+}
+
+impl SystemOrder {
+    pub const EMPTY: SystemOrder = SystemOrder {
+        name: "",
+        labels: Labels::EMPTY,
+        before: Labels::EMPTY,
+        after: Labels::EMPTY,
+    };
+
+    /// Whether `self` must run before `other`.
+    const fn precedes(&self, other: &SystemOrder) -> bool {
+        self.before.intersects(&other.labels) || other.after.intersects(&self.labels)
+    }
+}
+
+/// Panics if a `before`/`after` constraint of `system` references a label that no system in the
+/// stage has.
+const fn check_constraints(
+    system: &SystemOrder,
+    constraints: &Labels,
+    systems: &[SystemOrder; MAX_STAGE_SYSTEMS],
+    len: usize,
+) {
+    let mut i = 0;
+    while i < constraints.len {
+        let label = constraints.names[i];
+        let mut found = false;
+        let mut j = 0;
+        while j < len && !found {
+            found = systems[j].labels.contains(label);
+            j += 1;
+        }
+        if !found {
+            let message = Message::EMPTY
+                .push("System `")
+                .push(system.name)
+                .push("` is ordered relative to label `")
+                .push(label)
+                .push("` that no system in the stage has");
+            panic!("{}", message.as_str());
+        }
+        i += 1;
+    }
+}
+
+/// Sorts the first `len` systems topologically. Systems without constraints between them keep the
+/// order in which they were added. Constraints that reference labels outside of the stage fail to
+/// compile, systems in other stages are already ordered by the schedule.
+pub const fn topological_order(
+    systems: &[SystemOrder; MAX_STAGE_SYSTEMS],
+    len: usize,
+) -> [usize; MAX_STAGE_SYSTEMS] {
+    let mut i = 0;
+    while i < len {
+        check_constraints(&systems[i], &systems[i].before, systems, len);
+        check_constraints(&systems[i], &systems[i].after, systems, len);
+        i += 1;
+    }
+    // Number of systems that must run before a system.
+    let mut n_preceding = [0; MAX_STAGE_SYSTEMS];
+    let mut i = 0;
+    while i < len {
+        let mut j = 0;
+        while j < len {
+            if i != j && systems[j].precedes(&systems[i]) {
+                n_preceding[i] += 1;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    let mut order = [0; MAX_STAGE_SYSTEMS];
+    let mut done = [false; MAX_STAGE_SYSTEMS];
+    let mut n_done = 0;
+    while n_done < len {
+        let mut next = 0;
+        while next < len && (done[next] || n_preceding[next] != 0) {
+            next += 1;
+        }
+        if next == len {
+            panic!("Systems in a stage have cyclic `before`/`after` constraints");
+        }
+        done[next] = true;
+        order[n_done] = next;
+        n_done += 1;
+        let mut i = 0;
+        while i < len {
+            if !done[i] && systems[next].precedes(&systems[i]) {
+                n_preceding[i] -= 1;
+            }
+            i += 1;
+        }
+    }
+    order
+}
+
+macro_rules! def_order_builder {
+    ($name:ident, $doc:literal, $labels:expr, $before:expr, $after:expr) => {
+        #[doc = $doc]
+        pub struct $name<S, L> {
+            system: S,
+            _label: PhantomData<L>,
+        }
+
+        impl<S, L> $name<S, L> {
+            #[inline(always)]
+            pub(crate) fn new(system: S) -> Self {
+                Self {
+                    system,
+                    _label: PhantomData,
+                }
+            }
+        }
+
+        impl<'w, 's, S: SystemBuilder<'w, 's>, L: SystemLabel> SystemBuilder<'w, 's>
+            for $name<S, L>
+        {
+            type System<W: World, const N: usize> = S::System<W, N>;
+
+            const AFTER: Labels = $after;
+            const BEFORE: Labels = $before;
+            const LABELS: Labels = $labels;
+            const NAME: &'static str = S::NAME;
+
+            #[inline(always)]
+            fn build<W: World, const N: usize>(self, world: &mut W) -> Self::System<W, N> {
+                self.system.build(world)
+            }
+        }
+    };
+}
+
+def_order_builder!(
+    Labeled,
+    "Adds a label to a system, see `SystemBuilder::label`.",
+    { S::LABELS.push(L::NAME) },
+    { S::BEFORE },
+    { S::AFTER }
+);
+def_order_builder!(
+    Before,
+    "Runs a system before systems with a label, see `SystemBuilder::before`.",
+    { S::LABELS },
+    { S::BEFORE.push(L::NAME) },
+    { S::AFTER }
+);
+def_order_builder!(
+    After,
+    "Runs a system after systems with a label, see `SystemBuilder::after`.",
+    { S::LABELS },
+    { S::BEFORE },
+    { S::AFTER.push(L::NAME) }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn system(
+        name: &'static str,
+        label: &'static str,
+        before: Labels,
+        after: Labels,
+    ) -> SystemOrder {
+        SystemOrder {
+            name,
+            labels: Labels::EMPTY.push(label),
+            before,
+            after,
+        }
+    }
+
+    fn order(systems: &[SystemOrder]) -> [usize; MAX_STAGE_SYSTEMS] {
+        let mut all = [SystemOrder::EMPTY; MAX_STAGE_SYSTEMS];
+        all[..systems.len()].copy_from_slice(systems);
+        topological_order(&all, systems.len())
+    }
+
+    #[test]
+    fn constraints() {
+        let systems = [
+            system("a", "A", Labels::EMPTY, Labels::EMPTY.push("C")),
+            system("b", "B", Labels::EMPTY, Labels::EMPTY),
+            system("c", "C", Labels::EMPTY.push("B"), Labels::EMPTY),
+        ];
+        assert_eq!(order(&systems)[..3], [2, 0, 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "System `a` is ordered relative to label `D`")]
+    fn label_outside_of_stage() {
+        let systems = [
+            system("a", "A", Labels::EMPTY.push("D"), Labels::EMPTY),
+            system("b", "B", Labels::EMPTY, Labels::EMPTY),
+        ];
+        order(&systems);
+    }
+
+    #[test]
+    #[should_panic(expected = "cyclic")]
+    fn cycle() {
+        let systems = [
+            system("a", "A", Labels::EMPTY.push("B"), Labels::EMPTY),
+            system("b", "B", Labels::EMPTY.push("A"), Labels::EMPTY),
+        ];
+        order(&systems);
+    }
+}
@@ -1,9 +1,17 @@
 use core::marker::PhantomData;
 
-use all_tuples::{all_tuples, param_to_const_expr};
+use all_tuples::param_to_const_expr;
+use either::Either;
 use ergnomics::*;
+use inception_macros::{all_param_ids, all_system_params, max_system_params};
 
-use crate::{entities::EntityFetch, input::Input, world::World, EcsBuilder, Entity};
+use crate::{
+    entities::EntityFetch,
+    input::Input,
+    resource_check::{RequiredResource, RequiredResources},
+    world::World,
+    EcsBuilder, Entity,
+};
 
 /// Used in the `system` macro as a placeholder when the system is generic.
 pub struct UnknownSystem;
@@ -26,6 +34,9 @@ pub trait SystemParam: Sized {
     /// Used to cast lifetimes. This must be set to the Self but with the lifetimes and type of the
     /// world.
     type Item<'world, 'state, Wrld: World + 'static + 'world + 'state>;
+    /// Resources that the parameter accesses. A system fails to compile when it is built with a
+    /// world that doesn't contain them.
+    type Resources: RequiredResources = ();
     /// Determines wether to call `get_param_for_entity` or `get_param`. If it is a query, it will
     /// call `get_param_for_entity`.
     const IS_QUERY: bool = false;
@@ -133,33 +144,125 @@ impl<T: 'static> SystemParam for &mut T {
     }
 }
 
+/// Maximum number of parameters in a tuple of system parameters, see `ParamChunk`.
+pub const MAX_SYSTEM_PARAMS: usize = max_system_params!();
+
 pub trait Mapper<const N: usize> {
     type Name;
 }
 
-pub trait SystemParamNameMapper:
-    Mapper<0>
-    + Mapper<1>
-    + Mapper<2>
-    + Mapper<3>
-    + Mapper<4>
-    + Mapper<5>
-    + Mapper<6>
-    + Mapper<7>
-    + Mapper<8>
-    + Mapper<9>
-    + Mapper<10>
-    + Mapper<11>
-    + Mapper<12>
-    + Mapper<13>
-    + Mapper<14>
-    + Mapper<15>
-    + Mapper<15>
-    + 'static
-{
+macro_rules! def_mappers {
+    ($($param_id: literal),*) => {
+        /// Implemented for types that have a `Mapper` for each param of a system.
+        pub trait Mappers: $(Mapper<$param_id> +)* 'static {}
+
+        impl<T: $(Mapper<$param_id> +)* 'static> Mappers for T {}
+    };
+}
+
+all_param_ids!(def_mappers);
+
+pub trait SystemParamNameMapper: Mappers + Sized + 'static {
+    /// System that params are passed to. It differs from `Self` inside of a `ParamChunk`, whose
+    /// mapper only names the params of the chunk while configs stay keyed by the system.
+    type Root: SystemParamNameMapper = Self;
+    /// Whether the system runs for each entity. Params with per-entity state only add it to
+    /// entities when it's true. Defaults to `true` when it's not known.
+    const IS_QUERY: bool = true;
+
     fn get_param_name<const PARAM_ID: usize>() -> Option<&'static str>;
 }
 
+/// Params of a system with more than `MAX_SYSTEM_PARAMS` params are grouped into nested chunks by
+/// `#[system]`. `M` names the params of the chunk `T`.
+pub struct ParamChunk<T, M>(pub T, pub PhantomData<M>);
+
+pub struct ParamChunkState<T, M>(T, PhantomData<M>);
+
+/// Mapper of the params inside of a `ParamChunk` of system `SB`.
+pub struct ChunkMapper<SB, M>(PhantomData<(SB, M)>);
+
+impl<SB, M: Mapper<N>, const N: usize> Mapper<N> for ChunkMapper<SB, M> {
+    type Name = M::Name;
+}
+
+impl<SB, M> SystemParamNameMapper for ChunkMapper<SB, M>
+where
+    SB: SystemParamNameMapper,
+    M: SystemParamNameMapper,
+{
+    type Root = SB::Root;
+
+    const IS_QUERY: bool = SB::IS_QUERY;
+
+    #[inline(always)]
+    fn get_param_name<const PARAM_ID: usize>() -> Option<&'static str> {
+        M::get_param_name::<PARAM_ID>()
+    }
+}
+
+impl<T: SystemParamState, M: SystemParamNameMapper> SystemParamState for ParamChunkState<T, M> {
+    #[inline(always)]
+    fn init<W: World, SB: SystemParamNameMapper, ParamName: 'static, I: Input>(
+        inputs: &mut I,
+        world: &mut W,
+    ) -> Self {
+        Self(
+            T::init::<W, ChunkMapper<SB, M>, (), I>(inputs, world),
+            PhantomData,
+        )
+    }
+}
+
+impl<T: SystemParam, M: SystemParamNameMapper> SystemParam for ParamChunk<T, M> {
+    type Build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static> =
+        T::Build<B, ChunkMapper<SB, M>, ()>;
+    type Item<'world, 'state, Wrld: World> = ParamChunk<T::Item<'world, 'state, Wrld>, M>;
+    type Resources = T::Resources;
+    type State = ParamChunkState<T::State, M>;
+
+    const IS_QUERY: bool = T::IS_QUERY;
+
+    #[inline(always)]
+    fn get_param<'world, 'state, Wrld: World, SB: SystemParamNameMapper, ParamName: 'static>(
+        state: &'state mut Self::State,
+        world: &'world mut Wrld,
+    ) -> Self::Item<'world, 'state, Wrld> {
+        ParamChunk(
+            T::get_param::<_, ChunkMapper<SB, M>, ()>(&mut state.0, world),
+            PhantomData,
+        )
+    }
+
+    #[inline(always)]
+    fn get_param_for_entity<'world, 'state, Wrld, SB, ParamName: 'static, E>(
+        entity: &'world mut E,
+        state: &'state mut Self::State,
+        world: &'world mut Wrld,
+    ) -> Option<Self::Item<'world, 'state, Wrld>>
+    where
+        Wrld: World,
+        SB: SystemParamNameMapper,
+        E: EntityFetch,
+    {
+        Some(ParamChunk(
+            T::get_param_for_entity::<_, ChunkMapper<SB, M>, (), E>(entity, &mut state.0, world)?,
+            PhantomData,
+        ))
+    }
+
+    #[inline(always)]
+    fn build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static>(
+        builder: B,
+    ) -> Self::Build<B, SB, ParamName> {
+        T::build::<_, ChunkMapper<SB, M>, ()>(builder)
+    }
+}
+
+/// Name of the `I`th field of a `#[system_param]` struct that is passed to the param `P`, so
+/// that fields of the same type don't share their state or config.
+pub struct FieldName<P, const I: usize>(PhantomData<P>);
+
 pub struct PhantomSystemParam<'w, 's, const N: usize> {
     _w: PhantomData<&'w ()>,
     _s: PhantomData<&'s ()>,
@@ -187,6 +290,7 @@ pub struct Res<'w, 's, T, const N: usize> {
 impl<'w, 's, T: 'static, const N: usize> SystemParam for Res<'w, 's, T, N> {
     // cast lifetimes
     type Item<'world, 'state, Wrld: World> = Res<'world, 'state, T, N>;
+    type Resources = RequiredResource<T>;
     type State = ();
 
     type Build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static> =
@@ -211,6 +315,166 @@ impl<'w, 's, T: 'static, const N: usize> SystemParam for Res<'w, 's, T, N> {
     }
 }
 
+/// `None` if the resource wasn't added, so that a system can adapt to optional resources.
+impl<'w, 's, T: 'static, const N: usize> SystemParam for Option<Res<'w, 's, T, N>> {
+    // cast lifetimes
+    type Item<'world, 'state, Wrld: World> = Option<Res<'world, 'state, T, N>>;
+    type State = ();
+
+    impl_no_plugin!();
+
+    #[inline(always)]
+    fn get_param<'world, 'state, Wrld: World, SB: SystemParamNameMapper, ParamName>(
+        _state: &'state mut Self::State,
+        world: &'world mut Wrld,
+    ) -> Self::Item<'world, 'state, Wrld> {
+        world.get_resource_mut::<T>().map(|data| Res {
+            data,
+            _marker: Default::default(),
+        })
+    }
+}
+
+/// A system parameter that can be used to read a resource.
+#[derive(Deref)]
+pub struct ResRef<'w, 's, T, const N: usize> {
+    #[deref]
+    data: &'w T,
+    _marker: PhantomSystemParam<'w, 's, N>,
+}
+
+impl<'w, 's, T: 'static, const N: usize> SystemParam for ResRef<'w, 's, T, N> {
+    // cast lifetimes
+    type Item<'world, 'state, Wrld: World> = ResRef<'world, 'state, T, N>;
+    type Resources = RequiredResource<T>;
+    type State = ();
+
+    impl_no_plugin!();
+
+    #[inline(always)]
+    fn get_param<'world, 'state, Wrld: World, SB: SystemParamNameMapper, ParamName>(
+        _state: &'state mut Self::State,
+        world: &'world mut Wrld,
+    ) -> Self::Item<'world, 'state, Wrld> {
+        ResRef {
+            data: world.resource::<T>(),
+            _marker: Default::default(),
+        }
+    }
+}
+
+/// `None` if the resource wasn't added, so that a system can adapt to optional resources.
+impl<'w, 's, T: 'static, const N: usize> SystemParam for Option<ResRef<'w, 's, T, N>> {
+    // cast lifetimes
+    type Item<'world, 'state, Wrld: World> = Option<ResRef<'world, 'state, T, N>>;
+    type State = ();
+
+    impl_no_plugin!();
+
+    #[inline(always)]
+    fn get_param<'world, 'state, Wrld: World, SB: SystemParamNameMapper, ParamName>(
+        _state: &'state mut Self::State,
+        world: &'world mut Wrld,
+    ) -> Self::Item<'world, 'state, Wrld> {
+        world.get_resource::<T>().map(|data| ResRef {
+            data,
+            _marker: Default::default(),
+        })
+    }
+}
+
+/// Private state of a system that persists between its runs. It starts as `T::default()`. If the
+/// system runs for each entity then each entity gets its own copy of the initial state, otherwise
+/// it's stored in the system and entities don't get it.
+/// # Example
+/// ```
+/// use inception::*;
+/// #[system]
+/// fn count_bars(mut bars: Local<u32>, value: &f32) {
+///     if *value > 0. {
+///         **bars += 1;
+///     }
+/// }
+/// ```
+#[derive(Deref, DerefMut)]
+pub struct Local<'w, 's, T, const N: usize> {
+    #[deref]
+    #[deref_mut]
+    data: &'w mut T,
+    _marker: PhantomSystemParam<'w, 's, N>,
+}
+
+/// State of `Local`, it is stored in a system or in a config of each entity.
+#[derive(Clone, Default)]
+pub struct LocalState<T>(T);
+
+impl<T: Default> LocalState<T> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self(T::default())
+    }
+}
+
+impl<T: Default + 'static> SystemParamState for LocalState<T> {
+    #[inline(always)]
+    fn init<W: World, SB: SystemParamNameMapper, ParamName: 'static, I: Input>(
+        _inputs: &mut I,
+        _world: &mut W,
+    ) -> Self {
+        Self::new()
+    }
+}
+
+impl<'w, 's, T: Default + Clone + 'static, const N: usize> SystemParam for Local<'w, 's, T, N> {
+    // cast lifetimes
+    type Item<'world, 'state, Wrld: World> = Local<'world, 'state, T, N>;
+    type State = LocalState<T>;
+
+    type Build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static> =
+        impl EcsBuilder;
+
+    #[inline(always)]
+    fn get_param<'world, 'state, Wrld: World, SB: SystemParamNameMapper, ParamName>(
+        state: &'state mut Self::State,
+        _world: &'world mut Wrld,
+    ) -> Self::Item<'world, 'state, Wrld> {
+        Local {
+            // SAFETY: the state outlives the call of a system.
+            data: unsafe { &mut *(&mut state.0 as *mut T) },
+            _marker: Default::default(),
+        }
+    }
+
+    #[inline(always)]
+    fn get_param_for_entity<'world, 'state, Wrld, SB, ParamName, E>(
+        entity: &'world mut E,
+        _state: &'state mut Self::State,
+        _world: &'world mut Wrld,
+    ) -> Option<Self::Item<'world, 'state, Wrld>>
+    where
+        Wrld: World,
+        SB: SystemParamNameMapper,
+        E: EntityFetch,
+        ParamName: 'static,
+    {
+        Some(Local {
+            data: &mut entity.config_mut::<SB, ParamName, LocalState<T>>().0,
+            _marker: Default::default(),
+        })
+    }
+
+    #[inline(always)]
+    fn build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static>(
+        builder: B,
+    ) -> Self::Build<B, SB, ParamName> {
+        if SB::IS_QUERY {
+            Either::Right(builder.extend_generic_config::<SB, ParamName, _>(LocalState::<T>::new()))
+        } else {
+            Either::Left(builder)
+        }
+    }
+}
+
 /// Gets entity id from the current entity.
 #[derive(Deref, DerefMut)]
 pub struct EntityParam<'w, 's, const N: usize> {
@@ -255,12 +519,20 @@ impl<'w, 's, const N: usize> SystemParam for EntityParam<'w, 's, N> {
     }
 }
 
+macro_rules! required_resources {
+    () => { () };
+    ($param:ident $(, $rest:ident)*) => {
+        (<$param as SystemParam>::Resources, required_resources!($($rest),*))
+    };
+}
+
 macro_rules! impl_system_param {
     ($($param:ident),*) => {
         impl<$($param: SystemParam),*> SystemParam for ($($param,)*)
         {
             type State = ($($param::State,)*);
             type Item<'w, 's, Wrld: World> = ($($param::Item<'w, 's, Wrld>,)*);
+            type Resources = required_resources!($($param),*);
             type Build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static> = impl EcsBuilder;
             const IS_QUERY: bool = $($param::IS_QUERY ||)* false;
 
@@ -278,7 +550,7 @@ macro_rules! impl_system_param {
                     ($({
                         #![allow(unused_assignments)]
                         let world = &mut *world;
-                        let param = $param::get_param::<_, SB, <SB as Mapper<{param_to_const_expr!($param)}>>::Name>($param, world);
+                        let param = $param::get_param::<_, SB::Root, <SB as Mapper<{param_to_const_expr!($param)}>>::Name>($param, world);
                         param
                     },)*)
                 }
@@ -300,7 +572,7 @@ macro_rules! impl_system_param {
                     Some(($({
                         #![allow(unused_assignments)]
                         let world = &mut *world;
-                        let param = $param::get_param_for_entity::<_, SB, <SB as Mapper<{param_to_const_expr!($param)}>>::Name, E>(
+                        let param = $param::get_param_for_entity::<_, SB::Root, <SB as Mapper<{param_to_const_expr!($param)}>>::Name, E>(
                             &mut *entity,
                             $param,
                             world
@@ -317,14 +589,14 @@ macro_rules! impl_system_param {
             ) -> Self::Build<B, SB, ParamName> {
                 let mut builder = builder;
                 $(
-                    let mut builder = $param::build::<_, SB, <SB as Mapper<{param_to_const_expr!($param)}>>::Name>(builder);
+                    let mut builder = $param::build::<_, SB::Root, <SB as Mapper<{param_to_const_expr!($param)}>>::Name>(builder);
                 )*
                 builder
             }
         }
     };
 }
-all_tuples!(impl_system_param, 0, 16, P);
+all_system_params!(impl_system_param, P);
 
 macro_rules! impl_system_param_state {
     ($($param:ident),*) => {
@@ -340,7 +612,7 @@ macro_rules! impl_system_param_state {
                     unsafe {
                         let state = $param::init::<
                             W,
-                            SB,
+                            SB::Root,
                             <SB as Mapper<{param_to_const_expr!($param)}>>::Name,
                             I,
                         >(inputs, &mut *world);
@@ -351,4 +623,181 @@ macro_rules! impl_system_param_state {
         }
     };
 }
-all_tuples!(impl_system_param_state, 0, 16, P);
+all_system_params!(impl_system_param_state, P);
+
+#[cfg(test)]
+mod tests {
+    use std::prelude::v1::*;
+
+    use crate::{resources::Break, *};
+
+    schedule! {
+        struct Schedule,
+        loop {
+            Update,
+        },
+    }
+
+    #[derive(Default)]
+    pub struct Runs {
+        system: u32,
+        pair: (u32, u32),
+    }
+
+    #[system_param]
+    pub struct Pair {
+        first: Local<u32>,
+        second: Local<u32>,
+    }
+
+    #[system]
+    fn count_runs(mut runs: Local<u32>, mut total: Res<Runs>, mut stop: Res<Break>) {
+        **runs += 1;
+        total.system = **runs;
+        **stop = Break(**runs == 3);
+    }
+
+    #[system]
+    fn count_entity_runs(mut runs: Local<u32>, value: &mut u32) {
+        **runs += 1;
+        *value = **runs;
+    }
+
+    #[system]
+    fn count_pair(mut pair: Pair, mut total: Res<Runs>) {
+        **pair.first += 1;
+        **pair.second += 2;
+        total.pair = (**pair.first, **pair.second);
+    }
+
+    // More params than fit into a tuple, so they are split into chunks. `first` and `second` are
+    // the first params of their chunks.
+    #[system]
+    fn chunked_params(
+        mut first: Local<u32>,
+        i1: In<u32>,
+        i2: In<u32>,
+        i3: In<u32>,
+        i4: In<u32>,
+        i5: In<u32>,
+        i6: In<u32>,
+        i7: In<u32>,
+        i8: In<u32>,
+        i9: In<u32>,
+        i10: In<u32>,
+        i11: In<u32>,
+        i12: In<u32>,
+        i13: In<u32>,
+        i14: In<u32>,
+        i15: In<u32>,
+        mut second: Local<u32>,
+        step: In<u32>,
+        values: &mut [u32; 18],
+    ) {
+        **first += 1;
+        **second += *step;
+        let inputs = [
+            i1, i2, i3, i4, i5, i6, i7, i8, i9, i10, i11, i12, i13, i14, i15,
+        ];
+        for (i, input) in inputs.iter().enumerate() {
+            values[i + 1] = **input;
+        }
+        values[0] = **first;
+        values[16] = **second;
+        values[17] = *step;
+    }
+
+    pub struct Missing;
+
+    #[derive(Default)]
+    pub struct Found {
+        missing: Option<bool>,
+        missing_ref: Option<bool>,
+        present_ref: Option<u32>,
+        runs: u32,
+    }
+
+    #[system]
+    fn find_resources(
+        missing: Option<Res<Missing>>,
+        missing_ref: Option<ResRef<Missing>>,
+        present_ref: Option<ResRef<u32>>,
+        runs: ResRef<u32>,
+        mut found: Res<Found>,
+        mut stop: Res<Break>,
+    ) {
+        **stop = Break(true);
+        found.missing = Some(missing.is_some());
+        found.missing_ref = Some(missing_ref.is_some());
+        found.present_ref = present_ref.map(|x| **x);
+        found.runs = **runs;
+    }
+
+    #[test]
+    fn optional_resources() {
+        let mut ecs =
+            EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct1::new())
+                .add_plugin(CorePlugin)
+                .add_resource(Found::default())
+                .add_resource(7u32)
+                .add_system(find_resources::new(), Update::new())
+                .build();
+        ecs.run();
+        let found = ecs.resource::<Found>();
+        assert_eq!(found.missing, Some(false));
+        assert_eq!(found.missing_ref, Some(false));
+        assert_eq!(found.present_ref, Some(7));
+        assert_eq!(found.runs, 7);
+    }
+
+    #[test]
+    fn local() {
+        let mut ecs =
+            EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct2::new())
+                .add_plugin(CorePlugin)
+                .add_resource(Runs::default())
+                .extend_entities(0u32)
+                .add_system(count_runs::new(), Update::new())
+                .add_system(count_entity_runs::new(), Update::new())
+                .add_system(count_pair::new(), Update::new())
+                .build();
+        ecs.run();
+        let runs = ecs.resource::<Runs>();
+        assert_eq!(runs.system, 3);
+        assert_eq!(runs.pair, (3, 6));
+        assert_eq!(*ecs.component::<u32>(Entity(0)), 3);
+        assert_eq!(*ecs.component::<u32>(Entity(1)), 3);
+        // Only the per-entity system adds its state to entities.
+        let description = ecs.describe();
+        for entity in description.entities {
+            let locals = entity
+                .components
+                .iter()
+                .filter(|x| x.contains("LocalState"));
+            assert_eq!(locals.count(), 1);
+        }
+    }
+    #[test]
+    fn chunked_params() {
+        let mut ecs =
+            EcsBuilderStruct::new::<_, 0>(Schedule::builder(), EntitiesBuilderStruct1::new())
+                .add_plugin(CorePlugin)
+                .add_resource(Runs::default())
+                .extend_entities([0u32; 18])
+                .add_system(count_runs::new(), Update::new())
+                .add_system(
+                    chunked_params::new(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 2),
+                    Update::new(),
+                )
+                .build();
+        ecs.run();
+        // Inputs and per-entity state are looked up by the names of the params in each chunk.
+        let values = ecs.component::<[u32; 18]>(Entity(0));
+        assert_eq!(values[0], 3);
+        for (i, value) in values[1..16].iter().enumerate() {
+            assert_eq!(*value, i as u32 + 1);
+        }
+        assert_eq!(values[16], 6);
+        assert_eq!(values[17], 2);
+    }
+}
@@ -5,11 +5,15 @@ use either::Either;
 use ergnomics::*;
 
 use crate::{
+    change_detection::Tick,
     config::ConfigValue,
+    describe::EcsVisitor,
     entities::{
         DefaultEntitiesBuilder1, Entities, EntitiesBuilder, EntityFnMut, EntityRelay, WorldQuery,
     },
-    nest_module::{Nest, Nested, StackedNest},
+    nest_module::{Nest, NestTypes, Nested, StackedNest},
+    plugin::LoadedOnce,
+    resources::ChangeTick,
     Entity, ParamLabel,
 };
 
@@ -22,6 +26,9 @@ pub trait World: Sized + 'static {
     type InitResource<T: 'static>: World;
     type AddComponent<C: 'static, ER: EntityRelay>: World;
     type ExtendEntities<C: Clone + 'static>: World;
+    /// Resources that were added to the world.
+    type Resources: NestTypes;
+    type LoadedOnce<Plug: 'static, L: NestTypes>: World;
 
     #[must_use]
     fn add_component<C: 'static, ER: EntityRelay>(
@@ -67,8 +74,15 @@ pub trait World: Sized + 'static {
     fn resource_mut<T: 'static>(&mut self) -> &mut T;
     #[must_use]
     fn init_resource<T: FromWorld>(self) -> Self::InitResource<T>;
+    /// Current tick of `ChangeTick` resource or tick 0 if it wasn't added.
+    #[must_use]
+    fn change_tick(&self) -> Tick;
     #[must_use]
     fn add_resource<T: 'static>(self, resource: T) -> Self::AddResource<T>;
+    /// Used by `PluginLoader::load_once` to mark the branch that is taken when `Plug` is in plugins
+    /// `L`, see `LoadedOnce`.
+    #[must_use]
+    fn __loaded_once<Plug: 'static, L: NestTypes>(self) -> Self::LoadedOnce<Plug, L>;
 
     #[must_use]
     fn get_component<T: 'static>(&self, entity: Entity) -> Option<&T>;
@@ -93,6 +107,8 @@ pub trait World: Sized + 'static {
     where
         F: FnMut(<Q as WorldQuery>::Item<'w>),
         Q: WorldQuery;
+    /// Visits resources and entities with their components.
+    fn describe<V: EcsVisitor>(&self, visitor: &mut V);
 }
 
 pub trait FromWorld: 'static {
@@ -115,7 +131,7 @@ pub struct WorldStruct<R, E> {
 
 impl<R, Ents> World for WorldStruct<R, Ents>
 where
-    R: Nest + 'static,
+    R: Nest + NestTypes,
     Ents: Entities + EntitiesBuilder + 'static,
 {
     type AddComponent<Comp: 'static, ER: EntityRelay> = WorldStruct<R, Ents::Add<Comp, ER>>;
@@ -127,6 +143,8 @@ where
     type ExtendEntities<Comp: Clone + 'static> = WorldStruct<R, Ents::ExtendEntities<Comp>>;
     type ExtendGenericConfig<System: 'static, Param: 'static, T: Clone + 'static> =
         Self::ExtendEntities<ConfigValue<System, Param, T>>;
+    type LoadedOnce<Plug: 'static, L: NestTypes> = WorldStruct<LoadedOnce<R, Plug, L>, Ents>;
+    type Resources = R;
 
     type ExtendConfig<Param: ParamLabel, T: Clone + 'static> = impl World;
     type InitResource<T: 'static> = impl World;
@@ -191,30 +209,27 @@ where
 
     #[inline(always)]
     fn config<System: 'static, Param: 'static, Config: 'static>(&self) -> &Config {
-        &self
-            .resources
-            .get::<ConfigValue<System, Param, Config>>()
-            .expect_or_else(|| {
-                format!(
-                    "Config resource `{}` not found",
-                    ConfigValue::<System, Param, Config>::type_name()
-                )
-            })
-            .0
+        match self.resources.get::<ConfigValue<System, Param, Config>>() {
+            Some(x) => &x.0,
+            None => panic!(
+                "Config resource `{}` not found",
+                ConfigValue::<System, Param, Config>::type_name()
+            ),
+        }
     }
 
     #[inline(always)]
     fn config_mut<System: 'static, Param: 'static, Config: 'static>(&mut self) -> &mut Config {
-        &mut self
+        match self
             .resources
             .get_mut::<ConfigValue<System, Param, Config>>()
-            .expect_or_else(|| {
-                format!(
-                    "Config resource `{}` not found",
-                    ConfigValue::<System, Param, Config>::type_name()
-                )
-            })
-            .0
+        {
+            Some(x) => &mut x.0,
+            None => panic!(
+                "Config resource `{}` not found",
+                ConfigValue::<System, Param, Config>::type_name()
+            ),
+        }
     }
 
     #[inline(always)]
@@ -224,9 +239,10 @@ where
 
     #[inline(always)]
     fn resource<T: 'static>(&self) -> &T {
-        self.resources
-            .get::<T>()
-            .expect_or_else(|| format!("Resource `{}` not found", T::type_name()))
+        match self.resources.get::<T>() {
+            Some(x) => x,
+            None => panic!("Resource `{}` not found", T::type_name()),
+        }
     }
 
     #[inline(always)]
@@ -236,9 +252,10 @@ where
 
     #[inline(always)]
     fn resource_mut<T: 'static>(&mut self) -> &mut T {
-        self.resources
-            .get_mut::<T>()
-            .expect_or_else(|| format!("Resource `{}` not found", T::type_name()))
+        match self.resources.get_mut::<T>() {
+            Some(x) => x,
+            None => panic!("Resource `{}` not found", T::type_name()),
+        }
     }
 
     #[inline(always)]
@@ -250,6 +267,14 @@ where
         }
     }
 
+    #[inline(always)]
+    fn change_tick(&self) -> Tick {
+        self.resources
+            .get::<ChangeTick>()
+            .map(|x| x.0)
+            .unwrap_or_default()
+    }
+
     #[inline(always)]
     fn add_resource<T: 'static>(self, resource: T) -> Self::AddResource<T> {
         WorldStruct {
@@ -258,6 +283,14 @@ where
         }
     }
 
+    #[inline(always)]
+    fn __loaded_once<Plug: 'static, L: NestTypes>(self) -> Self::LoadedOnce<Plug, L> {
+        WorldStruct {
+            resources: LoadedOnce::new(self.resources),
+            entities: self.entities,
+        }
+    }
+
     #[inline(always)]
     fn get_component<T: 'static>(&self, entity: Entity) -> Option<&T> {
         self.entities.get_component::<T>(entity)
@@ -265,13 +298,14 @@ where
 
     #[inline(always)]
     fn component<T: 'static>(&self, entity: Entity) -> &T {
-        self.entities.get_component::<T>(entity).expect_or_else(|| {
-            format!(
+        match self.entities.get_component::<T>(entity) {
+            Some(x) => x,
+            None => panic!(
                 "Component `{}` not found in entity `{}`",
                 T::type_name(),
                 entity.0
-            )
-        })
+            ),
+        }
     }
 
     #[inline(always)]
@@ -281,25 +315,26 @@ where
 
     #[inline(always)]
     fn component_mut<T: 'static>(&mut self, entity: Entity) -> &mut T {
-        self.entities
-            .get_component_mut::<T>(entity)
-            .expect_or_else(|| {
-                format!(
-                    "Component `{}` not found in entity `{}`",
-                    T::type_name(),
-                    entity.0
-                )
-            })
+        match self.entities.get_component_mut::<T>(entity) {
+            Some(x) => x,
+            None => panic!(
+                "Component `{}` not found in entity `{}`",
+                T::type_name(),
+                entity.0
+            ),
+        }
     }
 
     #[inline(always)]
     fn get_components<'w, Q: WorldQuery>(&'w mut self, entity: Entity) -> Option<Q::Item<'w>> {
-        self.entities.get_components::<Q>(entity)
+        let tick = self.change_tick();
+        self.entities.get_components::<Q>(entity, tick)
     }
 
     #[inline(always)]
     fn components<'w, Q: WorldQuery>(&'w mut self, entity: Entity) -> Q::Item<'w> {
-        self.entities.components::<Q>(entity)
+        let tick = self.change_tick();
+        self.entities.components::<Q>(entity, tick)
     }
 
     #[inline(always)]
@@ -316,7 +351,8 @@ where
         F: FnMut(<Q as WorldQuery>::Item<'w>),
         Q: WorldQuery,
     {
-        self.entities.query::<F, Q>(f);
+        let tick = self.change_tick();
+        self.entities.query::<F, Q>(tick, f);
     }
 
     #[inline(always)]
@@ -325,6 +361,14 @@ where
         F: FnMut(<Q as WorldQuery>::Item<'w>),
         Q: WorldQuery,
     {
-        self.entities.query_entity::<F, Q>(entity, f);
+        let tick = self.change_tick();
+        self.entities.query_entity::<F, Q>(entity, tick, f);
+    }
+
+    #[inline(always)]
+    fn describe<V: EcsVisitor>(&self, visitor: &mut V) {
+        self.resources
+            .for_each_type_name(&mut |x| visitor.visit_resource(x));
+        self.entities.describe(visitor);
     }
 }
@@ -113,13 +113,13 @@ pub fn entities_builder(input: TokenStream) -> TokenStream {
     let query_impl = (0..n_impls).map(|x| {
         let field = format_ident!("e{}", x);
         quote! {
-            self.#field.query::<F, Q>(&mut f);
+            self.#field.query::<F, Q>(tick, &mut f);
         }
     });
     let query_entity_impl = (0..n_impls).map(|x| {
         let field = format_ident!("e{}", x);
         quote! {
-            self.#field.query_entity::<F, Q>(entity, &mut f);
+            self.#field.query_entity::<F, Q>(entity, tick, &mut f);
         }
     });
     let for_each_impl = (0..n_impls).map(|x| {
@@ -132,7 +132,7 @@ pub fn entities_builder(input: TokenStream) -> TokenStream {
         let field = format_ident!("e{}", x);
         quote! {
             if entity == self.#field.entity {
-                return Q::get_component(&self.#field.components);
+                return Q::get_component(&self.#field.components, tick);
             }
         }
     });
@@ -144,6 +144,12 @@ pub fn entities_builder(input: TokenStream) -> TokenStream {
             }
         }
     });
+    let describe_impl = (0..n_impls).map(|x| {
+        let field = format_ident!("e{}", x);
+        quote! {
+            self.#field.describe(visitor);
+        }
+    });
     let get_component_mut_impl = (0..n_impls).map(|x| {
         let field = format_ident!("e{}", x);
         quote! {
@@ -187,7 +193,7 @@ pub fn entities_builder(input: TokenStream) -> TokenStream {
 
         impl<#(#bounded_generics),*> Entities for #struct_ident<#(#generics),*> {
             #[inline(always)]
-            fn query<'w, F, Q>(&'w mut self, mut f: F)
+            fn query<'w, F, Q>(&'w mut self, tick: Tick, mut f: F)
             where
                 F: FnMut(<Q as WorldQuery>::Item<'w>),
                 Q: WorldQuery,
@@ -198,7 +204,7 @@ pub fn entities_builder(input: TokenStream) -> TokenStream {
             }
 
             #[inline(always)]
-            fn query_entity<'w, F, Q>(&'w mut self, entity: Entity, mut f: F)
+            fn query_entity<'w, F, Q>(&'w mut self, entity: Entity, tick: Tick, mut f: F)
             where
                 F: FnMut(<Q as WorldQuery>::Item<'w>),
                 Q: WorldQuery,
@@ -215,7 +221,11 @@ pub fn entities_builder(input: TokenStream) -> TokenStream {
             }
 
             #[inline(always)]
-            fn get_components<'w, Q: WorldQuery>(&'w mut self, entity: Entity) -> Option<Q::Item<'w>> {
+            fn get_components<'w, Q: WorldQuery>(
+                &'w mut self,
+                entity: Entity,
+                tick: Tick,
+            ) -> Option<Q::Item<'w>> {
                 unsafe {
                     #(#get_components_impl)*
                     None
@@ -223,8 +233,8 @@ pub fn entities_builder(input: TokenStream) -> TokenStream {
             }
 
             #[inline(always)]
-            fn components<'w, Q: WorldQuery>(&'w mut self, entity: Entity) -> Q::Item<'w> {
-                expect_components::<Q, _>(entity, self.get_components::<Q>(entity))
+            fn components<'w, Q: WorldQuery>(&'w mut self, entity: Entity, tick: Tick) -> Q::Item<'w> {
+                expect_components::<Q, _>(entity, self.get_components::<Q>(entity, tick))
             }
 
             #[inline(always)]
@@ -238,6 +248,11 @@ pub fn entities_builder(input: TokenStream) -> TokenStream {
                 #(#get_component_mut_impl)*
                 None
             }
+
+            #[inline(always)]
+            fn describe<V: EcsVisitor>(&self, visitor: &mut V) {
+                #(#describe_impl)*
+            }
         }
     };
 
@@ -1,7 +1,12 @@
+use inception_macros_core::MAX_SYSTEM_PARAMS;
 use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
 use proc_macro_error::proc_macro_error;
 use quote::{format_ident, quote};
-use syn::{parse_macro_input, DeriveInput, LitInt};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input, DeriveInput, Ident, LitInt, Path, Token,
+};
 
 mod nest_macro;
 mod schedule_macro;
@@ -21,6 +26,8 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
 }
 
 #[proc_macro_attribute]
+/// Makes a struct of system params a system param. Each field is passed its own param name
+/// `FieldName<ParamName, I>`, so fields keep separate state and configs.
 pub fn system_param(attributes: TokenStream, item: TokenStream) -> TokenStream {
     system_param_macro::system_param(attributes, item)
 }
@@ -37,6 +44,28 @@ pub fn nest(input: TokenStream) -> TokenStream {
     nest_macro::nest(input)
 }
 
+/// Defines a schedule with named stages. A stage label is generated for each stage except for
+/// `Last` which refers to `inception::stages::Last`. Stages inside of `loop` run until
+/// `inception::resources::Break` is set, `loop(T)` uses resource `T` instead. `if T`, `if !T` and
+/// `else` branch on a resource. Resources used by loops and branches implement
+/// `inception::resources::ScheduleFlag`.
+/// # Example
+/// ```ignore
+/// schedule! {
+///     struct Schedule,
+///     Init,
+///     loop(NextPass) {
+///         loop {
+///             Update,
+///             Last,
+///         },
+///         if Report {
+///             PrintReport,
+///         },
+///     },
+///     End,
+/// }
+/// ```
 #[proc_macro]
 pub fn schedule(input: TokenStream) -> TokenStream {
     schedule_macro::schedule(input)
@@ -66,6 +95,73 @@ pub fn system_param_plugin_derive(input: TokenStream) -> TokenStream {
     out.into()
 }
 
+/// Expands into the value of `inception_macros_core::MAX_SYSTEM_PARAMS` as a literal.
+#[proc_macro]
+pub fn max_system_params(_input: TokenStream) -> TokenStream {
+    let max = LitInt::new(&MAX_SYSTEM_PARAMS.to_string(), Span::call_site());
+    quote! { #max }.into()
+}
+
+/// Like `all_tuples!(macro, 0, MAX_SYSTEM_PARAMS, P)`, calls `macro` with each number of system
+/// params.
+#[proc_macro]
+pub fn all_system_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as AllSystemParams);
+    let macro_ident = &input.macro_ident;
+    let ident = &input.ident;
+    let invocations = (0..=MAX_SYSTEM_PARAMS).map(|n| {
+        let params = (0..n).map(|i| format_ident!("{}{}", ident, i));
+        quote! {
+            #macro_ident! { #(#params),* }
+        }
+    });
+    quote! { #(#invocations)* }.into()
+}
+
+/// Calls `macro` with all param ids `0, 1, .., MAX_SYSTEM_PARAMS - 1` followed by the rest of the
+/// input after `;`.
+#[proc_macro]
+pub fn all_param_ids(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as AllParamIds);
+    let macro_ident = &input.macro_ident;
+    let rest = &input.rest;
+    let ids = (0..MAX_SYSTEM_PARAMS).map(|i| LitInt::new(&i.to_string(), Span::call_site()));
+    quote! {
+        #macro_ident! { #(#ids),* #rest }
+    }
+    .into()
+}
+
+struct AllSystemParams {
+    macro_ident: Path,
+    ident: Ident,
+}
+
+impl Parse for AllSystemParams {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let macro_ident = input.parse()?;
+        input.parse::<Token![,]>()?;
+        Ok(Self {
+            macro_ident,
+            ident: input.parse()?,
+        })
+    }
+}
+
+struct AllParamIds {
+    macro_ident: Path,
+    rest: TokenStream2,
+}
+
+impl Parse for AllParamIds {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(Self {
+            macro_ident: input.parse()?,
+            rest: input.parse()?,
+        })
+    }
+}
+
 #[proc_macro]
 pub fn impl_system_param_plugin(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as LitInt);
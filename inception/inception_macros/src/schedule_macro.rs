@@ -1,5 +1,3 @@
-use core::str::FromStr;
-
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::{format_ident, quote};
@@ -9,24 +7,19 @@ struct Schedule {
     items: Punctuated<ScheduleItem, Token![,]>,
 }
 
-struct StageItem {
-    stage: syn::Ident,
-    alias: syn::Ident,
-}
-
-impl Parse for StageItem {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let stage = input.parse()?;
-        let _ = <Token![as]>::parse(input)?;
-        let alias = input.parse()?;
-        Ok(StageItem { stage, alias })
-    }
-}
-
 enum ScheduleItem {
     ScheduleName(syn::Ident),
-    Stage(StageItem),
-    Looped(Schedule),
+    Stage(syn::Ident),
+    Looped {
+        flag: Option<Type>,
+        schedule: Schedule,
+    },
+    Branch {
+        negate: bool,
+        flag: Type,
+        then: Schedule,
+        otherwise: Option<Schedule>,
+    },
 }
 
 impl Parse for Schedule {
@@ -39,11 +32,44 @@ impl Parse for Schedule {
 impl Parse for ScheduleItem {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         if input.peek(Token![loop]) {
-            let content;
             let _ = <Token![loop]>::parse(input)?;
+            let flag = if input.peek(token::Paren) {
+                let content;
+                let _ = parenthesized!(content in input);
+                Some(content.parse()?)
+            } else {
+                None
+            };
+            let content;
             let _ = braced!(content in input);
             let schedule = content.parse()?;
-            Ok(ScheduleItem::Looped(schedule))
+            Ok(ScheduleItem::Looped { flag, schedule })
+        } else if input.peek(Token![if]) {
+            let _ = <Token![if]>::parse(input)?;
+            let negate = input.parse::<Option<Token![!]>>()?.is_some();
+            let flag = input.parse()?;
+            let content;
+            let _ = braced!(content in input);
+            let then = content.parse()?;
+            let otherwise = if input.parse::<Option<Token![else]>>()?.is_some() {
+                if input.peek(Token![if]) {
+                    let mut items = Punctuated::new();
+                    items.push(input.parse()?);
+                    Some(Schedule { items })
+                } else {
+                    let content;
+                    let _ = braced!(content in input);
+                    Some(content.parse()?)
+                }
+            } else {
+                None
+            };
+            Ok(ScheduleItem::Branch {
+                negate,
+                flag,
+                then,
+                otherwise,
+            })
         } else if input.peek(Token![struct]) {
             let _ = <Token![struct]>::parse(input)?;
             let ident = input.parse()?;
@@ -56,33 +82,95 @@ impl Parse for ScheduleItem {
 }
 
 struct Stage {
-    id: usize,
     stage: syn::Ident,
-    generic_type: syn::Ident,
-    alias: syn::Ident,
+    index: proc_macro2::TokenStream,
+}
+
+fn is_last(stage: &syn::Ident) -> bool {
+    stage == "Last"
+}
+
+/// `inception::stages::Last` is always stored at the end of a stage list, other stages are stored
+/// in the order they are declared.
+fn stage_index(
+    inception: &proc_macro2::TokenStream,
+    stage: &syn::Ident,
+    i: usize,
+) -> proc_macro2::TokenStream {
+    if is_last(stage) {
+        return quote! {#inception::LastStage};
+    }
+    let mut index = quote! {#inception::FirstStage};
+    for _ in 0..i {
+        index = quote! {#inception::NextStage<#index>};
+    }
+    index
 }
 
-fn get_stages(schedule: &Schedule, schedule_name: &mut syn::Ident, stages: &mut Vec<Stage>) {
+fn get_stages(
+    inception: &proc_macro2::TokenStream,
+    schedule: &Schedule,
+    schedule_name: &mut syn::Ident,
+    stages: &mut Vec<Stage>,
+) {
     for item in &schedule.items {
         match item {
             ScheduleItem::Stage(x) => {
-                let name = x.stage.to_string();
-                let id = usize::from_str(&name[5..]).expect(
-                    "The first item of a stage must start with concrete type like `Stage0`.",
-                );
+                let i = stages.iter().filter(|x| !is_last(&x.stage)).count();
                 stages.push(Stage {
-                    id,
-                    stage: x.stage.clone(),
-                    generic_type: format_ident!("S{}", id),
-                    alias: x.alias.clone(),
+                    stage: x.clone(),
+                    index: stage_index(inception, x, i),
                 });
             },
-            ScheduleItem::Looped(x) => get_stages(x, schedule_name, stages),
+            ScheduleItem::Looped { schedule, .. } => {
+                get_stages(inception, schedule, schedule_name, stages)
+            },
+            ScheduleItem::Branch {
+                then, otherwise, ..
+            } => {
+                get_stages(inception, then, schedule_name, stages);
+                if let Some(otherwise) = otherwise {
+                    get_stages(inception, otherwise, schedule_name, stages);
+                }
+            },
             ScheduleItem::ScheduleName(name) => *schedule_name = name.clone(),
         }
     }
 }
 
+/// Resources that are used by loops and branches of a schedule as `RequiredResources`.
+fn get_flags(
+    inception: &proc_macro2::TokenStream,
+    schedule: &Schedule,
+    flags: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let required = |flag| quote! {#inception::RequiredResource<#flag>};
+    schedule.items.iter().fold(flags, |flags, item| match item {
+        ScheduleItem::ScheduleName(_) | ScheduleItem::Stage(_) => flags,
+        ScheduleItem::Looped { flag, schedule } => {
+            let flag = match flag {
+                Some(flag) => required(quote! {#flag}),
+                None => required(quote! {#inception::resources::Break}),
+            };
+            let tick = required(quote! {#inception::resources::ChangeTick});
+            get_flags(inception, schedule, quote! {((#flag, #tick), #flags)})
+        },
+        ScheduleItem::Branch {
+            flag,
+            then,
+            otherwise,
+            ..
+        } => {
+            let flag = required(quote! {#flag});
+            let flags = get_flags(inception, then, quote! {(#flag, #flags)});
+            match otherwise {
+                Some(otherwise) => get_flags(inception, otherwise, flags),
+                None => flags,
+            }
+        },
+    })
+}
+
 fn quote_run(
     inception: &proc_macro2::TokenStream,
     item: &ScheduleItem,
@@ -91,28 +179,65 @@ fn quote_run(
     match item {
         ScheduleItem::ScheduleName(_) => token_stream,
         ScheduleItem::Stage(s) => {
-            let name = &s.stage;
+            let index = if is_last(s) {
+                quote! {#inception::LastStage}
+            } else {
+                quote! {<#s as #inception::StageLabel>::Index}
+            };
             quote! {
                 #token_stream
-                self.#name.run(world);
+                self.stages.run_stage::<#index>(world);
             }
         },
-        ScheduleItem::Looped(looped) => {
-            let mut acc = proc_macro2::TokenStream::default();
-            for item in &looped.items {
-                acc = quote_run(inception, item, acc);
+        ScheduleItem::Looped { flag, schedule } => {
+            let flag = match flag {
+                Some(flag) => quote! {#flag},
+                None => quote! {#inception::resources::Break},
+            };
+            let body = quote_schedule_run(inception, schedule);
+            quote! {
+                #token_stream
+                while !#inception::resources::ScheduleFlag::is_set(world.resource::<#flag>()) {
+                    world.resource_mut::<#inception::resources::ChangeTick>().0.increment();
+                    #body
+                }
+                #inception::resources::ScheduleFlag::reset(world.resource_mut::<#flag>());
             }
+        },
+        ScheduleItem::Branch {
+            negate,
+            flag,
+            then,
+            otherwise,
+        } => {
+            let negate = negate.then(|| quote! {!});
+            let then = quote_schedule_run(inception, then);
+            let otherwise = otherwise
+                .as_ref()
+                .map(|x| quote_schedule_run(inception, x))
+                .map(|x| quote! {else { #x }});
             quote! {
                 #token_stream
-                while !world.resource_mut::<#inception::resources::Break>().0 {
-                    #acc
+                if #negate #inception::resources::ScheduleFlag::is_set(world.resource::<#flag>()) {
+                    #then
                 }
-                world.resource_mut::<#inception::resources::Break>().0 = false;
+                #otherwise
             }
         },
     }
 }
 
+fn quote_schedule_run(
+    inception: &proc_macro2::TokenStream,
+    schedule: &Schedule,
+) -> proc_macro2::TokenStream {
+    let mut acc = proc_macro2::TokenStream::default();
+    for item in &schedule.items {
+        acc = quote_run(inception, item, acc);
+    }
+    acc
+}
+
 pub fn schedule(input: TokenStream) -> TokenStream {
     let inception = macros_util::crate_name("inception");
     let mut docs_string = input.to_string();
@@ -133,167 +258,144 @@ pub fn schedule(input: TokenStream) -> TokenStream {
     };
     let mut stages = Vec::new();
     let mut schedule_name = syn::Ident::new("Schedule", Span::call_site());
-    get_stages(&input, &mut schedule_name, &mut stages);
+    get_stages(&inception, &input, &mut schedule_name, &mut stages);
     let schedule_builder = format_ident!("{}Builder", schedule_name);
-    let generic_stages = stages.iter().map(|x| &x.generic_type).collect::<Vec<_>>();
-    let mut run = proc_macro2::TokenStream::default();
-    for item in &input.items {
-        run = quote_run(&inception, item, run);
+    let run = quote_schedule_run(&inception, &input);
+    let flags = get_flags(&inception, &input, quote! {()});
+    let n_last = stages.iter().filter(|x| is_last(&x.stage)).count();
+    if n_last > 1 {
+        panic!(
+            "Schedule `{}` has more than one `Last` stage.",
+            schedule_name
+        );
     }
-    let default_stages_ty = stages
-        .iter()
-        .map(|_| quote! {#inception::StackedNest})
-        .collect::<Vec<_>>();
-    let default_stages_field = stages.iter().map(|x| {
-        let name = &x.stage;
-        quote! {#name: #inception::StackedNest}
-    });
-    let struct_fields = stages
-        .iter()
-        .map(|x| {
-            let name = &x.stage;
-            let generic_type = &x.generic_type;
-            quote! {
-                #name: #generic_type
+    let has_last = n_last == 1;
+    let (mut default_stages_ty, mut default_stages) = if n_last == 0 {
+        (
+            quote! {#inception::EmptyStageList},
+            quote! {#inception::EmptyStageList},
+        )
+    } else {
+        (
+            quote! {#inception::LastStageList<#inception::StackedNest>},
+            quote! {#inception::LastStageList(#inception::StackedNest)},
+        )
+    };
+    for _ in stages.iter().filter(|x| !is_last(&x.stage)) {
+        default_stages_ty = quote! {
+            #inception::StageList<#inception::StackedNest, #default_stages_ty>
+        };
+        default_stages = quote! {
+            #inception::StageList {
+                head: #inception::StackedNest,
+                tail: #default_stages,
             }
-        })
-        .collect::<Vec<_>>();
-    let build_stages_fields = stages.iter().map(|x| {
-        let name = &x.stage;
+        };
+    }
+    let describe = stages.iter().map(|x| {
+        let stage = &x.stage;
+        let index = &x.index;
         quote! {
-            #name: self.#name.build_stage(world),
+            visitor.visit_stage(stringify!(#stage));
+            self.stages.describe_stage::<#index, V>(visitor);
         }
     });
-
-    let blanked_impls = (0..32)
-        .filter(|x| !stages.iter().any(|s| s.id == *x))
-        .map(|x| {
-            let f = format_ident!("add_system_to_stage{}", x);
-            let ret = format_ident!("AddSystemToStage{}", x);
-            let stage_ident = format_ident!("Stage{}", x);
-            quote! {
-                type #ret<System: #inception::SystemBuilder<'static, 'static> + 'static> = Self;
-
-                fn #f<System>(
-                    self,
-                    system: System,
-                ) -> Self::#ret<System>
-                where
-                    System: #inception::SystemBuilder<'static, 'static> + 'static
-                {
-                    panic!(
-                        "Schedule `{}` does not have a stage `{}`.",
-                        stringify!(#schedule_name),
-                        stringify!(#stage_ident)
-                    );
-                }
-            }
-        });
-
-    let add_system_to_stage_impls = stages.iter().map(|x| {
-        let stage_ident = &x.stage;
-        let f = format_ident!("add_system_to_stage{}", x.id);
-        let ret = format_ident!("AddSystemToStage{}", x.id);
-        let pass_fields = stages.iter().filter(|stage| stage.id != x.id).map(|stage| {
-            let name = &stage.stage;
-            quote! {
-                #name: self.#name
-            }
-        });
-        let output_generics = stages.iter().map(|stage| {
-            let ty = &stage.generic_type;
-            if stage.id == x.id {
-                quote! {
-                    #inception::Nested<#ty, #inception::AddSystemToStageCommand<System>>
-                }
-            } else {
-                quote! {
-                    #ty
-                }
-            }
-        });
+    let labels = stages.iter().filter(|x| !is_last(&x.stage)).map(|x| {
+        let stage = &x.stage;
+        let index = &x.index;
+        let doc = format!("Stage of `{}`.", schedule_name);
         quote! {
-            type #ret<System: #inception::SystemBuilder<'static, 'static> + 'static> =
-                #schedule_builder<#(#output_generics),*>;
+            #[doc = #doc]
+            pub struct #stage;
 
-            #[inline(always)]
-            fn #f<System>(
-                self,
-                system: System,
-            ) -> Self::#ret<System>
-            where
-                System: #inception::SystemBuilder<'static, 'static> + 'static
-            {
-                #schedule_builder {
-                    #(#pass_fields,)*
-                    #stage_ident: #inception::Nested {
-                        item: #inception::AddSystemToStageCommand {
-                            builder: system,
-                        },
-                        inner: self.#stage_ident,
-                    }
+            impl #stage {
+                #[inline(always)]
+                pub fn new() -> Self {
+                    #stage
                 }
             }
-        }
-    });
 
-    let assert_ty_eq = stages.iter().map(|x| {
-        let stage = &x.stage;
-        let alias = &x.alias;
-        quote! {
-            inception::static_assertions::assert_type_eq_all!(#stage, #alias);
+            impl #inception::StageLabel for #stage {
+                type Index = #index;
+                type Schedule = #schedule_name<()>;
+            }
         }
     });
 
     let out = quote! {
-        #(#assert_ty_eq)*
-        #[allow(non_snake_case)]
-        pub struct #schedule_builder<#(#generic_stages),*> {
-            #(#struct_fields),*
+        #(#labels)*
+
+        pub struct #schedule_builder<L> {
+            stages: L,
         }
 
-        #[allow(non_snake_case)]
         #docs
-        pub struct #schedule_name<#(#generic_stages),*> {
-            #(#struct_fields),*
+        pub struct #schedule_name<L> {
+            stages: L,
         }
 
-        impl #schedule_name<#(#default_stages_ty),*> {
+        impl #schedule_name<#default_stages_ty> {
             #[inline(always)]
-            pub fn builder() -> #schedule_builder<#(#default_stages_ty),*> {
+            pub fn builder() -> #schedule_builder<#default_stages_ty> {
                 #schedule_builder {
-                    #(#default_stages_field),*
+                    stages: #default_stages,
                 }
             }
         }
-        impl<#(#generic_stages: #inception::StageBuilder),*> #inception::ScheduleBuilderTrait for
-            #schedule_builder<#(#generic_stages),*> {
-            #(#add_system_to_stage_impls)*
-            #(#blanked_impls)*
+
+        impl<L: #inception::StageListBuilder> #inception::ScheduleBuilderTrait for
+            #schedule_builder<L> {
+            type AddSystemToStage<
+                I: #inception::StageIndex,
+                System: #inception::SystemBuilder<'static, 'static> + 'static,
+            > = #schedule_builder<L::AddSystem<I, System>>;
+            type Schedule = #schedule_name<()>;
+
+            const HAS_LAST: bool = #has_last;
+
+            #[inline(always)]
+            fn add_system_to_stage<I, System>(
+                self,
+                system: System,
+            ) -> Self::AddSystemToStage<I, System>
+            where
+                I: #inception::StageIndex,
+                System: #inception::SystemBuilder<'static, 'static> + 'static
+            {
+                #schedule_builder {
+                    stages: self.stages.add_system::<I, System>(system),
+                }
+            }
         }
 
-        impl<#(#generic_stages: #inception::StageBuilder),*> #inception::StageBuilder for
-            #schedule_builder<#(#generic_stages),*> {
-            type BuildStage<W: #inception::World, const N_EVENTS: usize> = #schedule_name<
-                #(<#generic_stages as #inception::StageBuilder>::BuildStage<W, N_EVENTS>),*
-            >;
+        impl<L: #inception::StageListBuilder> #inception::StageBuilder for #schedule_builder<L> {
+            type BuildStage<W: #inception::World, const N_EVENTS: usize> =
+                #schedule_name<L::Build<W, N_EVENTS>>;
+
             #[inline(always)]
             fn build_stage<W: #inception::World, const N_EVENTS: usize>(
                 self,
                 world: &mut W,
             ) -> Self::BuildStage<W, N_EVENTS> {
+                #[allow(clippy::let_unit_value)]
+                let () = #inception::ScheduleFlagCheck::<W, #flags, #schedule_name<()>>::CHECK;
                 #schedule_name {
-                    #(#build_stages_fields)*
+                    stages: self.stages.build(world),
                 }
             }
         }
 
-        impl<W: #inception::World, #(#generic_stages: #inception::Stage<W>),*> Stage<W> for
-            #schedule_name<#(#generic_stages),*> {
+        impl<W: #inception::World, L: #inception::StageListRun<W>> #inception::Stage<W> for
+            #schedule_name<L> {
             #[inline(always)]
             fn run(&mut self, world: &mut W) {
                 #run
             }
+
+            #[inline(always)]
+            fn describe<V: #inception::EcsVisitor>(&self, visitor: &mut V) {
+                #(#describe)*
+            }
         }
     };
     // eprintln!("{}", out.to_string());
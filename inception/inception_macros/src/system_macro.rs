@@ -1,6 +1,6 @@
 use inception_macros_core::*;
 use proc_macro::TokenStream;
-use proc_macro2::Span;
+use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{format_ident, quote};
 use syn::{
     parse_macro_input, parse_quote, punctuated::Punctuated, FnArg, GenericArgument, GenericParam,
@@ -29,7 +29,7 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
         .iter()
         .map(|x| {
             if let FnArg::Typed(x) = x {
-                &x.ty
+                &*x.ty
             } else {
                 panic!("system functions cannot have a self argument");
             }
@@ -81,11 +81,17 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
             syn::GenericParam::Const(x) => &x.ident,
         })
         .collect::<Vec<_>>();
-    let turbofish = input.sig.generics.params.iter().filter_map(|x| match x {
-        GenericParam::Type(x) => Some(&x.ident),
-        GenericParam::Const(x) => Some(&x.ident),
-        _ => None,
-    });
+    let turbofish = input
+        .sig
+        .generics
+        .params
+        .iter()
+        .filter_map(|x| match x {
+            GenericParam::Type(x) => Some(&x.ident),
+            GenericParam::Const(x) => Some(&x.ident),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
     let param_names = input
         .sig
         .inputs
@@ -104,18 +110,6 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
             },
         })
         .collect::<Vec<_>>();
-    let param_names_str = input.sig.inputs.iter().enumerate().map(|(i, x)| match x {
-        FnArg::Receiver(_) => panic!("system functions cannot have a self argument"),
-        FnArg::Typed(x) => match &*x.pat {
-            syn::Pat::Ident(x) => {
-                let name = &x.ident;
-                quote! {
-                    #i => Some(core::any::type_name::<#name>())
-                }
-            },
-            _ => panic!("Only `name: Type` is supported"),
-        },
-    });
     let system_inputs = input
         .sig
         .inputs
@@ -210,20 +204,24 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
         }
     });
     let create_input_item = system_inputs.iter().map(|x| {
-        let param_id = x.0;
+        let param_name = &param_names[x.0];
         let field = &x.1;
         quote! {
             #inception::InputItem {
                 data: Some(self.#field),
-                param_name: core::marker::PhantomData::<<Self as inception::Mapper::<#param_id>>::Name>,
+                param_name: core::marker::PhantomData::<#param_name>,
             }
         }
     });
-    let impl_mapper = (0..16).map(|param_id| {
-        let param_name = match param_names.get(param_id) {
-            Some(name) => quote! {#name},
-            None => quote! {()},
-        };
+    let params = chunk_params(param_names.len());
+    let params_ty = params_type(&params, &expanded_args, &inception, &quote! {});
+    let def_params_ty = params_type(&params, &expanded_args, &inception, &quote! { super:: });
+    let param_names_str = names_of(&params, &param_names).map(|(i, name)| {
+        quote! {
+            #i => Some(core::any::type_name::<#name>())
+        }
+    });
+    let impl_mapper = mapped_names(&params, &param_names).map(|(param_id, param_name)| {
         quote! {
             #[automatically_derived]
             impl<#(#struct_generics),*> #inception::Mapper<#param_id> for
@@ -235,6 +233,127 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
             }
         }
     });
+    let mut chunks = Vec::new();
+    collect_chunks(&params, &mut chunks);
+    let impl_chunks = chunks.iter().map(|(chunk_id, children)| {
+        let chunk = format_ident!("Chunk{}", chunk_id);
+        let mapped = mapped_names(children, &param_names).map(|(param_id, param_name)| {
+            quote! {
+                impl #inception::Mapper<#param_id> for #chunk {
+                    type Name = #param_name;
+                }
+            }
+        });
+        let names = names_of(children, &param_names).map(|(i, name)| {
+            quote! {
+                #i => Some(core::any::type_name::<#name>())
+            }
+        });
+        quote! {
+            /// Names of the params in a chunk of the system, see `ParamChunk`.
+            pub struct #chunk;
+            #(#mapped)*
+            #[automatically_derived]
+            impl #inception::SystemParamNameMapper for #chunk {
+                #[inline(always)]
+                fn get_param_name<const PARAM_ID: usize>() -> Option<&'static str> {
+                    match PARAM_ID {
+                        #(#names,)*
+                        _ => None,
+                    }
+                }
+            }
+        }
+    });
+    // Systems with chunked params are called through a function that takes the chunks.
+    let (call_fn, chunks_fn) = if chunks.is_empty() {
+        (fn_name.clone(), quote! {})
+    } else {
+        let mut sig = input.sig.clone();
+        sig.ident = format_ident!("{}_chunks", fn_name);
+        let inputs = params.iter().map(|x| {
+            let pat = param_pattern(x, &param_names, &inception);
+            let ty = param_type(x, &expanded_args, &inception, &quote! { super:: });
+            quote! { #pat: #ty }
+        });
+        sig.inputs = parse_quote! { #(#inputs),* };
+        let call_fn = sig.ident.clone();
+        (
+            call_fn,
+            quote! {
+                #[allow(clippy::too_many_arguments)]
+                #[inline(always)]
+                #sig {
+                    #fn_name::<#(#turbofish),*>(#(#param_names),*)
+                }
+            },
+        )
+    };
+    // Only systems with a `Query` are generic over the world, see `expand_inputs`.
+    let has_query = input.sig.inputs.iter().any(|x| match x {
+        FnArg::Typed(x) => matches!(
+            &*x.ty,
+            Type::Path(path) if path.path.segments.last().map_or(false, |x| x.ident == "Query")
+        ),
+        FnArg::Receiver(_) => false,
+    });
+    let (world_param, world_arg, world_dummy) = if has_query {
+        (
+            quote! { W: #inception::World + 'static, },
+            quote! { W, },
+            quote! { #inception::BasicWorld, },
+        )
+    } else {
+        (quote! {}, quote! {}, quote! {})
+    };
+    // Systems that return `bool` can be used as run conditions.
+    let is_condition = matches!(
+        output,
+        syn::ReturnType::Type(_, ty) if matches!(&**ty, Type::Path(x) if x.path.is_ident("bool"))
+    );
+    let condition_builder = if is_condition {
+        quote! {
+            #[automatically_derived]
+            impl<'w, 's, #(#struct_generics),*> #inception::RunConditionBuilder<'w, 's> for
+                System<#(#struct_type_generics),*>
+            where
+                #(#where_clause_predicates),*
+            {
+                type Condition<W: #inception::World, const N: usize> =
+                    self::def::System<'w, 's, #(#struct_type_generics,)* #world_arg N>;
+
+                #[inline(always)]
+                fn build_condition<W: #inception::World, const N: usize>(
+                    self,
+                    world: &mut W,
+                ) -> Self::Condition<W, N> {
+                    #inception::SystemBuilder::<'w, 's>::build(self, world)
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+    let condition = if is_condition {
+        quote! {
+            impl<'w, 's, #(#struct_generics,)* W: World, const N: usize> inception::RunCondition<'w, 's, W> for
+                System<'w, 's, #(#struct_type_generics,)* #world_arg N>
+            where
+                #(#where_clause_predicates),*
+            {
+                #[inline(always)]
+                fn check(&'s mut self, world: &'w mut W) -> bool {
+                    self.state
+                        .check::<_, super::System<#(#struct_type_generics),*>, _>(
+                        &mut #call_fn::<#(#turbofish),*>,
+                        world
+                    )
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
     // `SystemLabel` is disabled for generic systems.
     let system_label = if struct_type_generics.is_empty() {
         quote! {
@@ -253,6 +372,9 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
             use inception::*;
 
             pub struct System<#(#struct_type_generics),*> #struct_body
+            /// Label of the system that is used to order it inside of a stage.
+            pub struct Label;
+            impl #inception::SystemLabel for Label {}
             #(
                 pub struct #param_names;
                 impl #inception::ParamLabel for #param_names {
@@ -260,6 +382,7 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
                 }
             )*
 
+            #[allow(clippy::too_many_arguments)]
             #[inline(always)]
             pub fn new<#(#struct_generics),*>(#(#new_inputs),*) -> System<#(#struct_type_generics),*>
             where
@@ -278,7 +401,11 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
                 #(#where_clause_predicates),*
             {
                 type System<W: #inception::World, const N: usize> =
-                    self::def::System<'w, 's, #(#struct_type_generics,)* N>;
+                    self::def::System<'w, 's, #(#struct_type_generics,)* #world_arg N>;
+
+                const LABELS: #inception::Labels =
+                    #inception::Labels::EMPTY.push(<Label as #inception::SystemLabel>::NAME);
+                const NAME: &'static str = module_path!();
 
                 #[inline(always)]
                 fn build<W: #inception::World, const N: usize>(
@@ -286,6 +413,8 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
                     ___build_world: &mut W,
                 ) -> Self::System<W, N> {
                     use #inception::Nestable;
+                    #[allow(clippy::let_unit_value)]
+                    let () = #inception::ResourceCheck::<W, #params_ty, Self>::CHECK;
                     let mut ___build_inputs = #inception::StackedNest;
                     #(
                         let mut ___build_inputs = ___build_inputs.push(#create_input_item);
@@ -306,6 +435,9 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
             where
                 #(#where_clause_predicates),*
             {
+                const IS_QUERY: bool =
+                    self::def::System::<'static, 'static, #(#struct_type_generics,)* #world_dummy 0>::IS_QUERY;
+
                 #[inline(always)]
                 fn get_param_name<const PARAM_ID: usize>() -> Option<&'static str> {
                     match PARAM_ID {
@@ -317,19 +449,31 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
 
             #(#impl_mapper)*
 
+            #(#impl_chunks)*
+
+            #condition_builder
+
             // Creating another module to avoid param name conflicts with their type names.
             mod def {
                 use inception::*;
                 use super::super::*;
-                pub struct System<'w, 's, #(#struct_generics,)* const N: usize>
+                pub struct System<'w, 's, #(#struct_generics,)* #world_param const N: usize>
                 where
                     #(#where_clause_predicates),*
                 {
-                    pub(super) state: SystemState<(#(#expanded_args,)*)>,
+                    pub(super) state: SystemState<#def_params_ty>,
                     pub(super) _p: PhantomSystemParam<'w, 's, N>,
                 }
+                impl<'w, 's, #(#struct_generics,)* #world_param const N: usize>
+                    System<'w, 's, #(#struct_type_generics,)* #world_arg N>
+                where
+                    #(#where_clause_predicates),*
+                {
+                    pub(super) const IS_QUERY: bool =
+                        <#def_params_ty as SystemParam>::IS_QUERY;
+                }
                 impl<'w, 's, #(#struct_generics,)* W: World, const N: usize> inception::System<'w, 's, W> for
-                    System<'w, 's, #(#struct_type_generics,)* N>
+                    System<'w, 's, #(#struct_type_generics,)* #world_arg N>
                 where
                     #(#where_clause_predicates),*
                 {
@@ -337,23 +481,25 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
                     fn call(&'s mut self, world: &'w mut W) {
                         self.state
                             .call::<_, super::System<#(#struct_type_generics),*>, _>(
-                            &mut #fn_name::<#(#turbofish),*>,
+                            &mut #call_fn::<#(#turbofish),*>,
                             world
                         );
                     }
                 }
 
-                impl<'w, 's, #(#struct_generics,)* const N: usize> SystemParamPlugin for
-                    System<'w, 's, #(#struct_type_generics,)* N>
+                #condition
+
+                impl<'w, 's, #(#struct_generics,)* #world_param const N: usize> SystemParamPlugin for
+                    System<'w, 's, #(#struct_type_generics,)* #world_arg N>
                 where
                     #(#where_clause_predicates),*
                 {
                     type Build<B: EcsBuilder> =
-                        <(#(#expanded_args,)*) as SystemParam>::Build<B, super::System<#(#struct_type_generics),*>, ()>;
+                        <#def_params_ty as SystemParam>::Build<B, super::System<#(#struct_type_generics),*>, ()>;
 
                     #[inline(always)]
                     fn build<B: EcsBuilder>(mut builder: B) -> Self::Build<B> {
-                        <(#(#expanded_args,)*) as SystemParam>::build::<
+                        <#def_params_ty as SystemParam>::build::<
                             B,
                             super::System<#(#struct_type_generics),*>,
                             (),
@@ -363,6 +509,8 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
                 #[allow(clippy::too_many_arguments)]
                 #[inline(always)]
                 #input
+
+                #chunks_fn
             }
 
         }
@@ -371,6 +519,115 @@ pub fn system(attributes: TokenStream, item: TokenStream) -> TokenStream {
     output.into()
 }
 
+/// Param of a system, or a chunk of params when the system has more than `MAX_SYSTEM_PARAMS`
+/// params.
+enum ParamNode {
+    Param(usize),
+    Chunk(usize, Vec<ParamNode>),
+}
+
+/// Groups `n` params into nested chunks until there are at most `MAX_SYSTEM_PARAMS` at each level.
+fn chunk_params(n: usize) -> Vec<ParamNode> {
+    let mut nodes = (0..n).map(ParamNode::Param).collect::<Vec<_>>();
+    let mut n_chunks = 0;
+    while nodes.len() > MAX_SYSTEM_PARAMS {
+        let mut chunks = Vec::new();
+        let mut nodes_iter = nodes.into_iter().peekable();
+        while nodes_iter.peek().is_some() {
+            let children = nodes_iter.by_ref().take(MAX_SYSTEM_PARAMS).collect();
+            chunks.push(ParamNode::Chunk(n_chunks, children));
+            n_chunks += 1;
+        }
+        nodes = chunks;
+    }
+    nodes
+}
+
+fn collect_chunks<'a>(nodes: &'a [ParamNode], chunks: &mut Vec<(usize, &'a [ParamNode])>) {
+    for node in nodes {
+        if let ParamNode::Chunk(chunk_id, children) = node {
+            chunks.push((*chunk_id, children));
+            collect_chunks(children, chunks);
+        }
+    }
+}
+
+/// Names of the params at each position of `nodes`.
+fn names_of<'a>(
+    nodes: &'a [ParamNode],
+    param_names: &'a [TokenStream2],
+) -> impl Iterator<Item = (usize, &'a TokenStream2)> {
+    nodes
+        .iter()
+        .enumerate()
+        .filter_map(move |(i, node)| match node {
+            ParamNode::Param(param) => Some((i, &param_names[*param])),
+            ParamNode::Chunk(..) => None,
+        })
+}
+
+/// `Mapper::Name` of all param ids for `nodes`. Chunks and unused ids are mapped to `()`.
+fn mapped_names<'a>(
+    nodes: &'a [ParamNode],
+    param_names: &'a [TokenStream2],
+) -> impl Iterator<Item = (usize, TokenStream2)> + 'a {
+    (0..MAX_SYSTEM_PARAMS).map(move |param_id| match nodes.get(param_id) {
+        Some(ParamNode::Param(param)) => {
+            let name = &param_names[*param];
+            (param_id, quote! { #name })
+        },
+        _ => (param_id, quote! { () }),
+    })
+}
+
+fn param_type(
+    node: &ParamNode,
+    types: &[&Type],
+    inception: &TokenStream2,
+    chunk_path: &TokenStream2,
+) -> TokenStream2 {
+    match node {
+        ParamNode::Param(param) => {
+            let ty = types[*param];
+            quote! { #ty }
+        },
+        ParamNode::Chunk(chunk_id, children) => {
+            let chunk = format_ident!("Chunk{}", chunk_id);
+            let children = params_type(children, types, inception, chunk_path);
+            quote! { #inception::ParamChunk<#children, #chunk_path #chunk> }
+        },
+    }
+}
+
+/// Tuple of system params, with the types of chunks prefixed by `chunk_path`.
+fn params_type(
+    nodes: &[ParamNode],
+    types: &[&Type],
+    inception: &TokenStream2,
+    chunk_path: &TokenStream2,
+) -> TokenStream2 {
+    let types = nodes
+        .iter()
+        .map(|x| param_type(x, types, inception, chunk_path));
+    quote! { (#(#types,)*) }
+}
+
+fn param_pattern(
+    node: &ParamNode,
+    param_names: &[TokenStream2],
+    inception: &TokenStream2,
+) -> TokenStream2 {
+    match node {
+        ParamNode::Param(param) => param_names[*param].clone(),
+        ParamNode::Chunk(_, children) => {
+            let children = children
+                .iter()
+                .map(|x| param_pattern(x, param_names, inception));
+            quote! { #inception::ParamChunk((#(#children,)*), _) }
+        },
+    }
+}
+
 fn is_world(bounds: &Punctuated<TypeParamBound, Token![+]>) -> bool {
     bounds.iter().any(|x| match x {
         syn::TypeParamBound::Trait(x) => x
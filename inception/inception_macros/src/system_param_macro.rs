@@ -86,11 +86,15 @@ pub fn system_param(_attributes: TokenStream, input: TokenStream) -> TokenStream
         let ty = &x.ty;
         quote! { <#ty as SystemParam>::IS_QUERY}
     });
-    let get = fields.iter().enumerate().map(|(_i, x)| {
+    // Each field gets its own param name, so that fields of the same type have separate state.
+    let field_names = (0..fields.len())
+        .map(|i| quote! { #inception::FieldName<ParamName, #i> })
+        .collect::<Vec<_>>();
+    let get = fields.iter().zip(&field_names).map(|(x, field_name)| {
         let ident = &x.ident;
         let ty = &x.ty;
         quote! {
-            let #ident = <#ty>::get_param_for_entity::<Wrld, SB, ParamName, E>(
+            let #ident = <#ty>::get_param_for_entity::<Wrld, SB, #field_name, E>(
                 &mut *__entity,
                 &mut __state.#ident,
                 &mut *__world,
@@ -121,11 +125,11 @@ pub fn system_param(_attributes: TokenStream, input: TokenStream) -> TokenStream
         }
     };
 
-    let get = fields.iter().enumerate().map(|(_i, x)| {
+    let get = fields.iter().zip(&field_names).map(|(x, field_name)| {
         let ident = &x.ident;
         let ty = &x.ty;
         quote! {
-            let #ident = <#ty>::get_param::<Wrld, SB, ParamName>(
+            let #ident = <#ty>::get_param::<Wrld, SB, #field_name>(
                 &mut __state.#ident,
                 &mut *__world
             );
@@ -149,11 +153,15 @@ pub fn system_param(_attributes: TokenStream, input: TokenStream) -> TokenStream
             }
         }
     };
-    let builds = fields.iter().map(|x| {
+    let resources = fields.iter().rev().fold(quote! { () }, |resources, x| {
+        let ty = &x.ty;
+        quote! { (<#ty as SystemParam>::Resources, #resources) }
+    });
+    let builds = fields.iter().zip(&field_names).map(|(x, field_name)| {
         let ty = &x.ty;
         quote! {
             let mut builder =
-                <#ty as SystemParam>::build::<_, SB, ParamName>(builder);
+                <#ty as SystemParam>::build::<_, SB, #field_name>(builder);
         }
     });
     let mut renamed_generics = input.generics.clone();
@@ -187,12 +195,17 @@ pub fn system_param(_attributes: TokenStream, input: TokenStream) -> TokenStream
             #ident: #state
         }
     });
-    let state_inits = fields.iter().zip(&states).map(|(field, state)| {
-        let ident = &field.ident;
-        quote! {
-            let #ident = #state::init::<W, SB, ParamName, I>(inputs, &mut *world);
-        }
-    });
+    let state_inits =
+        fields
+            .iter()
+            .zip(&states)
+            .zip(&field_names)
+            .map(|((field, state), field_name)| {
+                let ident = &field.ident;
+                quote! {
+                    let #ident = #state::init::<W, SB, #field_name, I>(inputs, &mut *world);
+                }
+            });
     let vis = &input.vis;
     let state_struct = format_ident!("{}State", struct_name);
     let (impl_generics, state_ty_generics, where_clause) = state_generics.split_for_impl();
@@ -225,6 +238,7 @@ pub fn system_param(_attributes: TokenStream, input: TokenStream) -> TokenStream
             // cast lifetimes
             type Item<'world, 'state, Wrld: World> = #struct_name #renamed_ty_generics;
             type State = #state_struct #state_ty_generics;
+            type Resources = #resources;
 
             type Build<B: EcsBuilder, SB: SystemParamNameMapper + 'static, ParamName: 'static> =
                 impl EcsBuilder;
@@ -8,6 +8,12 @@ use syn::{
     PathArguments, Token, TypeParam, TypeParamBound, *,
 };
 
+/// Maximum number of parameters in a tuple of system parameters. `#[system]` groups the
+/// parameters of larger systems into nested `ParamChunk`s. Everything that depends on it is
+/// generated from it, see `inception_macros::all_system_params` and
+/// `inception_macros::all_param_ids`.
+pub const MAX_SYSTEM_PARAMS: usize = 16;
+
 pub fn is_lifetime_w(arg: &GenericArgument) -> bool {
     if let syn::GenericArgument::Lifetime(x) = arg {
         x.ident == "'w"
@@ -245,6 +251,20 @@ pub fn expand_inputs_with_names<'a>(
                                 add_lifetimes(x, world_lifetime);
                             }
                         },
+                        // e.g. `Option<&T>`
+                        Type::Path(x) => {
+                            for segment in &mut x.path.segments {
+                                let args = match &mut segment.arguments {
+                                    PathArguments::AngleBracketed(args) => args,
+                                    _ => continue,
+                                };
+                                for arg in &mut args.args {
+                                    if let GenericArgument::Type(x) = arg {
+                                        add_lifetimes(x, world_lifetime);
+                                    }
+                                }
+                            }
+                        },
                         _ => {},
                     }
                 }
@@ -263,6 +283,17 @@ pub fn expand_inputs_with_names<'a>(
             },
             syn::Type::Path(path) => {
                 let segment = path.path.segments.last_mut().unwrap();
+                // `Option<Res<T>>` is a parameter, generics are added to the inner type.
+                if segment.ident == "Option" {
+                    if let PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                        for arg in &mut args.args {
+                            if let GenericArgument::Type(x) = arg {
+                                process_type(x, world_lifetime, state_lifetime, world_name, n_name);
+                            }
+                        }
+                    }
+                    return;
+                }
                 // W is only added to `Query` other parameters don't need it. It bloats
                 // compile times a lot to the point where it is unusable. It would take 50
                 // seconds for incremental compilation and 12 GiB of RAM.
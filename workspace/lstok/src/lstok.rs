@@ -9,7 +9,7 @@ use bytemuck::offset_of;
 use esl::{
     block_relays::UpdateRelay,
     simulation_relays::SimulationEnding,
-    stages::{PostTrade0, Signal},
+    stages::{PostTrade, Signal},
     ta::rsi::{Rsi, RsiConfig, RsiState},
     *,
 };
@@ -44,27 +44,29 @@ pub fn run(hlcvs: &[Hlcv], timeframe_s: u32) -> f32 {
     let now = Instant::now();
     let schedule = esl::stages::BacktestSchedule::builder();
     let builder = EcsBuilderStruct::new::<_, 6>(schedule, EntitiesBuilderStruct1::new())
-        // Resources are like singletons in ECS. These ones may be needed. If a resource isn't
-        // present then the system will panic. If an entity doesn't have a component then a system
-        // will just skip it.
-        .add_resource(MetricsPtr(metrics.as_mut_ptr()))
-        .add_resource(AccountsPerThread(1))
-        .add_resource(ThreadsPerDevice(1))
-        .add_resource(ThreadId(0))
-        .add_resource(NSamples(input_len))
-        .add_resource(RiskFreeRate(0.))
-        .add_resource(TradingDaysPerYear(252.))
-        .add_plugin(CorePlugin {
-            loop_end_bound_excluded: input_len,
-        })
-        .add_plugin(HlcvBacktestPlugin {
-            timeframe_s,
-            backtest_plugin: BacktestPlugin {
-                inputs: unsafe { Series::<hlcv::HlcvInputNest>::new(hlcvs.as_ptr() as *const u8) },
-                starting_balance: 1.0,
-                slippage: types::Slippage::Relative(0.),
-                fee: types::Fee::RelativeToVolume(0.00075 * 2.),
-                inputs_marker: PhantomData::<hlcv::HlcvInput>,
+        // Plugins add resources (singletons in ECS) and systems of a backtest. If a resource
+        // isn't present then the system will panic. If an entity doesn't have a component then a
+        // system will just skip it.
+        .add_plugins(DefaultBacktestPlugins {
+            core: CorePlugin {
+                loop_start_bound: 0,
+                loop_end_bound_excluded: input_len,
+            },
+            metrics: MetricsPlugin::new(metrics.as_mut_ptr(), input_len),
+            backtest: HlcvBacktestPlugin {
+                timeframe_s,
+                funding_interval_s: 0,
+                backtest_plugin: BacktestPlugin {
+                    inputs: unsafe {
+                        Series::<hlcv::HlcvInputNest>::new(hlcvs.as_ptr() as *const u8)
+                    },
+                    starting_balance: 1.0,
+                    slippage: types::Slippage::Relative(0.),
+                    fee: types::Fee::RelativeToVolume(0.00075 * 2.),
+                    max_pyramiding: 1,
+                    margin: Default::default(),
+                    inputs_marker: PhantomData::<hlcv::HlcvInput>,
+                },
             },
         })
         .add_plugin(ComputeIndicatorPlugin::<